@@ -47,8 +47,15 @@ class t_rs_generator : public t_oop_generator {
                  const string& option_string)
     : t_oop_generator(program)
   {
-    (void) parsed_options;
     (void) option_string;
+    std::map<std::string, std::string>::const_iterator iter;
+
+    iter = parsed_options.find("preserve_unknown");
+    preserve_unknown_ = (iter != parsed_options.end());
+
+    iter = parsed_options.find("async");
+    async_ = (iter != parsed_options.end());
+
     // FIXME: change back to gen-rs when we finalize mod structure for generated code
     out_dir_base_ = "src";
   }
@@ -64,11 +71,23 @@ class t_rs_generator : public t_oop_generator {
   void generate_typedef(t_typedef*  ttypedef);
   void generate_enum(t_enum*     tenum);
   void generate_struct(t_struct*   tstruct);
+  void generate_union(t_struct*    tunion);
   void generate_service(t_service*  tservice);
   void generate_consts(std::vector<t_const*>);
   string render_const_value(t_type* type, t_const_value* value);
+  string render_field_default(t_field* tfield);
 
  private:
+  /**
+   * True to keep unknown fields of structs, so they are written again on encode.
+   */
+  bool preserve_unknown_;
+
+  /**
+   * True to also generate async traits, processors and clients for services.
+   */
+  bool async_;
+
   string rs_autogen_comment();
   string rs_imports();
 
@@ -272,7 +291,7 @@ string t_rs_generator::render_const_value(t_type* type, t_const_value* value) {
       out << '"' << get_escaped_string(value) << '"';
       break;
     case t_base_type::TYPE_BOOL:
-      out << (value->get_integer() > 0 ? "True" : "False");
+      out << (value->get_integer() > 0 ? "true" : "false");
       break;
     case t_base_type::TYPE_BYTE:
     case t_base_type::TYPE_I16:
@@ -292,7 +311,7 @@ string t_rs_generator::render_const_value(t_type* type, t_const_value* value) {
     }
   } else if (type->is_enum()) {
       // pull out the enum name and the enum const name
-      indent(out) << render_rs_type(type) << "::" << value->get_identifier_name();
+      out << render_rs_type(type) << "::" << capitalize(value->get_identifier_name());
   }
 #if 0
   // @todo: not working yet
@@ -370,6 +389,30 @@ string t_rs_generator::render_const_value(t_type* type, t_const_value* value) {
   return out.str();
 }
 
+/**
+ * Renders the IDL default value of a field as an expression of the field's
+ * Rust type, or returns an empty string if the type has no default support.
+ */
+string t_rs_generator::render_field_default(t_field* tfield) {
+  t_type* type = get_true_type(tfield->get_type());
+  if (!type->is_base_type() && !type->is_enum()) {
+    pwarning(1, "Rust generator ignores the default value of field %s\n", tfield->get_name().c_str());
+    return "";
+  }
+
+  string value = render_const_value(type, tfield->get_value());
+  if (is_string(type)) {
+    value = "String::from(" + value + ")";
+  } else if (is_binary(type)) {
+    value = "String::from(" + value + ").into_bytes()";
+  }
+
+  if (tfield->get_req() == t_field::T_OPTIONAL) {
+    value = "Some(" + value + ")";
+  }
+  return value;
+}
+
 void t_rs_generator::generate_consts(std::vector<t_const*> objects) {
     for (auto o_iter = objects.begin(); o_iter != objects.end(); ++o_iter) {
         string tdef = render_rs_type( (*o_iter)->get_type());
@@ -410,6 +453,11 @@ void t_rs_generator::generate_enum(t_enum* tenum) {
 
 // Generate a struct, translating a thrift struct into a rust struct.
 void t_rs_generator::generate_struct(t_struct* tstruct) {
+  if (tstruct->is_union()) {
+    generate_union(tstruct);
+    return;
+  }
+
   string sname = pascalcase(tstruct->get_name());
 
   indent(f_mod_) << "strukt! {\n";
@@ -422,9 +470,20 @@ void t_rs_generator::generate_struct(t_struct* tstruct) {
 
   vector<t_field*>::const_iterator m_iter;
   const vector<t_field*>& members = tstruct->get_members();
+  vector<string> required;
+  vector<string> defaults;
   for (m_iter = members.begin(); m_iter != members.end(); ++m_iter) {
     t_field* tfield = *m_iter;
     string type = render_rs_type(tfield->get_type());
+    if (tfield->get_req() == t_field::T_REQUIRED) {
+      required.push_back(to_field_name(tfield->get_name()));
+    }
+    if (tfield->get_value() != NULL) {
+      string value = render_field_default(tfield);
+      if (!value.empty()) {
+        defaults.push_back(to_field_name(tfield->get_name()) + ": " + value);
+      }
+    }
     // like the Java generator, "default" requiredness is treated as required
     if (tfield->get_req() == t_field::T_OPTIONAL) {
       type = "Option<" + type + ">";
@@ -435,18 +494,75 @@ void t_rs_generator::generate_struct(t_struct* tstruct) {
   }
 
   indent_down();
-  indent(f_mod_) << "}\n";
+  indent(f_mod_) << "}";
+
+  if (!defaults.empty()) {
+    f_mod_ << ",\n";
+    indent(f_mod_) << "defaults = {\n";
+    indent_up();
+    vector<string>::const_iterator d_iter;
+    for (d_iter = defaults.begin(); d_iter != defaults.end(); ++d_iter) {
+      indent(f_mod_) << *d_iter << ",\n";
+    }
+    indent_down();
+    indent(f_mod_) << "}";
+  }
+
+  if (!required.empty()) {
+    f_mod_ << ",\n";
+    indent(f_mod_) << "required = [";
+    vector<string>::const_iterator r_iter;
+    for (r_iter = required.begin(); r_iter != required.end(); ++r_iter) {
+      f_mod_ << *r_iter << ", ";
+    }
+    f_mod_ << "]";
+  }
+
+  if (preserve_unknown_) {
+    f_mod_ << ",\n";
+    indent(f_mod_) << "unknown_fields = unknown_fields";
+  }
+  f_mod_ << "\n";
 
   indent_down();
   indent(f_mod_) << "}\n\n"; // Close strukt invocation.
 }
 
+// Generate a union, translating a thrift union into a rust enum.
+void t_rs_generator::generate_union(t_struct* tunion) {
+  indent(f_mod_) << "unyon! {\n";
+  indent_up();
+
+  indent(f_mod_) << "name = " << pascalcase(tunion->get_name()) << ",\n";
+
+  indent(f_mod_) << "fields = {\n";
+  indent_up();
+
+  vector<t_field*>::const_iterator m_iter;
+  const vector<t_field*>& members = tunion->get_members();
+  for (m_iter = members.begin(); m_iter != members.end(); ++m_iter) {
+    t_field* tfield = *m_iter;
+    indent(f_mod_) << pascalcase(tfield->get_name())
+      << "(" << to_field_name(tfield->get_name())
+      << ": " << render_rs_type(tfield->get_type())
+      << " => " << tfield->get_key() << "),\n";
+  }
+
+  indent_down();
+  indent(f_mod_) << "}\n";
+
+  indent_down();
+  indent(f_mod_) << "}\n\n"; // Close unyon invocation.
+}
+
 // Generate a service, translating from a thrift service to a rust trait.
 void t_rs_generator::generate_service(t_service* tservice) {
     const string sname = pascalcase(tservice->get_name());
     const string trait_name = sname;
     const string processor_name = sname + "Processor";
     const string client_name = sname + "Client";
+    const string concurrent_client_name = sname + "ConcurrentClient";
+    const string async_trait_name = sname + "Async";
 
     indent(f_mod_) << "service! {\n";
     indent_up();
@@ -455,6 +571,12 @@ void t_rs_generator::generate_service(t_service* tservice) {
     indent(f_mod_) << "trait_name = " << trait_name << ",\n";
     indent(f_mod_) << "processor_name = " << processor_name << ",\n";
     indent(f_mod_) << "client_name = " << client_name << ",\n";
+    indent(f_mod_) << "concurrent_client_name = " << concurrent_client_name << ",\n";
+    if (async_) {
+      indent(f_mod_) << "async_trait_name = " << async_trait_name << ",\n";
+      indent(f_mod_) << "async_processor_name = " << async_trait_name << "Processor,\n";
+      indent(f_mod_) << "async_client_name = " << async_trait_name << "Client,\n";
+    }
 
     // The methods originating in this service to go in the service trait.
     indent(f_mod_) << "service_methods = [\n";
@@ -523,7 +645,8 @@ void t_rs_generator::generate_service_methods(char field, t_service* tservice) {
           rettype = "Result<" + rettype + ", " + errname + ">";
     }
 
-        indent(f_mod_) << "] (" << rettype << "),\n";
+        indent(f_mod_) << "] (" << rettype << ")"
+          << (tfunction->is_oneway() ? " oneway" : "") << ",\n";
     }
 }
 
@@ -643,5 +766,7 @@ string t_rs_generator::render_rs_type(t_type* type) {
   return ""; // silence the compiler warning
 }
 
-THRIFT_REGISTER_GENERATOR(rs, "Rust", "")
+THRIFT_REGISTER_GENERATOR(rs, "Rust",
+                          "    preserve_unknown: Keep unknown struct fields and write them back on encode.\n"
+                          "    async: Also generate async services, for the runtime's async feature.\n")
 
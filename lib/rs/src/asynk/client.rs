@@ -0,0 +1,100 @@
+use std::future::Future;
+use std::marker::PhantomData;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use protocol::{helpers, Decode, Encode, MessageType, Protocol};
+use transport::memory::MemoryTransport;
+use transport::slice::SliceTransport;
+use Result;
+use super::AsyncTransport;
+use super::frame::{FrameReader, FrameWriter};
+
+/// A call made by an async client, resolving to its translated result.
+///
+/// The call owns the connection until it completes. Dropping it halfway
+/// leaves its reply unread, which makes the next call on the connection
+/// fail with `Error::UnexpectedReply`.
+pub struct Call<'a, P: 'a, T: 'a, R, F> {
+    protocol: &'a mut P,
+    transport: &'a mut T,
+    name: &'static str,
+    seqid: i32,
+    oneway: bool,
+    state: State,
+    translate: Option<F>,
+    result: PhantomData<fn() -> R>,
+}
+
+enum State {
+    Failed(::Error),
+    Writing(FrameWriter),
+    Reading(FrameReader),
+    Done,
+}
+
+/// Sends the call `name` with `args`, and passes the result read from its
+/// reply to `translate`. Oneway calls don't wait for a reply, their result
+/// is the default one.
+pub fn call<'a, P, T, A, R, O, F>(protocol: &'a mut P, transport: &'a mut T, name: &'static str,
+                                  oneway: bool, seqid: i32, args: &A, translate: F) -> Call<'a, P, T, R, F>
+where P: Protocol, T: AsyncTransport, A: Encode, R: Decode, F: FnOnce(R) -> Result<O> {
+    let ty = if oneway { MessageType::Oneway } else { MessageType::Call };
+    let mut request = MemoryTransport::default();
+    let state = match helpers::send(protocol, &mut request, name, ty, args, seqid)
+                                   .and_then(|_| FrameWriter::new(request.written())) {
+        Ok(writer) => State::Writing(writer),
+        Err(e) => State::Failed(e),
+    };
+
+    Call {
+        protocol: protocol,
+        transport: transport,
+        name: name,
+        seqid: seqid,
+        oneway: oneway,
+        state: state,
+        translate: Some(translate),
+        result: PhantomData,
+    }
+}
+
+// No field is ever pinned.
+impl<'a, P, T, R, F> Unpin for Call<'a, P, T, R, F> {}
+
+impl<'a, P, T, R, O, F> Future for Call<'a, P, T, R, F>
+where P: Protocol, T: AsyncTransport, R: Decode, F: FnOnce(R) -> Result<O> {
+    type Output = Result<O>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<O>> {
+        let this = self.get_mut();
+        loop {
+            match this.state {
+                State::Failed(_) => {
+                    if let State::Failed(e) = mem::replace(&mut this.state, State::Done) {
+                        return Poll::Ready(Err(e));
+                    }
+                }
+                State::Writing(ref mut writer) => {
+                    try_ready!(writer.poll_write(cx, this.transport));
+                    if this.oneway {
+                        this.state = State::Done;
+                        return Poll::Ready(this.translate.take().unwrap()(R::default()));
+                    }
+                    this.state = State::Reading(FrameReader::new(this.protocol.limits().max_message_size));
+                }
+                State::Reading(ref mut reader) => {
+                    let frame = try_ready!(reader.poll_read(cx, this.transport));
+                    this.state = State::Done;
+
+                    let mut result = R::default();
+                    try_ready!(Poll::Ready(helpers::receive_reply(this.protocol, &mut SliceTransport::new(&frame),
+                                                                  this.name, this.seqid, &mut result)));
+                    return Poll::Ready(this.translate.take().unwrap()(result));
+                }
+                State::Done => panic!("call polled after completion"),
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::cmp;
+use std::io;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use protocol::{self, Limit};
+use {Error, Result};
+
+const HEADER_LEN: usize = 4;
+
+/// How much of a frame is read at once.
+const CHUNK_LEN: usize = 8 * 1024;
+
+/// Reads one frame after the other, across as many polls as it takes.
+///
+/// Like `FramedTransport`, the buffer grows as the body arrives rather than
+/// being allocated up front from the untrusted frame size.
+pub struct FrameReader {
+    header: [u8; HEADER_LEN],
+    /// Bytes of the header of the current frame read so far.
+    filled: usize,
+    /// The size of the current frame's body, once its header was read.
+    len: usize,
+    body: Vec<u8>,
+    max_size: usize,
+}
+
+impl FrameReader {
+    pub fn new(max_size: usize) -> FrameReader {
+        FrameReader { header: [0; HEADER_LEN], filled: 0, len: 0, body: Vec::new(), max_size: max_size }
+    }
+
+    /// Whether no part of the next frame has been read yet, so that the
+    /// peer closing the connection doesn't cut a message short.
+    pub fn is_empty(&self) -> bool {
+        self.filled == 0
+    }
+
+    pub fn poll_read<T>(&mut self, cx: &mut Context, transport: &mut T) -> Poll<Result<Vec<u8>>>
+    where T: AsyncRead + Unpin {
+        while self.filled < HEADER_LEN {
+            let n = try_ready!(poll_read_into(cx, transport, &mut self.header[self.filled..]));
+            self.filled += n;
+            if self.filled == HEADER_LEN {
+                let len = ((self.header[0] as i32) << 24) | ((self.header[1] as i32) << 16)
+                        | ((self.header[2] as i32) << 8) | (self.header[3] as i32);
+                if len < 0 {
+                    return Poll::Ready(Err(Error::from(protocol::Error::ProtocolViolation)));
+                } else if len as usize > self.max_size {
+                    return Poll::Ready(Err(Error::from(protocol::Error::LimitExceeded(Limit::MessageSize))));
+                }
+                self.len = len as usize;
+            }
+        }
+
+        while self.body.len() < self.len {
+            let mut chunk = [0; CHUNK_LEN];
+            let wanted = cmp::min(CHUNK_LEN, self.len - self.body.len());
+            let n = try_ready!(poll_read_into(cx, transport, &mut chunk[..wanted]));
+            self.body.extend_from_slice(&chunk[..n]);
+        }
+
+        self.filled = 0;
+        Poll::Ready(Ok(mem::replace(&mut self.body, Vec::new())))
+    }
+}
+
+/// Reads into `buf`, failing at the end of the input.
+fn poll_read_into<T>(cx: &mut Context, transport: &mut T, buf: &mut [u8]) -> Poll<io::Result<usize>>
+where T: AsyncRead + Unpin {
+    let mut buf = ReadBuf::new(buf);
+    try_ready!(Pin::new(transport).poll_read(cx, &mut buf));
+    match buf.filled().len() {
+        0 => Poll::Ready(Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"))),
+        n => Poll::Ready(Ok(n)),
+    }
+}
+
+/// Writes and flushes one frame, across as many polls as it takes.
+pub struct FrameWriter {
+    buf: Vec<u8>,
+    written: usize,
+}
+
+impl FrameWriter {
+    pub fn new(frame: &[u8]) -> Result<FrameWriter> {
+        if frame.len() > i32::max_value() as usize {
+            return Err(Error::from(protocol::Error::LimitExceeded(Limit::MessageSize)));
+        }
+        let len = frame.len() as u32;
+        let mut buf = Vec::with_capacity(HEADER_LEN + frame.len());
+        buf.extend_from_slice(&[(len >> 24) as u8, (len >> 16) as u8, (len >> 8) as u8, len as u8]);
+        buf.extend_from_slice(frame);
+        Ok(FrameWriter { buf: buf, written: 0 })
+    }
+
+    pub fn poll_write<T>(&mut self, cx: &mut Context, transport: &mut T) -> Poll<Result<()>>
+    where T: AsyncWrite + Unpin {
+        while self.written < self.buf.len() {
+            let n = try_ready!(Pin::new(&mut *transport).poll_write(cx, &self.buf[self.written..]));
+            if n == 0 {
+                return Poll::Ready(Err(Error::from(io::Error::new(io::ErrorKind::WriteZero, "failed to write frame"))));
+            }
+            self.written += n;
+        }
+        try_ready!(Pin::new(transport).poll_flush(cx));
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,54 @@
+//! Async clients and servers on top of tokio.
+//!
+//! Messages travel in frames, each prefixed with its length as a big-endian
+//! i32 like with the framed transports of other Thrift implementations.
+//! Only the frames are read and written asynchronously: their contents are
+//! encoded and decoded in memory with the usual `Protocol`s.
+//!
+//! `service!` generates an async handler trait, processor and client when
+//! given `async_trait_name`, `async_processor_name` and `async_client_name`.
+//! The handler trait has the methods of the service and its parents, each
+//! returning a `BoxFuture`.
+
+use std::future::{self, Future};
+use std::pin::Pin;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use transport::memory::MemoryTransport;
+
+/// Unwraps a `Poll<Result<T, E>>`, returning early unless it is a success.
+macro_rules! try_ready {
+    ($e:expr) => {
+        match $e {
+            ::std::task::Poll::Ready(Ok(v)) => v,
+            ::std::task::Poll::Ready(Err(e)) => return ::std::task::Poll::Ready(Err(From::from(e))),
+            ::std::task::Poll::Pending => return ::std::task::Poll::Pending,
+        }
+    }
+}
+
+mod frame;
+pub mod client;
+pub mod server;
+
+pub use self::client::{call, Call};
+pub use self::server::{reply, reply_exception, unknown_method, AsyncProcessor, AsyncServer};
+
+/// Connections the async clients and servers can run over.
+pub trait AsyncTransport: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> AsyncTransport for T {}
+
+pub type BoxFuture<'a, T> = Pin<Box<Future<Output = T> + Send + 'a>>;
+
+/// A future resolving to `value` right away.
+pub fn ready<T: Send + 'static>(value: T) -> BoxFuture<'static, T> {
+    Box::pin(future::ready(value))
+}
+
+/// What was written to `transport`, if anything.
+fn written(transport: MemoryTransport) -> Option<Vec<u8>> {
+    let written = transport.into_written();
+    if written.is_empty() { None } else { Some(written) }
+}
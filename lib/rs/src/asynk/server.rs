@@ -0,0 +1,218 @@
+use std::future::Future;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use tokio::{self, net::TcpListener};
+
+use exception::ApplicationException;
+use processor;
+use protocol::{helpers, Encode, MessageType, Protocol, ProtocolFactory};
+use transport::memory::MemoryTransport;
+use {Error, Result};
+use super::{written, AsyncTransport, BoxFuture};
+use super::frame::{FrameReader, FrameWriter};
+
+/// Implemented by the async processors generated with `service!`.
+pub trait AsyncProcessor {
+    /// Processes a request frame, resolving to the reply frame unless the
+    /// call was oneway.
+    fn process<P>(&self, protocol: P, request: Vec<u8>) -> BoxFuture<'static, Result<Option<Vec<u8>>>>
+    where P: Protocol + Send + 'static;
+}
+
+/// Resolves to the reply to the call `name`, once the future of its handler
+/// is done. `encode` turns the handler's return value into the result struct.
+pub fn reply<P, R, E, F>(protocol: P, name: &'static str, ty: MessageType, id: i32,
+                         call: BoxFuture<'static, R>, encode: F) -> BoxFuture<'static, Result<Option<Vec<u8>>>>
+where P: Protocol + Send + 'static, R: 'static, E: Encode, F: FnOnce(R) -> E + Send + 'static {
+    Box::pin(Reply {
+        protocol: protocol,
+        name: name,
+        ty: ty,
+        id: id,
+        call: call,
+        encode: Some(encode),
+    })
+}
+
+/// Resolves to a reply with `error`, or to nothing for oneway calls.
+pub fn reply_exception<P>(mut protocol: P, name: &str, ty: MessageType, id: i32,
+                          error: &ApplicationException) -> BoxFuture<'static, Result<Option<Vec<u8>>>>
+where P: Protocol {
+    let mut transport = MemoryTransport::default();
+    super::ready(processor::reply_exception(&mut protocol, &mut transport, name, ty, id, error)
+                 .map(|_| written(transport)))
+}
+
+/// Resolves to the reply to a call of a method the processor doesn't know.
+pub fn unknown_method<P>(mut protocol: P, mut transport: MemoryTransport, name: &str,
+                         ty: MessageType, id: i32) -> BoxFuture<'static, Result<Option<Vec<u8>>>>
+where P: Protocol {
+    super::ready(processor::unknown_method(&mut protocol, &mut transport, name, ty, id)
+                 .map(|_| written(transport)))
+}
+
+struct Reply<P, R, F> {
+    protocol: P,
+    name: &'static str,
+    ty: MessageType,
+    id: i32,
+    call: BoxFuture<'static, R>,
+    encode: Option<F>,
+}
+
+// The handler's future is pinned on the heap, no other field ever is.
+impl<P, R, F> Unpin for Reply<P, R, F> {}
+
+impl<P, R, E, F> Future for Reply<P, R, F>
+where P: Protocol, E: Encode, F: FnOnce(R) -> E {
+    type Output = Result<Option<Vec<u8>>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let mut transport = MemoryTransport::default();
+
+        let sent = match panic::catch_unwind(AssertUnwindSafe(|| this.call.as_mut().poll(cx))) {
+            Ok(Poll::Pending) => return Poll::Pending,
+            Ok(Poll::Ready(_)) if this.ty == MessageType::Oneway => Ok(()),
+            Ok(Poll::Ready(value)) => {
+                let result = this.encode.take().unwrap()(value);
+                helpers::send(&mut this.protocol, &mut transport, this.name,
+                              MessageType::Reply, &result, this.id)
+            }
+            Err(payload) => {
+                let error = processor::internal_error(this.name, payload);
+                processor::reply_exception(&mut this.protocol, &mut transport, this.name,
+                                           this.ty, this.id, &error)
+            }
+        };
+        Poll::Ready(sent.map(|_| written(transport)))
+    }
+}
+
+/// A server running generated async processors on a tokio runtime.
+///
+/// The requests of a connection are processed one after the other, each
+/// connection on its own task.
+pub struct AsyncServer<P, PF> {
+    inner: Arc<AsyncServerInner<P, PF>>,
+}
+
+struct AsyncServerInner<P, PF> {
+    processor: P,
+    protocol_factory: PF,
+    max_frame_size: usize,
+}
+
+impl<P, PF> AsyncServer<P, PF>
+where P: AsyncProcessor + Send + Sync + 'static,
+      PF: ProtocolFactory + Send + Sync + 'static,
+      PF::Protocol: Send + 'static {
+
+    /// Frames may be as large as the protocols' `max_message_size`.
+    pub fn new(processor: P, factory: PF) -> Self {
+        let max_frame_size = factory.new_protocol().limits().max_message_size;
+        AsyncServer {
+            inner: Arc::new(AsyncServerInner {
+                processor: processor,
+                protocol_factory: factory,
+                max_frame_size: max_frame_size,
+            })
+        }
+    }
+
+    /// Serves the connections accepted by `listener`, spawning a task for
+    /// each. The future only resolves if accepting fails.
+    pub fn serve(self, listener: TcpListener) -> BoxFuture<'static, io::Result<()>> {
+        Box::pin(Serve { inner: self.inner, listener: listener })
+    }
+
+    /// Serves a single connection, until the peer closes it.
+    pub fn serve_connection<T>(&self, transport: T) -> BoxFuture<'static, Result<()>>
+    where T: AsyncTransport + 'static {
+        Box::pin(Connection::new(self.inner.clone(), transport))
+    }
+}
+
+struct Serve<P, PF> {
+    inner: Arc<AsyncServerInner<P, PF>>,
+    listener: TcpListener,
+}
+
+impl<P, PF> Future for Serve<P, PF>
+where P: AsyncProcessor + Send + Sync + 'static,
+      PF: ProtocolFactory + Send + Sync + 'static,
+      PF::Protocol: Send + 'static {
+    type Output = io::Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            let (stream, _) = try_ready!(this.listener.poll_accept(cx));
+            tokio::spawn(Connection::new(this.inner.clone(), stream));
+        }
+    }
+}
+
+struct Connection<P, PF, T> {
+    inner: Arc<AsyncServerInner<P, PF>>,
+    transport: T,
+    state: ConnectionState,
+}
+
+enum ConnectionState {
+    Reading(FrameReader),
+    Processing(BoxFuture<'static, Result<Option<Vec<u8>>>>),
+    Writing(FrameWriter),
+}
+
+impl<P, PF, T> Connection<P, PF, T> {
+    fn new(inner: Arc<AsyncServerInner<P, PF>>, transport: T) -> Connection<P, PF, T> {
+        let reader = FrameReader::new(inner.max_frame_size);
+        Connection { inner: inner, transport: transport, state: ConnectionState::Reading(reader) }
+    }
+}
+
+impl<P, PF, T> Future for Connection<P, PF, T>
+where P: AsyncProcessor,
+      PF: ProtocolFactory,
+      PF::Protocol: Send + 'static,
+      T: AsyncTransport {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        loop {
+            let next = match this.state {
+                ConnectionState::Reading(ref mut reader) => {
+                    match reader.poll_read(cx, &mut this.transport) {
+                        Poll::Pending => return Poll::Pending,
+                        Poll::Ready(Err(Error::TransportError(ref e)))
+                            if reader.is_empty() && e.kind() == io::ErrorKind::UnexpectedEof => {
+                            return Poll::Ready(Ok(()));
+                        }
+                        Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                        Poll::Ready(Ok(request)) => {
+                            let protocol = this.inner.protocol_factory.new_protocol();
+                            ConnectionState::Processing(this.inner.processor.process(protocol, request))
+                        }
+                    }
+                }
+                ConnectionState::Processing(ref mut reply) => {
+                    match try_ready!(reply.as_mut().poll(cx)) {
+                        Some(reply) => ConnectionState::Writing(try_ready!(Poll::Ready(FrameWriter::new(&reply)))),
+                        None => ConnectionState::Reading(FrameReader::new(this.inner.max_frame_size)),
+                    }
+                }
+                ConnectionState::Writing(ref mut writer) => {
+                    try_ready!(writer.poll_write(cx, &mut this.transport));
+                    ConnectionState::Reading(FrameReader::new(this.inner.max_frame_size))
+                }
+            };
+            this.state = next;
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! Clients with several calls in flight over one connection.
+//!
+//! A `Channel` owns the writing end of a connection and a thread reading
+//! replies from its reading end. Every call is sent under a fresh sequence
+//! id, and its caller blocks until the reader hands over the reply with that
+//! id, so the server may answer in any order. The concurrent clients
+//! generated by `service!` wrap a `Channel` and can be shared between threads.
+
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+
+use exception::ApplicationException;
+use protocol::{self, helpers, Decode, Encode, MessageType, Protocol, ReplyMismatch, Type};
+use transport::{Split, Transport};
+use transport::header::Header;
+use transport::slice::SliceTransport;
+use {Error, Result};
+
+/// A message read by the reader thread, before its caller decodes it.
+///
+/// The header has been decoded already. The rest of the message is kept as
+/// it was received, since only the caller knows the type of its body,
+/// together with the reader's protocol in the state right after the header.
+struct Reply<P> {
+    name: String,
+    ty: MessageType,
+    protocol: P,
+    body: Vec<u8>,
+}
+
+struct Pending<P> {
+    waiters: BTreeMap<i32, mpsc::Sender<Reply<P>>>,
+    /// Set once the reader stopped or the channel was closed, after which no
+    /// reply can arrive.
+    closed: bool,
+}
+
+struct Writer<P, W> {
+    protocol: P,
+    /// `None` once the channel was closed.
+    transport: Option<W>,
+    seqid: i32,
+}
+
+pub struct Channel<P, W> {
+    writer: Mutex<Writer<P, W>>,
+    pending: Arc<Mutex<Pending<P>>>,
+}
+
+impl<P: Protocol + Clone + Send + 'static, W: Transport> Channel<P, W> {
+    /// Writes calls to `writer` with `protocol`, and starts a thread reading
+    /// their replies from `reader`.
+    ///
+    /// The thread stops when reading fails, e.g. because the connection was
+    /// closed, failing all calls still waiting for a reply.
+    pub fn new<R>(protocol: P, reader: R, writer: W) -> Channel<P, W>
+    where R: Transport + Send + 'static {
+        let pending = Arc::new(Mutex::new(Pending { waiters: BTreeMap::new(), closed: false }));
+        let (shared, read_protocol) = (pending.clone(), protocol.clone());
+        thread::spawn(move || read_replies(read_protocol, reader, shared));
+
+        Channel {
+            writer: Mutex::new(Writer { protocol: protocol, transport: Some(writer), seqid: 0 }),
+            pending: pending,
+        }
+    }
+
+    /// Splits `transport` into its reading and writing ends.
+    pub fn split<T>(protocol: P, transport: T) -> io::Result<Channel<P, W>>
+    where T: Split<Writer = W> {
+        let (reader, writer) = try!(transport.split());
+        Ok(Channel::new(protocol, reader, writer))
+    }
+}
+
+impl<P: Protocol, W: Transport> Channel<P, W> {
+    /// Sends the call `name` with `args`, and waits for its result.
+    pub fn call<A: Encode, R: Decode>(&self, name: &str, args: &A) -> Result<R> {
+        let (tx, rx) = mpsc::channel();
+        try!(self.send(name, MessageType::Call, args, Some(tx)));
+        let reply = try!(rx.recv().map_err(|_| closed()));
+
+        if reply.name != name {
+            let mismatch = ReplyMismatch::MethodName(name.to_string(), reply.name);
+            return Err(Error::from(protocol::Error::UnexpectedReply(mismatch)));
+        }
+        match reply.ty {
+            MessageType::Reply => decode(reply.protocol, &reply.body),
+            MessageType::Exception => Err(Error::from(try!(decode::<_, ApplicationException>(reply.protocol, &reply.body)))),
+            ty => Err(Error::from(protocol::Error::UnexpectedReply(ReplyMismatch::MessageType(ty)))),
+        }
+    }
+
+    /// Sends the oneway call `name` with `args`.
+    pub fn call_oneway<A: Encode>(&self, name: &str, args: &A) -> Result<()> {
+        self.send(name, MessageType::Oneway, args, None)
+    }
+
+    /// Closes the channel: calls still waiting for a reply fail, and so do
+    /// all later ones.
+    ///
+    /// The writing end is dropped, which for a split `TcpStream` shuts the
+    /// connection down and so also stops the reader thread. With other
+    /// transports, the thread stops once its current read returns.
+    pub fn close(&self) {
+        self.writer.lock().unwrap().transport = None;
+
+        // Dropping the senders wakes up everybody still waiting.
+        let mut pending = self.pending.lock().unwrap();
+        pending.closed = true;
+        pending.waiters.clear();
+    }
+
+    fn send<A: Encode>(&self, name: &str, ty: MessageType, args: &A,
+                       waiter: Option<mpsc::Sender<Reply<P>>>) -> Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        if writer.transport.is_none() {
+            return Err(closed());
+        }
+        writer.seqid = writer.seqid.wrapping_add(1);
+        let seqid = writer.seqid;
+
+        // Registered before sending, so the reply can't arrive first.
+        if let Some(waiter) = waiter {
+            let mut pending = self.pending.lock().unwrap();
+            if pending.closed {
+                return Err(closed());
+            }
+            pending.waiters.insert(seqid, waiter);
+        }
+
+        let Writer { ref mut protocol, ref mut transport, .. } = *writer;
+        let transport = transport.as_mut().expect("checked above");
+        let sent = helpers::send(protocol, transport, name, ty, args, seqid);
+        if sent.is_err() {
+            self.pending.lock().unwrap().waiters.remove(&seqid);
+        }
+        sent
+    }
+}
+
+/// Decodes the rest of a message read by the reader thread.
+fn decode<P: Protocol, R: Decode>(mut protocol: P, body: &[u8]) -> Result<R> {
+    let mut transport = SliceTransport::new(body);
+    let mut value = R::default();
+    try!(value.decode(&mut protocol, &mut transport));
+    try!(protocol.read_message_end(&mut transport));
+    Ok(value)
+}
+
+fn read_replies<P, R>(mut protocol: P, mut transport: R, pending: Arc<Mutex<Pending<P>>>)
+where P: Protocol + Clone, R: Transport {
+    while let Ok((seqid, reply)) = read_reply(&mut protocol, &mut transport) {
+        let mut pending = pending.lock().unwrap();
+        if pending.closed {
+            return;
+        }
+        match pending.waiters.remove(&seqid) {
+            Some(waiter) => {
+                let _ = waiter.send(reply);
+            }
+            None => warn!("dropping reply to {} with unknown sequence id {}", reply.name, seqid),
+        }
+    }
+
+    // Dropping the senders wakes up everybody still waiting.
+    let mut pending = pending.lock().unwrap();
+    pending.closed = true;
+    pending.waiters.clear();
+}
+
+fn read_reply<P, R>(protocol: &mut P, transport: &mut R) -> Result<(i32, Reply<P>)>
+where P: Protocol + Clone, R: Transport {
+    let (name, ty, seqid) = try!(protocol.read_message_begin(transport));
+    let after_header = protocol.clone();
+    let mut transport = Recording { inner: transport, bytes: Vec::new() };
+    try!(protocol.skip(&mut transport, Type::Struct));
+    try!(protocol.read_message_end(&mut transport));
+    Ok((seqid, Reply { name: name, ty: ty, protocol: after_header, body: transport.bytes }))
+}
+
+/// Keeps a copy of everything read from `inner`.
+struct Recording<'a, T: 'a> {
+    inner: &'a mut T,
+    bytes: Vec<u8>,
+}
+
+impl<'a, T: Transport> Read for Recording<'a, T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = try!(self.inner.read(buf));
+        self.bytes.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+}
+
+impl<'a, T: Transport> Write for Recording<'a, T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<'a, T: Transport> Transport for Recording<'a, T> {
+    fn header(&mut self) -> Option<&mut Header> { self.inner.header() }
+    fn prepare_read(&mut self) -> io::Result<()> { self.inner.prepare_read() }
+}
+
+fn closed() -> Error {
+    Error::from(io::Error::new(io::ErrorKind::ConnectionAborted, "connection closed"))
+}
@@ -3,8 +3,12 @@ macro_rules! service {
     (trait_name = $name:ident,
      processor_name = $processor_name:ident,
      client_name = $client_name:ident,
-     service_methods = [$($siname:ident -> $soname:ident = $smfname:ident.$smname:ident($($saname:ident: $saty:ty => $said:expr,)*) -> $srty:ty => $senname:ident = [$($sevname:ident($sename:ident: $sety:ty => $seid:expr),)*] ($srrty:ty),)*],
-     parent_methods = [$($piname:ident -> $poname:ident = $pmfname:ident.$pmname:ident($($paname:ident: $paty:ty => $paid:expr,)*) -> $prty:ty => $penname:ident = [$($pevname:ident($pename:ident: $pety:ty => $peid:expr),)*] ($prrty:ty),)*],
+     $(concurrent_client_name = $concurrent_name:ident,)*
+     $(async_trait_name = $async_name:ident,
+       async_processor_name = $async_processor_name:ident,
+       async_client_name = $async_client_name:ident,)*
+     service_methods = [$($siname:ident -> $soname:ident = $smfname:ident.$smname:ident($($saname:ident: $saty:ty => $said:expr,)*) -> $srty:ty => $senname:ident = [$($sevname:ident($sename:ident: $sety:ty => $seid:expr),)*] ($srrty:ty) $($soneway:ident)*,)*],
+     parent_methods = [$($piname:ident -> $poname:ident = $pmfname:ident.$pmname:ident($($paname:ident: $paty:ty => $paid:expr,)*) -> $prty:ty => $penname:ident = [$($pevname:ident($pename:ident: $pety:ty => $peid:expr),)*] ($prrty:ty) $($poneway:ident)*,)*],
      bounds = [$($boundty:ident: $bound:ident,)*],
      fields = [$($fname:ident: $fty:ty,)*]) => {
         pub trait $name {
@@ -13,25 +17,82 @@ macro_rules! service {
 
         service_processor! {
             processor_name = $processor_name,
-            service_methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty),)*],
-            parent_methods = [$($piname -> $poname = $pmfname.$pmname($($paname: $paty => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty),)*],
+            service_methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty) $($soneway)*,)*],
+            parent_methods = [$($piname -> $poname = $pmfname.$pmname($($paname: $paty => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty) $($poneway)*,)*],
             bounds = [$($boundty: $bound,)*],
             fields = [$($fname: $fty,)*]
         }
 
         service_client! {
             client_name = $client_name,
-            service_methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty),)*],
-            parent_methods = [$($piname -> $poname = $pmfname.$pmname($($paname: $paty => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty),)*]
+            $(concurrent_client_name = $concurrent_name,)*
+            service_methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty) $($soneway)*,)*],
+            parent_methods = [$($piname -> $poname = $pmfname.$pmname($($paname: $paty => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty) $($poneway)*,)*]
+        }
+
+        service_async! {
+            $(names = [$async_name, $async_processor_name, $async_client_name],)*
+            methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty) $($soneway)*,)*
+                       $($piname -> $poname = $pmfname.$pmname($($paname: $paty => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty) $($poneway)*,)*]
+        }
+
+        impl<$($boundty: $bound),*> $crate::descriptor::ThriftService for $processor_name<$($boundty),*> {
+            fn descriptor() -> &'static $crate::descriptor::ServiceDescriptor {
+                service_descriptor! {
+                    name = $name,
+                    methods = [$($smname($($saname: $saty => $said,)*) -> $srty = [$($sename: $sety => $seid,)*] $($soneway)*,)*
+                               $($pmname($($paname: $paty => $paid,)*) -> $prty = [$($pename: $pety => $peid,)*] $($poneway)*,)*]
+                }
+            }
+        }
+
+        impl<P: $crate::Protocol, T: $crate::Transport> $crate::descriptor::ThriftService for $client_name<P, T> {
+            fn descriptor() -> &'static $crate::descriptor::ServiceDescriptor {
+                service_descriptor! {
+                    name = $name,
+                    methods = [$($smname($($saname: $saty => $said,)*) -> $srty = [$($sename: $sety => $seid,)*] $($soneway)*,)*
+                               $($pmname($($paname: $paty => $paid,)*) -> $prty = [$($pename: $pety => $peid,)*] $($poneway)*,)*]
+                }
+            }
         }
     }
 }
 
+#[macro_export]
+macro_rules! service_descriptor {
+    (name = $name:ident,
+     methods = [$($mname:ident($($aname:ident: $aty:ty => $aid:expr,)*) -> $rty:ty = [$($ename:ident: $ety:ty => $eid:expr,)*] $($oneway:ident)*,)*]) => {{
+        static DESCRIPTOR: $crate::descriptor::ServiceDescriptor = $crate::descriptor::ServiceDescriptor {
+            name: stringify!($name),
+            methods: &[$($crate::descriptor::MethodDescriptor {
+                name: stringify!($mname),
+                args: &[$($crate::descriptor::FieldDescriptor {
+                    name: stringify!($aname),
+                    id: $aid,
+                    typ: <$aty as $crate::descriptor::Described>::DESCRIPTOR,
+                    requiredness: $crate::descriptor::Requiredness::Default,
+                    required: false,
+                },)*],
+                result: <$rty as $crate::descriptor::Described>::DESCRIPTOR,
+                exceptions: &[$($crate::descriptor::FieldDescriptor {
+                    name: stringify!($ename),
+                    id: $eid,
+                    typ: <$ety as $crate::descriptor::Described>::DESCRIPTOR,
+                    requiredness: $crate::descriptor::Requiredness::Optional,
+                    required: false,
+                },)*],
+                oneway: service_oneway!($($oneway)*),
+            },)*],
+        };
+        &DESCRIPTOR
+    }}
+}
+
 #[macro_export]
 macro_rules! service_processor {
     (processor_name = $name:ident,
-     service_methods = [$($siname:ident -> $soname:ident = $smfname:ident.$smname:ident($($saname:ident: $saty:ty => $said:expr,)*) -> $srty:ty => $senname:ident = [$($sevname:ident($sename:ident: $sety:ty => $seid:expr),)*] ($srrty:ty),)*],
-     parent_methods = [$($piname:ident -> $poname:ident = $pmfname:ident.$pmname:ident($($paname:ident: $paty:ty => $paid:expr,)*) -> $prty:ty => $penname:ident = [$($pevname:ident($pename:ident: $pety:ty => $peid:expr),)*] ($prrty:ty),)*],
+     service_methods = [$($siname:ident -> $soname:ident = $smfname:ident.$smname:ident($($saname:ident: $saty:ty => $said:expr,)*) -> $srty:ty => $senname:ident = [$($sevname:ident($sename:ident: $sety:ty => $seid:expr),)*] ($srrty:ty) $($soneway:ident)*,)*],
+     parent_methods = [$($piname:ident -> $poname:ident = $pmfname:ident.$pmname:ident($($paname:ident: $paty:ty => $paid:expr,)*) -> $prty:ty => $penname:ident = [$($pevname:ident($pename:ident: $pety:ty => $peid:expr),)*] ($prrty:ty) $($poneway:ident)*,)*],
      bounds = [$($boundty:ident: $bound:ident,)*],
      fields = [$($fname:ident: $fty:ty,)*]) => {
         pub struct $name<$($boundty: $bound),*> {
@@ -59,12 +120,19 @@ macro_rules! service_processor {
                 match name {
                     $(stringify!($smname) => self.$smname(prot, transport, ty, id),)*
                     $(stringify!($pmname) => self.$pmname(prot, transport, ty, id),)*
-                    _ => Err($crate::Error::from($crate::protocol::Error::ProtocolViolation))
+                    _ => $crate::processor::unknown_method(prot, transport, name, ty, id)
                 }
             }
 
-            service_processor_methods! { methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty),)*] }
-            service_processor_methods! { methods = [$($piname -> $poname = $pmfname.$pmname($($paname: $paty => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty),)*] }
+            service_processor_methods! { methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty) $($soneway)*,)*] }
+            service_processor_methods! { methods = [$($piname -> $poname = $pmfname.$pmname($($paname: $paty => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty) $($poneway)*,)*] }
+        }
+
+        impl<P: $crate::Protocol, T: $crate::Transport, $($boundty: $bound),*> $crate::processor::Dispatcher<P, T> for $name<$($boundty),*> {
+            fn dispatch(&self, prot: &mut P, transport: &mut T,
+                        name: &str, ty: $crate::protocol::MessageType, id: i32) -> $crate::Result<()> {
+                $name::dispatch(self, prot, transport, name, ty, id)
+            }
         }
 
         impl<P: $crate::Protocol, T: $crate::Transport, $($boundty: $bound),*> $crate::Processor<P, T> for $name<$($boundty),*> {
@@ -102,21 +170,44 @@ macro_rules! service_processor_error_enum {
 
 #[macro_export]
 macro_rules! service_processor_methods {
-    (methods = [$($iname:ident -> $oname:ident = $fname:ident.$mname:ident($($aname:ident: $aty:ty => $aid:expr,)*) -> $rty:ty => $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*] ($rrty:ty),)*]) => {
+    (methods = [$($iname:ident -> $oname:ident = $fname:ident.$mname:ident($($aname:ident: $aty:ty => $aid:expr,)*) -> $rty:ty => $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*] ($rrty:ty) $($oneway:ident)*,)*]) => {
         $(fn $mname<P: $crate::Protocol, T: $crate::Transport>(&self, prot: &mut P, transport: &mut T,
                                                                ty: $crate::protocol::MessageType, id: i32) -> $crate::Result<()> {
             use $crate::proxy::Proxy;
 
             static MNAME: &'static str = stringify!($mname);
+            static ONEWAY: bool = service_oneway!($($oneway)*);
 
             let mut args = $iname::default();
-            try!($crate::protocol::helpers::receive_body(prot, transport, MNAME,
-                                                         &mut args, MNAME, ty, id));
+            try!($crate::protocol::helpers::receive_args(prot, transport, &mut args, ty));
 
             self.proxies.proxy(ty, MNAME, id, &args);
 
-            // TODO: Further investigate this unwrap.
-            let result = self.$fname.$mname($(args.$aname.unwrap()),*);
+            $(let $aname = match args.$aname.take() {
+                Some(arg) => arg,
+                None => {
+                    let error = $crate::ApplicationException::new(
+                        $crate::exception::ApplicationExceptionKind::ProtocolError,
+                        format!("missing argument {} to {}", stringify!($aname), MNAME));
+                    return $crate::processor::reply_exception(prot, transport, MNAME, ty, id, &error);
+                }
+            };)*
+
+            let result = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+                $crate::transport::header::scope(transport, || {
+                    self.$fname.$mname($($aname),*)
+                })
+            }));
+            let result = match result {
+                Ok(result) => result,
+                Err(payload) => {
+                    let error = $crate::processor::internal_error(MNAME, payload);
+                    return $crate::processor::reply_exception(prot, transport, MNAME, ty, id, &error);
+                }
+            };
+            if ONEWAY {
+                return Ok(());
+            }
             let result = service_processor_methods_translate_return!(
                 result, $oname, $enname = [$($evname($ename: $ety => $eid),)*]);
             try!($crate::protocol::helpers::send(prot, transport, MNAME,
@@ -149,86 +240,309 @@ macro_rules! service_processor_methods_translate_return {
 #[macro_export]
 macro_rules! service_client {
     (client_name = $client_name:ident,
-     service_methods = [$($siname:ident -> $soname:ident = $smfname:ident.$smname:ident($($saname:ident: $saty:ty => $said:expr,)*) -> $srty:ty => $senname:ident = [$($sevname:ident($sename:ident: $sety:ty => $seid:expr),)*] ($srrty:ty),)*],
-     parent_methods = [$($piname:ident -> $poname:ident = $pmfname:ident.$pmname:ident($($paname:ident: $paty:ty => $paid:expr,)*) -> $prty:ty => $penname:ident = [$($pevname:ident($pename:ident: $pety:ty => $peid:expr),)*] ($prrty:ty),)*]) => {
+     $(concurrent_client_name = $concurrent_name:ident,)*
+     service_methods = [$($siname:ident -> $soname:ident = $smfname:ident.$smname:ident($($saname:ident: $saty:ty => $said:expr,)*) -> $srty:ty => $senname:ident = [$($sevname:ident($sename:ident: $sety:ty => $seid:expr),)*] ($srrty:ty) $($soneway:ident)*,)*],
+     parent_methods = [$($piname:ident -> $poname:ident = $pmfname:ident.$pmname:ident($($paname:ident: $paty:ty => $paid:expr,)*) -> $prty:ty => $penname:ident = [$($pevname:ident($pename:ident: $pety:ty => $peid:expr),)*] ($prrty:ty) $($poneway:ident)*,)*]) => {
         pub struct $client_name<P: $crate::Protocol, T: $crate::Transport> {
             pub protocol: P,
-            pub transport: T
+            pub transport: T,
+            seqid: i32,
         }
 
         impl<P: $crate::Protocol, T: $crate::Transport> $client_name<P, T> {
             pub fn new(protocol: P, transport: T) -> Self {
                 $client_name {
                     protocol: protocol,
-                    transport: transport
+                    transport: transport,
+                    seqid: 0,
+                }
+            }
+
+            service_client_methods! { methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty) $($soneway)*,)*] }
+            service_client_methods! { methods = [$($piname -> $poname = $pmfname.$pmname($($paname: $paty => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty) $($poneway)*,)*] }
+        }
+
+        service_concurrent_client! {
+            $(client_name = $concurrent_name,)*
+            methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty) $($soneway)*,)*
+                       $($piname -> $poname = $pmfname.$pmname($($paname: $paty => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty) $($poneway)*,)*]
+        }
+    }
+}
+
+/// A client sharing one connection between threads, generated when
+/// `service!` is given a `concurrent_client_name`.
+#[macro_export]
+macro_rules! service_concurrent_client {
+    (methods = [$($iname:ident -> $oname:ident = $fname:ident.$mname:ident($($aname:ident: $aty:ty => $aid:expr,)*) -> $rty:ty => $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*] ($rrty:ty) $($oneway:ident)*,)*]) => {};
+    (client_name = $client_name:ident,
+     methods = [$($iname:ident -> $oname:ident = $fname:ident.$mname:ident($($aname:ident: $aty:ty => $aid:expr,)*) -> $rty:ty => $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*] ($rrty:ty) $($oneway:ident)*,)*]) => {
+        pub struct $client_name<P: $crate::Protocol, W: $crate::Transport> {
+            pub channel: $crate::client::Channel<P, W>,
+        }
+
+        impl<P: $crate::Protocol, W: $crate::Transport> $client_name<P, W> {
+            pub fn new(channel: $crate::client::Channel<P, W>) -> Self {
+                $client_name { channel: channel }
+            }
+
+            $(pub fn $mname(&self, $($aname: $aty,)*) -> $crate::Result<$rrty> {
+                static MNAME: &'static str = stringify!($mname);
+
+                #[allow(unused_mut)]
+                let mut args = $iname::default();
+                $(args.$aname = Some($aname);)*
+                service_concurrent_client_call!(
+                    [$($oneway)*] self, MNAME, args, $oname, $rty, $enname = [$($evname($ename: $ety => $eid),)*])
+            })*
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! service_concurrent_client_call {
+    ([oneway] $this:ident, $mname:expr, $args:ident, $oname:ident, $rty:ty, $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*]) => {
+        $this.channel.call_oneway($mname, &$args)
+    };
+    ([] $this:ident, $mname:expr, $args:ident, $oname:ident, $rty:ty, $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*]) => {{
+        let result: $oname = try!($this.channel.call($mname, &$args));
+        service_client_methods_translate_result!(
+            result, $mname, $rty, $enname = [$($evname($ename: $ety => $eid),)*])
+    }};
+}
+
+/// The async handler trait, processor and client of `service!`, generated
+/// when it is given their names and the `async` feature is enabled.
+#[cfg(feature = "async")]
+#[macro_export]
+macro_rules! service_async {
+    (methods = [$($iname:ident -> $oname:ident = $fname:ident.$mname:ident($($aname:ident: $aty:ty => $aid:expr,)*) -> $rty:ty => $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*] ($rrty:ty) $($oneway:ident)*,)*]) => {};
+    (names = [$name:ident, $processor_name:ident, $client_name:ident],
+     methods = [$($iname:ident -> $oname:ident = $fname:ident.$mname:ident($($aname:ident: $aty:ty => $aid:expr,)*) -> $rty:ty => $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*] ($rrty:ty) $($oneway:ident)*,)*]) => {
+        pub trait $name {
+            $(fn $mname(&self, $($aname: $aty),*) -> $crate::asynk::BoxFuture<'static, $rrty>;)*
+        }
+
+        pub struct $processor_name<S> {
+            handler: S,
+        }
+
+        impl<S: $name> $processor_name<S> {
+            pub fn new(handler: S) -> Self {
+                $processor_name { handler: handler }
+            }
+
+            $(fn $mname<P: $crate::Protocol + Send + 'static>(&self, mut prot: P, mut transport: $crate::transport::memory::MemoryTransport,
+                                                              ty: $crate::protocol::MessageType, id: i32)
+                                                              -> $crate::asynk::BoxFuture<'static, $crate::Result<Option<Vec<u8>>>> {
+                static MNAME: &'static str = stringify!($mname);
+
+                let mut args = $iname::default();
+                if let Err(e) = $crate::protocol::helpers::receive_args(&mut prot, &mut transport, &mut args, ty) {
+                    return $crate::asynk::ready(Err(e));
+                }
+
+                $(let $aname = match args.$aname.take() {
+                    Some(arg) => arg,
+                    None => {
+                        let error = $crate::ApplicationException::new(
+                            $crate::exception::ApplicationExceptionKind::ProtocolError,
+                            format!("missing argument {} to {}", stringify!($aname), MNAME));
+                        return $crate::asynk::reply_exception(prot, MNAME, ty, id, &error);
+                    }
+                };)*
+
+                let call = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+                    self.handler.$mname($($aname),*)
+                }));
+                match call {
+                    Ok(call) => $crate::asynk::reply(prot, MNAME, ty, id, call, |result: $rrty| {
+                        service_processor_methods_translate_return!(
+                            result, $oname, $enname = [$($evname($ename: $ety => $eid),)*])
+                    }),
+                    Err(payload) => {
+                        let error = $crate::processor::internal_error(MNAME, payload);
+                        $crate::asynk::reply_exception(prot, MNAME, ty, id, &error)
+                    }
+                }
+            })*
+        }
+
+        impl<S: $name> $crate::asynk::AsyncProcessor for $processor_name<S> {
+            fn process<P>(&self, mut prot: P, request: Vec<u8>) -> $crate::asynk::BoxFuture<'static, $crate::Result<Option<Vec<u8>>>>
+            where P: $crate::Protocol + Send + 'static {
+                #[allow(unused_imports)]
+                use $crate::Protocol;
+
+                let mut transport = $crate::transport::memory::MemoryTransport::new(request);
+                let (name, ty, id) = match prot.read_message_begin(&mut transport) {
+                    Ok(header) => header,
+                    Err(e) => return $crate::asynk::ready(Err(e)),
+                };
+                match &name[..] {
+                    $(stringify!($mname) => self.$mname(prot, transport, ty, id),)*
+                    _ => $crate::asynk::unknown_method(prot, transport, &name, ty, id),
+                }
+            }
+        }
+
+        pub struct $client_name<P: $crate::Protocol, T: $crate::asynk::AsyncTransport> {
+            pub protocol: P,
+            pub transport: T,
+            seqid: i32,
+        }
+
+        impl<P: $crate::Protocol + Send, T: $crate::asynk::AsyncTransport> $client_name<P, T> {
+            pub fn new(protocol: P, transport: T) -> Self {
+                $client_name {
+                    protocol: protocol,
+                    transport: transport,
+                    seqid: 0,
                 }
             }
 
-            service_client_methods! { methods = [$($siname -> $soname = $smfname.$smname($($saname: $saty => $said,)*) -> $srty => $senname = [$($sevname($sename: $sety => $seid),)*] ($srrty),)*] }
-            service_client_methods! { methods = [$($piname -> $poname = $pmfname.$pmname($($paname: $paty => $paid,)*) -> $prty => $penname = [$($pevname($pename: $pety => $peid),)*] ($prrty),)*] }
+            $(pub fn $mname<'a>(&'a mut self, $($aname: $aty,)*) -> $crate::asynk::BoxFuture<'a, $crate::Result<$rrty>> {
+                static MNAME: &'static str = stringify!($mname);
+
+                #[allow(unused_mut)]
+                let mut args = $iname::default();
+                $(args.$aname = Some($aname);)*
+                self.seqid = self.seqid.wrapping_add(1);
+                Box::pin($crate::asynk::call(&mut self.protocol, &mut self.transport, MNAME,
+                                             service_oneway!($($oneway)*), self.seqid, &args, |result: $oname| {
+                    service_client_methods_translate_result!(
+                        result, MNAME, $rty, $enname = [$($evname($ename: $ety => $eid),)*])
+                }))
+            })*
         }
     }
 }
 
+#[cfg(not(feature = "async"))]
+#[macro_export]
+macro_rules! service_async {
+    ($($tokens:tt)*) => {};
+}
+
 #[macro_export]
 macro_rules! service_client_methods {
-    (methods = [$($iname:ident -> $oname:ident = $fname:ident.$mname:ident($($aname:ident: $aty:ty => $aid:expr,)*) -> $rty:ty => $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*] ($rrty:ty),)*]) => {
+    (methods = [$($iname:ident -> $oname:ident = $fname:ident.$mname:ident($($aname:ident: $aty:ty => $aid:expr,)*) -> $rty:ty => $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*] ($rrty:ty) $($oneway:ident)*,)*]) => {
         $(pub fn $mname(&mut self, $($aname: $aty,)*) -> $crate::Result<$rrty> {
             static MNAME: &'static str = stringify!($mname);
 
             let mut args = $iname::default();
             $(args.$aname = Some($aname);)*
+            let ty = if service_oneway!($($oneway)*) {
+                $crate::protocol::MessageType::Oneway
+            } else {
+                $crate::protocol::MessageType::Call
+            };
+            self.seqid = self.seqid.wrapping_add(1);
             try!($crate::protocol::helpers::send(&mut self.protocol, &mut self.transport,
-                                                 MNAME, $crate::protocol::MessageType::Call, &mut args, 0));
+                                                 MNAME, ty, &mut args, self.seqid));
 
-            let mut result = $oname::default();
-            try!($crate::protocol::helpers::receive(&mut self.protocol, &mut self.transport,
-                                                    MNAME, &mut result));
-
-            let result = service_client_methods_translate_result!(
-                result, $enname = [$($evname($ename: $ety => $eid),)*]);
-            Ok(result)
+            service_client_methods_receive!(
+                [$($oneway)*] self, MNAME, $oname, $rty, $enname = [$($evname($ename: $ety => $eid),)*])
         })*
     }
 }
 
 #[macro_export]
-macro_rules! service_client_methods_translate_result {
-    ($result:expr, $enname:ident = []) => {{
-        use $crate::protocol::Encode;
+macro_rules! service_client_methods_receive {
+    ([oneway] $this:ident, $mname:expr, $oname:ident, $rty:ty, $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*]) => {
+        Ok(())
+    };
+    ([] $this:ident, $mname:expr, $oname:ident, $rty:ty, $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*]) => {{
+        let mut result = $oname::default();
+        try!($crate::protocol::helpers::receive_reply(&mut $this.protocol, &mut $this.transport,
+                                                      $mname, $this.seqid, &mut result));
 
-        let result = $result;
+        service_client_methods_translate_result!(
+            result, $mname, $rty, $enname = [$($evname($ename: $ety => $eid),)*])
+    }};
+}
 
-        if result.success.should_encode() {
-            result.success.unwrap()
-        } else {
-            result.success.unwrap_or_default()
+/// Whether a method of `service!` was marked `oneway`.
+#[macro_export]
+macro_rules! service_oneway {
+    () => { false };
+    (oneway) => { true };
+}
+
+#[macro_export]
+macro_rules! service_client_methods_translate_result {
+    ($result:expr, $mname:expr, $rty:ty, $enname:ident = []) => {{
+        let result = $result;
+        match result.success {
+            Some(s) => Ok(s),
+            None => $crate::protocol::helpers::missing_result::<$rty>($mname),
         }
     }};
-    ($result:expr, $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*]) => {{
+    ($result:expr, $mname:expr, $rty:ty, $enname:ident = [$($evname:ident($ename:ident: $ety:ty => $eid:expr),)*]) => {{
         let result = $result;
         if let Some(s) = result.success {
-            Ok(s)
+            Ok(Ok(s))
         }
         $(
             else if let Some(e) = result.$ename {
-                Err($enname::$evname(e))
+                Ok(Err($enname::$evname(e)))
             }
         )*
         else {
-            // TODO investigate this
-            unreachable!()
+            $crate::protocol::helpers::missing_result::<$rty>($mname).map(Ok)
         }
     }}
 }
 
+/// Implements `DecodeBorrowed` for types that own all their data.
+#[macro_export]
+macro_rules! decode_borrowed_owned {
+    ($($T:ty),*) => {
+        $(impl<'a> $crate::protocol::DecodeBorrowed<'a> for $T {
+            fn decode_borrowed<P>(&mut self, protocol: &mut P,
+                                  transport: &mut $crate::transport::slice::SliceTransport<'a>) -> $crate::Result<()>
+            where P: $crate::Protocol {
+                $crate::protocol::Decode::decode(self, protocol, transport)
+            }
+        })*
+    }
+}
+
 #[macro_export]
 macro_rules! strukt {
     (name = $name:ident,
-     fields = { $($fname:ident: $fty:ty => $id:expr,)+ }) => {
-        #[derive(Debug, Clone, Default, Eq, PartialEq, PartialOrd, Ord, Hash)]
+     fields = { $($fname:ident: $fty:ty => $id:expr,)+ }
+     $(, defaults = { $($dname:ident: $dval:expr,)* })*
+     $(, required = [$($req:ident,)*])*) => {
+        strukt! { @owned $name, { $($fname: $fty => $id,)+ }, { $($($dname: $dval,)*)* },
+                  [$($($req,)*)*], [] }
+    };
+    (name = $name:ident,
+     fields = { $($fname:ident: $fty:ty => $id:expr,)* }
+     $(, defaults = { $($dname:ident: $dval:expr,)* })*
+     $(, required = [$($req:ident,)*])*,
+     unknown_fields = $uname:ident) => {
+        strukt! { @owned $name, { $($fname: $fty => $id,)* }, { $($($dname: $dval,)*)* },
+                  [$($($req,)*)*], [$uname] }
+    };
+    (@owned $name:ident, { $($fname:ident: $fty:ty => $id:expr,)* }, { $($dname:ident: $dval:expr,)* },
+     [$($req:ident,)*], [$($uname:ident)*]) => {
+        #[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Ord, Hash)]
         pub struct $name {
-            $(pub $fname: $fty,)+
+            $(pub $fname: $fty,)*
+            $(pub $uname: $crate::value::UnknownFields,)*
+        }
+
+        impl Default for $name {
+            #[allow(unused_mut)]
+            fn default() -> Self {
+                let mut this = $name {
+                    $($fname: Default::default(),)*
+                    $($uname: Default::default(),)*
+                };
+                $(this.$dname = $dval;)*
+                this
+            }
         }
 
         impl $crate::protocol::ThriftTyped for $name {
@@ -252,6 +566,11 @@ macro_rules! strukt {
                     try!(protocol.write_field_end(transport));
                 })*
 
+                // An unknown field is only written if no known one with its id was.
+                #[allow(unused_variables)]
+                let written = |id: i16| $((id == $id && $crate::protocol::Encode::should_encode(&self.$fname)) ||)* false;
+                $(try!(self.$uname.write_except(protocol, transport, &written));)*
+
                 try!(protocol.write_field_stop(transport));
                 try!(protocol.write_struct_end(transport));
 
@@ -260,6 +579,7 @@ macro_rules! strukt {
         }
 
         impl $crate::protocol::Decode for $name {
+            #[allow(unused_mut, unused_variables, unused_assignments)]
             fn decode<P, T>(&mut self, protocol: &mut P, transport: &mut T) -> $crate::Result<()>
             where P: $crate::Protocol, T: $crate::Transport {
                 #[allow(unused_imports)]
@@ -269,6 +589,9 @@ macro_rules! strukt {
 
                 try!(protocol.read_struct_begin(transport));
 
+                // Whether each field has been seen, to check the required ones.
+                $(let mut $fname = false;)*
+
                 loop {
                     let (_, typ, id) = try!(protocol.read_field_begin(transport));
 
@@ -276,8 +599,10 @@ macro_rules! strukt {
                         break;
                     } $(else if (typ, id) == ($crate::protocol::helpers::typ::<$fty>(), $id) {
                         try!($crate::protocol::Decode::decode(&mut self.$fname, protocol, transport));
+                        $fname = true;
                     })* else {
-                        try!(protocol.skip(transport, typ));
+                        // Includes known ids with another type than expected.
+                        strukt! { @unknown self, protocol, transport, typ, id, [$($uname)*] }
                     }
 
                     try!(protocol.read_field_end(transport));
@@ -285,13 +610,141 @@ macro_rules! strukt {
 
                 try!(protocol.read_struct_end(transport));
 
+                $(if !$req {
+                    return Err($crate::Error::from(
+                        $crate::protocol::Error::MissingRequiredField(stringify!($name), stringify!($req))));
+                })*
+
                 Ok(())
             }
         }
 
-		custom_struct_traits ! ( $name, { $($fname: $fty => $id, )+ } );
+        decode_borrowed_owned! { $name }
+
+        impl $crate::descriptor::ThriftStruct for $name {
+            fn descriptor() -> &'static $crate::descriptor::StructDescriptor {
+                #[allow(dead_code)]
+                const REQUIRED: &'static [&'static str] = &[$(stringify!($req),)*];
+                static DESCRIPTOR: $crate::descriptor::StructDescriptor = $crate::descriptor::StructDescriptor {
+                    name: stringify!($name),
+                    fields: &[$($crate::descriptor::FieldDescriptor {
+                        name: stringify!($fname),
+                        id: $id,
+                        typ: <$fty as $crate::descriptor::Described>::DESCRIPTOR,
+                        requiredness: $crate::descriptor::requiredness(
+                            stringify!($fname), REQUIRED,
+                            <$fty as $crate::descriptor::Described>::OPTIONAL),
+                        required: $crate::descriptor::is_required(stringify!($fname), REQUIRED),
+                    },)*],
+                };
+                &DESCRIPTOR
+            }
+
+            #[allow(unused_variables)]
+            fn get_field(&self, id: i16) -> Option<$crate::value::Value> {
+                $(if id == $id {
+                    if !$crate::protocol::Encode::should_encode(&self.$fname) {
+                        return None;
+                    }
+                    return $crate::value::to_value(&self.$fname).ok();
+                })*
+                None
+            }
+
+            #[allow(unused_variables)]
+            fn set_field(&mut self, id: i16, value: $crate::value::Value) -> $crate::Result<()> {
+                $(if id == $id {
+                    self.$fname = try!($crate::value::from_value(&value));
+                    return Ok(());
+                })*
+                Err($crate::Error::from($crate::protocol::Error::ProtocolViolation))
+            }
 
+            #[allow(unused_variables)]
+            fn clear_field(&mut self, id: i16) -> $crate::Result<()> {
+                $(if id == $id && <$fty as $crate::descriptor::Described>::OPTIONAL {
+                    self.$fname = Default::default();
+                    return Ok(());
+                })*
+                Err($crate::Error::from($crate::protocol::Error::ProtocolViolation))
+            }
+        }
+
+        impl $crate::descriptor::Described for $name {
+            const DESCRIPTOR: $crate::descriptor::TypeDescriptor =
+                $crate::descriptor::TypeDescriptor::Struct(<$name as $crate::descriptor::ThriftStruct>::descriptor);
+        }
 
+		custom_struct_traits ! ( $name, { $($fname: $fty => $id, )* } );
+    };
+    (@unknown $this:ident, $protocol:ident, $transport:ident, $typ:ident, $id:ident, []) => {
+        try!($protocol.skip($transport, $typ))
+    };
+    (@unknown $this:ident, $protocol:ident, $transport:ident, $typ:ident, $id:ident, [$uname:ident]) => {
+        try!($this.$uname.read($protocol, $transport, $typ, $id))
+    };
+    (name = $name:ident<$lt:tt>,
+     fields = { $($fname:ident: $fty:ty => $id:expr,)+ }) => {
+        /// A struct borrowing from the buffer it was decoded from.
+        #[derive(Debug, Clone, Default, Eq, PartialEq, PartialOrd, Ord, Hash)]
+        pub struct $name<$lt> {
+            $(pub $fname: $fty,)+
+        }
+
+        impl<$lt> $crate::protocol::ThriftTyped for $name<$lt> {
+            fn typ(&self) -> $crate::protocol::Type { $crate::protocol::Type::Struct }
+        }
+
+        impl<$lt> $crate::protocol::Encode for $name<$lt> {
+            fn encode<P, T>(&self, protocol: &mut P, transport: &mut T) -> $crate::Result<()>
+            where P: $crate::Protocol, T: $crate::Transport {
+                #[allow(unused_imports)]
+                use $crate::{Protocol};
+
+                try!(protocol.write_struct_begin(transport, stringify!($name)));
+
+                $(if $crate::protocol::Encode::should_encode(&self.$fname) {
+                    try!(protocol.write_field_begin(transport, stringify!($fname),
+                                                    $crate::protocol::helpers::typ::<$fty>(), $id));
+                    try!($crate::protocol::Encode::encode(&self.$fname, protocol, transport));
+                    try!(protocol.write_field_end(transport));
+                })*
+
+                try!(protocol.write_field_stop(transport));
+                try!(protocol.write_struct_end(transport));
+
+                Ok(())
+            }
+        }
+
+        impl<$lt> $crate::protocol::DecodeBorrowed<$lt> for $name<$lt> {
+            fn decode_borrowed<P>(&mut self, protocol: &mut P,
+                                  transport: &mut $crate::transport::slice::SliceTransport<$lt>) -> $crate::Result<()>
+            where P: $crate::Protocol {
+                #[allow(unused_imports)]
+                use $crate::Protocol;
+
+                try!(protocol.read_struct_begin(transport));
+
+                loop {
+                    let (_, typ, id) = try!(protocol.read_field_begin(transport));
+
+                    if typ == $crate::protocol::Type::Stop {
+                        break;
+                    } $(else if (typ, id) == ($crate::protocol::helpers::typ::<$fty>(), $id) {
+                        try!($crate::protocol::DecodeBorrowed::decode_borrowed(&mut self.$fname, protocol, transport));
+                    })* else {
+                        try!(protocol.skip(transport, typ));
+                    }
+
+                    try!(protocol.read_field_end(transport));
+                }
+
+                try!(protocol.read_struct_end(transport));
+
+                Ok(())
+            }
+        }
     };
     (name = $name:ident, fields = {}) => {
         #[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -334,16 +787,198 @@ macro_rules! strukt {
             }
         }
 
+        decode_borrowed_owned! { $name }
+
+        impl $crate::descriptor::ThriftStruct for $name {
+            fn descriptor() -> &'static $crate::descriptor::StructDescriptor {
+                static DESCRIPTOR: $crate::descriptor::StructDescriptor = $crate::descriptor::StructDescriptor {
+                    name: stringify!($name),
+                    fields: &[],
+                };
+                &DESCRIPTOR
+            }
+
+            fn get_field(&self, _id: i16) -> Option<$crate::value::Value> {
+                None
+            }
+
+            fn set_field(&mut self, _id: i16, _value: $crate::value::Value) -> $crate::Result<()> {
+                Err($crate::Error::from($crate::protocol::Error::ProtocolViolation))
+            }
+
+            fn clear_field(&mut self, _id: i16) -> $crate::Result<()> {
+                Err($crate::Error::from($crate::protocol::Error::ProtocolViolation))
+            }
+        }
+
+        impl $crate::descriptor::Described for $name {
+            const DESCRIPTOR: $crate::descriptor::TypeDescriptor =
+                $crate::descriptor::TypeDescriptor::Struct(<$name as $crate::descriptor::ThriftStruct>::descriptor);
+        }
+
         custom_struct_traits! ($name, { } );
 
     }
 }
 
+#[macro_export]
+macro_rules! unyon {
+    (name = $name:ident,
+     fields = { $($vname:ident($fname:ident: $fty:ty => $id:expr),)* }) => {
+        /// A union: at most one of the fields is set.
+        #[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Ord, Hash)]
+        pub enum $name {
+            $($vname($fty),)*
+            /// No field is set, or only fields unknown to this side.
+            Empty,
+        }
+
+        impl Default for $name {
+            fn default() -> Self { $name::Empty }
+        }
+
+        impl $crate::protocol::ThriftTyped for $name {
+            fn typ(&self) -> $crate::protocol::Type { $crate::protocol::Type::Struct }
+        }
+
+        impl $crate::protocol::Encode for $name {
+            fn should_encode(&self) -> bool {
+                *self != $name::Empty
+            }
+
+            // A union without fields can only fail to encode.
+            #[allow(unreachable_code)]
+            fn encode<P, T>(&self, protocol: &mut P, transport: &mut T) -> $crate::Result<()>
+            where P: $crate::Protocol, T: $crate::Transport {
+                #[allow(unused_imports)]
+                use $crate::Protocol;
+
+                try!(protocol.write_struct_begin(transport, stringify!($name)));
+
+                match *self {
+                    $($name::$vname(ref value) => {
+                        try!(protocol.write_field_begin(transport, stringify!($fname),
+                                                        $crate::protocol::helpers::typ::<$fty>(), $id));
+                        try!($crate::protocol::Encode::encode(value, protocol, transport));
+                        try!(protocol.write_field_end(transport));
+                    })*
+                    $name::Empty => return Err($crate::Error::from($crate::protocol::Error::ProtocolViolation)),
+                }
+
+                try!(protocol.write_field_stop(transport));
+                try!(protocol.write_struct_end(transport));
+
+                Ok(())
+            }
+        }
+
+        impl $crate::protocol::Decode for $name {
+            #[allow(unused_variables)]
+            fn decode<P, T>(&mut self, protocol: &mut P, transport: &mut T) -> $crate::Result<()>
+            where P: $crate::Protocol, T: $crate::Transport {
+                #[allow(unused_imports)]
+                use $crate::Protocol;
+
+                *self = $name::Empty;
+                try!(protocol.read_struct_begin(transport));
+
+                loop {
+                    let (_, typ, id) = try!(protocol.read_field_begin(transport));
+
+                    if typ == $crate::protocol::Type::Stop {
+                        break;
+                    } $(else if (typ, id) == ($crate::protocol::helpers::typ::<$fty>(), $id) {
+                        if *self != $name::Empty {
+                            return Err($crate::Error::from($crate::protocol::Error::ProtocolViolation));
+                        }
+                        let mut value = <$fty as Default>::default();
+                        try!($crate::protocol::Decode::decode(&mut value, protocol, transport));
+                        *self = $name::$vname(value);
+                    })* else {
+                        try!(protocol.skip(transport, typ));
+                    }
+
+                    try!(protocol.read_field_end(transport));
+                }
+
+                try!(protocol.read_struct_end(transport));
+
+                Ok(())
+            }
+        }
+
+        decode_borrowed_owned! { $name }
+
+        impl $crate::descriptor::ThriftStruct for $name {
+            fn descriptor() -> &'static $crate::descriptor::StructDescriptor {
+                static DESCRIPTOR: $crate::descriptor::StructDescriptor = $crate::descriptor::StructDescriptor {
+                    name: stringify!($name),
+                    fields: &[$($crate::descriptor::FieldDescriptor {
+                        name: stringify!($fname),
+                        id: $id,
+                        typ: <$fty as $crate::descriptor::Described>::DESCRIPTOR,
+                        requiredness: $crate::descriptor::Requiredness::Optional,
+                        required: false,
+                    },)*],
+                };
+                &DESCRIPTOR
+            }
+
+            #[allow(unused_variables)]
+            fn get_field(&self, id: i16) -> Option<$crate::value::Value> {
+                match *self {
+                    $($name::$vname(ref value) if id == $id => $crate::value::to_value(value).ok(),)*
+                    _ => None,
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn set_field(&mut self, id: i16, value: $crate::value::Value) -> $crate::Result<()> {
+                $(if id == $id {
+                    *self = $name::$vname(try!($crate::value::from_value(&value)));
+                    return Ok(());
+                })*
+                Err($crate::Error::from($crate::protocol::Error::ProtocolViolation))
+            }
+
+            #[allow(unused_variables)]
+            fn clear_field(&mut self, id: i16) -> $crate::Result<()> {
+                // Clearing the field that is set leaves the union empty.
+                match *self {
+                    $($name::$vname(_) if id == $id => *self = $name::Empty,)*
+                    _ => {}
+                }
+                if $(id == $id ||)* false {
+                    Ok(())
+                } else {
+                    Err($crate::Error::from($crate::protocol::Error::ProtocolViolation))
+                }
+            }
+        }
+
+        impl $crate::descriptor::Described for $name {
+            const DESCRIPTOR: $crate::descriptor::TypeDescriptor =
+                $crate::descriptor::TypeDescriptor::Struct(<$name as $crate::descriptor::ThriftStruct>::descriptor);
+        }
+
+        custom_struct_traits! ( $name, { $($fname: $fty => $id, )* } );
+    }
+}
+
 #[macro_export]
 macro_rules! enom {
     (name = $name:ident,
      values = [$($vname:ident = $val:expr,)*],
      default = $dname:ident) => {
+        enom! { @owned $name, [$($vname = $val,)*], $dname, [] }
+    };
+    (name = $name:ident,
+     values = [$($vname:ident = $val:expr,)*],
+     default = $dname:ident,
+     unknown = $uname:ident) => {
+        enom! { @owned $name, [$($vname = $val,)*], $dname, [$uname] }
+    };
+    (@owned $name:ident, [$($vname:ident = $val:expr,)*], $dname:ident, [$($uname:ident)*]) => {
         #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
         #[repr(i32)]
         pub enum $name {
@@ -380,12 +1015,39 @@ macro_rules! enom {
         impl $crate::protocol::Decode for $name {
             fn decode<P, T>(&mut self, protocol: &mut P, transport: &mut T) -> $crate::Result<()>
             where P: $crate::Protocol, T: $crate::Transport {
-                *self = try!($crate::protocol::helpers::read_enum(protocol, transport));
+                *self = enom! { @read $name, protocol, transport, [$($uname)*] };
                 Ok(())
             }
         }
 
+        decode_borrowed_owned! { $name }
+
+        impl $crate::descriptor::ThriftEnum for $name {
+            fn descriptor() -> &'static $crate::descriptor::EnumDescriptor {
+                static DESCRIPTOR: $crate::descriptor::EnumDescriptor = $crate::descriptor::EnumDescriptor {
+                    name: stringify!($name),
+                    values: &[$((stringify!($vname), $val),)*],
+                };
+                &DESCRIPTOR
+            }
+        }
+
+        impl $crate::descriptor::Described for $name {
+            const DESCRIPTOR: $crate::descriptor::TypeDescriptor =
+                $crate::descriptor::TypeDescriptor::Enum(<$name as $crate::descriptor::ThriftEnum>::descriptor);
+        }
+
         custom_enum_traits! ( $name, { $($vname = $val, )* });
-    }
+    };
+    (@read $name:ident, $protocol:ident, $transport:ident, []) => {
+        try!($crate::protocol::helpers::read_enum($protocol, $transport))
+    };
+    (@read $name:ident, $protocol:ident, $transport:ident, [$uname:ident]) => {{
+        #[allow(unused_imports)]
+        use $crate::Protocol;
+
+        let raw = try!($protocol.read_i32($transport));
+        $crate::protocol::FromNum::from_num(raw).unwrap_or($name::$uname)
+    }};
 }
 
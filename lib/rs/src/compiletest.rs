@@ -74,9 +74,24 @@ service! {
     client_name = ServiceWithExceptionClient,
     service_methods = [
         ServiceWithExceptionOperationArgs -> ServiceWithExceptionOperationResult = this.operation() -> i32 => ServiceWithExceptionOperationError = [Bad(bad: Exception => 1),] (Result<i32, ServiceWithExceptionOperationError>),
+        ServiceWithExceptionCheckArgs -> ServiceWithExceptionCheckResult = this.check() -> () => ServiceWithExceptionCheckError = [Bad(bad: Exception => 1),] (Result<(), ServiceWithExceptionCheckError>),
     ],
     parent_methods = [],
     bounds = [S: ServiceWithException,],
     fields = [this: S,]
 }
 
+
+service! {
+    trait_name = NotifyService,
+    processor_name = NotifyServiceProcessor,
+    client_name = NotifyServiceClient,
+    concurrent_client_name = NotifyServiceConcurrentClient,
+    service_methods = [
+        NotifyServiceNotifyArgs -> NotifyServiceNotifyResult = this.notify(message: String => 1,) -> () => NotifyServiceNotifyError = [] (()) oneway,
+        NotifyServiceCountArgs -> NotifyServiceCountResult = this.count() -> i32 => NotifyServiceCountError = [] (i32),
+    ],
+    parent_methods = [],
+    bounds = [S: NotifyService,],
+    fields = [this: S,]
+}
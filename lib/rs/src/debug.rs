@@ -0,0 +1,21 @@
+//! Helpers for inspecting Thrift values while debugging.
+
+use std::io::Cursor;
+
+use protocol::Encode;
+use protocol::debug_protocol::DebugProtocol;
+use transport::RwTransport;
+
+/// Renders `value` as indented, IDL-like text including field ids.
+///
+/// Unlike the derived `Debug` output this shows exactly what would be put on
+/// the wire: fields that would not be encoded are left out.
+pub fn pretty<E: Encode>(value: &E) -> String {
+    let mut transport = RwTransport(Cursor::new(Vec::new()));
+    let result = value.encode(&mut DebugProtocol::new(), &mut transport);
+    let mut text = String::from_utf8_lossy(transport.0.get_ref()).into_owned();
+    if let Err(e) = result {
+        text.push_str(&format!("<error: {}>", e));
+    }
+    text
+}
@@ -0,0 +1,269 @@
+//! Static descriptions of the types generated by `strukt!` and `enom!`.
+//!
+//! These let generic tools enumerate fields and enum values at runtime,
+//! without knowing the generated types.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::{mem, ptr};
+
+use protocol::{self, Type};
+use value::Value;
+use {Error, Result};
+
+/// The Thrift type of a field or container element.
+///
+/// Structs and enums are referred to through their descriptor functions,
+/// so that recursive types can be described.
+#[derive(Copy, Clone, Debug)]
+pub enum TypeDescriptor {
+    Void,
+    Bool,
+    Byte,
+    I16,
+    I32,
+    I64,
+    Double,
+    String,
+    Binary,
+    Struct(fn() -> &'static StructDescriptor),
+    Enum(fn() -> &'static EnumDescriptor),
+    List(&'static TypeDescriptor),
+    Set(&'static TypeDescriptor),
+    Map(&'static TypeDescriptor, &'static TypeDescriptor),
+}
+
+impl TypeDescriptor {
+    /// The type used on the wire.
+    pub fn typ(&self) -> Type {
+        match *self {
+            TypeDescriptor::Void => Type::Void,
+            TypeDescriptor::Bool => Type::Bool,
+            TypeDescriptor::Byte => Type::Byte,
+            TypeDescriptor::I16 => Type::I16,
+            TypeDescriptor::I32 | TypeDescriptor::Enum(_) => Type::I32,
+            TypeDescriptor::I64 => Type::I64,
+            TypeDescriptor::Double => Type::Double,
+            TypeDescriptor::String | TypeDescriptor::Binary => Type::String,
+            TypeDescriptor::Struct(_) => Type::Struct,
+            TypeDescriptor::List(_) => Type::List,
+            TypeDescriptor::Set(_) => Type::Set,
+            TypeDescriptor::Map(..) => Type::Map,
+        }
+    }
+}
+
+impl PartialEq for TypeDescriptor {
+    fn eq(&self, other: &TypeDescriptor) -> bool {
+        use self::TypeDescriptor::*;
+
+        match (*self, *other) {
+            (Struct(a), Struct(b)) => ptr::eq(a(), b()),
+            (Enum(a), Enum(b)) => ptr::eq(a(), b()),
+            (List(a), List(b)) | (Set(a), Set(b)) => a == b,
+            (Map(ka, va), Map(kb, vb)) => ka == kb && va == vb,
+            (a, b) => mem::discriminant(&a) == mem::discriminant(&b),
+        }
+    }
+}
+
+/// Whether a field has to be present on the wire.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Requiredness {
+    Required,
+    Optional,
+    /// Always written, but accepted when missing.
+    Default,
+}
+
+/// Whether field `name` of a `strukt!` is one of its required fields.
+#[doc(hidden)]
+pub const fn is_required(name: &str, required: &[&str]) -> bool {
+    let mut i = 0;
+    while i < required.len() {
+        if str_eq(name, required[i]) {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+/// The requiredness of field `name` of a `strukt!`, given its required fields.
+#[doc(hidden)]
+pub const fn requiredness(name: &str, required: &[&str], optional: bool) -> Requiredness {
+    if is_required(name, required) {
+        Requiredness::Required
+    } else if optional {
+        Requiredness::Optional
+    } else {
+        Requiredness::Default
+    }
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+#[derive(Debug)]
+pub struct FieldDescriptor {
+    pub name: &'static str,
+    pub id: i16,
+    pub typ: TypeDescriptor,
+    pub requiredness: Requiredness,
+    /// Whether decoding fails when the field is missing.
+    pub required: bool,
+}
+
+#[derive(Debug)]
+pub struct StructDescriptor {
+    pub name: &'static str,
+    pub fields: &'static [FieldDescriptor],
+}
+
+impl StructDescriptor {
+    pub fn field(&self, id: i16) -> Option<&'static FieldDescriptor> {
+        self.fields.iter().find(|f| f.id == id)
+    }
+
+    pub fn field_by_name(&self, name: &str) -> Option<&'static FieldDescriptor> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+}
+
+#[derive(Debug)]
+pub struct EnumDescriptor {
+    pub name: &'static str,
+    pub values: &'static [(&'static str, i32)],
+}
+
+impl EnumDescriptor {
+    pub fn name_of(&self, value: i32) -> Option<&'static str> {
+        self.values.iter().find(|v| v.1 == value).map(|v| v.0)
+    }
+
+    pub fn value_of(&self, name: &str) -> Option<i32> {
+        self.values.iter().find(|v| v.0 == name).map(|v| v.1)
+    }
+}
+
+#[derive(Debug)]
+pub struct MethodDescriptor {
+    pub name: &'static str,
+    pub args: &'static [FieldDescriptor],
+    /// The return type, `Void` for methods returning nothing.
+    pub result: TypeDescriptor,
+    pub exceptions: &'static [FieldDescriptor],
+    pub oneway: bool,
+}
+
+#[derive(Debug)]
+pub struct ServiceDescriptor {
+    pub name: &'static str,
+    /// All methods, including those inherited from parent services.
+    pub methods: &'static [MethodDescriptor],
+}
+
+impl ServiceDescriptor {
+    pub fn method(&self, name: &str) -> Option<&'static MethodDescriptor> {
+        self.methods.iter().find(|m| m.name == name)
+    }
+}
+
+/// Types which can appear as fields of a `strukt!`.
+pub trait Described {
+    const DESCRIPTOR: TypeDescriptor;
+
+    /// Whether the type makes a field optional, which only `Option` does.
+    const OPTIONAL: bool = false;
+}
+
+/// Implemented by `strukt!` types.
+pub trait ThriftStruct {
+    fn descriptor() -> &'static StructDescriptor;
+
+    /// The field with the given id, or `None` if there is none or it is unset.
+    fn get_field(&self, id: i16) -> Option<Value>;
+
+    /// Replaces the field with the given id. Fails with
+    /// `Error::ProtocolViolation` if there is no such field or `value` has
+    /// the wrong type.
+    fn set_field(&mut self, id: i16, value: Value) -> Result<()>;
+
+    /// Unsets the optional field with the given id, so that it is no longer
+    /// encoded. Fails with `Error::ProtocolViolation` if there is no such
+    /// field or it is not optional.
+    fn clear_field(&mut self, id: i16) -> Result<()>;
+
+    fn get_field_by_name(&self, name: &str) -> Option<Value> where Self: Sized {
+        Self::descriptor().field_by_name(name).and_then(|f| self.get_field(f.id))
+    }
+
+    fn set_field_by_name(&mut self, name: &str, value: Value) -> Result<()> where Self: Sized {
+        match Self::descriptor().field_by_name(name) {
+            Some(f) => self.set_field(f.id, value),
+            None => Err(Error::from(protocol::Error::ProtocolViolation)),
+        }
+    }
+
+    fn clear_field_by_name(&mut self, name: &str) -> Result<()> where Self: Sized {
+        match Self::descriptor().field_by_name(name) {
+            Some(f) => self.clear_field(f.id),
+            None => Err(Error::from(protocol::Error::ProtocolViolation)),
+        }
+    }
+}
+
+/// Implemented by the processors and clients generated by `service!`.
+pub trait ThriftService {
+    fn descriptor() -> &'static ServiceDescriptor;
+}
+
+/// Implemented by `enom!` types.
+pub trait ThriftEnum {
+    fn descriptor() -> &'static EnumDescriptor;
+}
+
+macro_rules! described {
+    ($($T:ty => $desc:ident),*) => {
+        $(impl Described for $T {
+            const DESCRIPTOR: TypeDescriptor = TypeDescriptor::$desc;
+        })*
+    }
+}
+
+described! {
+    () => Void, bool => Bool, i8 => Byte, i16 => I16, i32 => I32, i64 => I64,
+    f64 => Double, String => String, str => String, Vec<u8> => Binary, [u8] => Binary
+}
+
+impl<'a, T: ?Sized + Described> Described for &'a T {
+    const DESCRIPTOR: TypeDescriptor = T::DESCRIPTOR;
+}
+
+impl<T: Described> Described for Option<T> {
+    const DESCRIPTOR: TypeDescriptor = T::DESCRIPTOR;
+    const OPTIONAL: bool = true;
+}
+
+impl<T: Described> Described for Vec<T> {
+    const DESCRIPTOR: TypeDescriptor = TypeDescriptor::List(&T::DESCRIPTOR);
+}
+
+impl<T: Described> Described for BTreeSet<T> {
+    const DESCRIPTOR: TypeDescriptor = TypeDescriptor::Set(&T::DESCRIPTOR);
+}
+
+impl<K: Described, V: Described> Described for BTreeMap<K, V> {
+    const DESCRIPTOR: TypeDescriptor = TypeDescriptor::Map(&K::DESCRIPTOR, &V::DESCRIPTOR);
+}
@@ -0,0 +1,50 @@
+//! Errors reported by the Thrift machinery itself rather than by handlers.
+
+use std::error::Error as StdError;
+use std::fmt;
+
+enom! {
+    name = ApplicationExceptionKind,
+    values = [
+        Unknown = 0,
+        UnknownMethod = 1,
+        InvalidMessageType = 2,
+        WrongMethodName = 3,
+        BadSequenceId = 4,
+        MissingResult = 5,
+        InternalError = 6,
+        ProtocolError = 7,
+        InvalidTransform = 8,
+        InvalidProtocol = 9,
+        UnsupportedClientType = 10,
+    ],
+    default = Unknown,
+    // Kinds added by newer peers still let the exception be decoded.
+    unknown = Unknown
+}
+
+strukt! {
+    name = ApplicationException,
+    fields = {
+        message: String => 1,
+        kind: ApplicationExceptionKind => 2,
+    }
+}
+
+impl ApplicationException {
+    pub fn new<S: Into<String>>(kind: ApplicationExceptionKind, message: S) -> ApplicationException {
+        ApplicationException { message: message.into(), kind: kind }
+    }
+}
+
+impl fmt::Display for ApplicationException {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}: {}", self.kind, self.message)
+    }
+}
+
+impl StdError for ApplicationException {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
@@ -1,9 +1,15 @@
-pub use protocol::{self, Encode, Decode, Type, ThriftTyped};
+pub use protocol::{self, Encode, Decode, DecodeBorrowed, Type, ThriftTyped};
 pub use {Protocol, Transport, Result, Error};
 
 pub use std::collections::{BTreeSet, BTreeMap};
 
+use std::{cmp, str};
+
 use protocol::helpers::typ;
+use transport::slice::SliceTransport;
+
+/// The most elements reserved up front when decoding a list.
+const MAX_PREALLOCATED_ELEMENTS: usize = 4096;
 
 impl ThriftTyped for bool { fn typ(&self) -> Type { Type::Bool } }
 impl ThriftTyped for i8  { fn typ(&self) -> Type { Type::Byte } }
@@ -14,6 +20,8 @@ impl ThriftTyped for f64 { fn typ(&self) -> Type { Type::Double } }
 impl ThriftTyped for () { fn typ(&self) -> Type { Type::Void } }
 impl ThriftTyped for String { fn typ(&self) -> Type { Type::String } }
 impl ThriftTyped for Vec<u8> { fn typ(&self) -> Type { Type::String } }
+impl ThriftTyped for str { fn typ(&self) -> Type { Type::String } }
+impl ThriftTyped for [u8] { fn typ(&self) -> Type { Type::String } }
 impl<T: ThriftTyped> ThriftTyped for Vec<T> { fn typ(&self) -> Type { Type::List } }
 impl<T: ThriftTyped + Default> ThriftTyped for Option<T> { fn typ(&self) -> Type { typ::<T>() } }
 impl<T: ThriftTyped> ThriftTyped for BTreeSet<T> { fn typ(&self) -> Type { Type::Set } }
@@ -95,6 +103,22 @@ impl Encode for Vec<u8> {
     }
 }
 
+impl Encode for str {
+    fn encode<P, T>(&self, protocol: &mut P, transport: &mut T) -> Result<()>
+    where P: Protocol, T: Transport {
+        try!(protocol.write_str(transport, self));
+        Ok(())
+    }
+}
+
+impl Encode for [u8] {
+    fn encode<P, T>(&self, protocol: &mut P, transport: &mut T) -> Result<()>
+    where P: Protocol, T: Transport {
+        try!(protocol.write_binary(transport, self));
+        Ok(())
+    }
+}
+
 impl Encode for () {
     fn should_encode(&self) -> bool {
         false
@@ -123,18 +147,20 @@ prim_encode! {
 
 fn decode<D, P, T>(protocol: &mut P, transport: &mut T) -> Result<D>
 where D: Decode, P: Protocol, T: Transport {
-     let mut elem = D::default();
-     try!(elem.decode(protocol, transport));
-     Ok(elem)
+    let mut elem = D::default();
+    try!(elem.decode(protocol, transport));
+    Ok(elem)
 }
 
 impl<X: Decode> Decode for Vec<X> {
     fn decode<P, T>(&mut self, protocol: &mut P, transport: &mut T) -> Result<()>
     where P: Protocol, T: Transport {
         let (type_, len) = try!(protocol.read_list_begin(transport));
+        let len = try!(protocol.limits().container_len(len as i64));
 
         if type_ == typ::<X>() {
-            self.reserve(len as usize);
+            // The length is untrusted, so only preallocate a bounded amount.
+            self.reserve(cmp::min(len, MAX_PREALLOCATED_ELEMENTS));
             for _ in 0..len { self.push(try!(decode(protocol, transport))); }
             try!(protocol.read_list_end(transport));
             Ok(())
@@ -148,6 +174,7 @@ impl<X: Decode + Ord> Decode for BTreeSet<X> {
     fn decode<P, T>(&mut self, protocol: &mut P, transport: &mut T) -> Result<()>
     where P: Protocol, T: Transport {
         let (type_, len) = try!(protocol.read_set_begin(transport));
+        let len = try!(protocol.limits().container_len(len as i64));
 
         if type_ == typ::<X>() {
             for _ in 0..len { self.insert(try!(decode(protocol, transport))); }
@@ -163,8 +190,10 @@ impl<K: Decode + Ord, V: Decode> Decode for BTreeMap<K, V> {
     fn decode<P, T>(&mut self, protocol: &mut P, transport: &mut T) -> Result<()>
     where P: Protocol, T: Transport {
         let (ktyp, vtyp, len) = try!(protocol.read_map_begin(transport));
+        let len = try!(protocol.limits().container_len(len as i64));
 
-        if ktyp == typ::<K>() && vtyp == typ::<V>() {
+        // The compact protocol leaves out the key and value types of empty maps.
+        if (ktyp == typ::<K>() && vtyp == typ::<V>()) || len == 0 {
             for _ in 0..len {
                 let key = try!(decode(protocol, transport));
                 let value = try!(decode(protocol, transport));
@@ -213,3 +242,92 @@ prim_decode! {
     Vec<u8> => read_binary
 }
 
+
+fn decode_borrowed<'a, D, P>(protocol: &mut P, transport: &mut SliceTransport<'a>) -> Result<D>
+where D: DecodeBorrowed<'a>, P: Protocol {
+    let mut elem = D::default();
+    try!(elem.decode_borrowed(protocol, transport));
+    Ok(elem)
+}
+
+impl<'a> DecodeBorrowed<'a> for &'a [u8] {
+    fn decode_borrowed<P>(&mut self, protocol: &mut P, transport: &mut SliceTransport<'a>) -> Result<()>
+    where P: Protocol {
+        *self = try!(protocol.read_borrowed_binary(transport));
+        Ok(())
+    }
+}
+
+impl<'a> DecodeBorrowed<'a> for &'a str {
+    fn decode_borrowed<P>(&mut self, protocol: &mut P, transport: &mut SliceTransport<'a>) -> Result<()>
+    where P: Protocol {
+        let bytes = try!(protocol.read_borrowed_binary(transport));
+        *self = try!(str::from_utf8(bytes).map_err(protocol::Error::from));
+        Ok(())
+    }
+}
+
+impl<'a, X: DecodeBorrowed<'a>> DecodeBorrowed<'a> for Vec<X> {
+    fn decode_borrowed<P>(&mut self, protocol: &mut P, transport: &mut SliceTransport<'a>) -> Result<()>
+    where P: Protocol {
+        let (type_, len) = try!(protocol.read_list_begin(transport));
+        let len = try!(protocol.limits().container_len(len as i64));
+
+        if type_ == typ::<X>() {
+            self.reserve(cmp::min(len, MAX_PREALLOCATED_ELEMENTS));
+            for _ in 0..len { self.push(try!(decode_borrowed(protocol, transport))); }
+            try!(protocol.read_list_end(transport));
+            Ok(())
+        } else {
+            Err(Error::from(protocol::Error::ProtocolViolation))
+        }
+    }
+}
+
+impl<'a, X: DecodeBorrowed<'a> + Ord> DecodeBorrowed<'a> for BTreeSet<X> {
+    fn decode_borrowed<P>(&mut self, protocol: &mut P, transport: &mut SliceTransport<'a>) -> Result<()>
+    where P: Protocol {
+        let (type_, len) = try!(protocol.read_set_begin(transport));
+        let len = try!(protocol.limits().container_len(len as i64));
+
+        if type_ == typ::<X>() {
+            for _ in 0..len { self.insert(try!(decode_borrowed(protocol, transport))); }
+            try!(protocol.read_set_end(transport));
+            Ok(())
+        } else {
+            Err(Error::from(protocol::Error::ProtocolViolation))
+        }
+    }
+}
+
+impl<'a, K: DecodeBorrowed<'a> + Ord, V: DecodeBorrowed<'a>> DecodeBorrowed<'a> for BTreeMap<K, V> {
+    fn decode_borrowed<P>(&mut self, protocol: &mut P, transport: &mut SliceTransport<'a>) -> Result<()>
+    where P: Protocol {
+        let (ktyp, vtyp, len) = try!(protocol.read_map_begin(transport));
+        let len = try!(protocol.limits().container_len(len as i64));
+
+        // The compact protocol leaves out the key and value types of empty maps.
+        if (ktyp == typ::<K>() && vtyp == typ::<V>()) || len == 0 {
+            for _ in 0..len {
+                let key = try!(decode_borrowed(protocol, transport));
+                let value = try!(decode_borrowed(protocol, transport));
+                self.insert(key, value);
+            }
+
+            try!(protocol.read_map_end(transport));
+            Ok(())
+        } else {
+            Err(Error::from(protocol::Error::ProtocolViolation))
+        }
+    }
+}
+
+impl<'a, X: DecodeBorrowed<'a>> DecodeBorrowed<'a> for Option<X> {
+    fn decode_borrowed<P>(&mut self, protocol: &mut P, transport: &mut SliceTransport<'a>) -> Result<()>
+    where P: Protocol {
+        *self = Some(try!(decode_borrowed(protocol, transport)));
+        Ok(())
+    }
+}
+
+decode_borrowed_owned! { bool, i8, i16, i32, i64, f64, (), String, Vec<u8> }
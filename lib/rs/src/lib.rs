@@ -1,6 +1,7 @@
 #![recursion_limit="1024"]
 extern crate podio;
 extern crate ordered_float;
+extern crate flate2;
 
 #[macro_use]
 extern crate log;
@@ -8,12 +9,19 @@ extern crate log;
 #[cfg(feature = "redis")]
 extern crate redis;
 
+#[cfg(feature = "async")]
+extern crate tokio;
+
+#[cfg(feature = "nonblocking")]
+extern crate mio;
+
 use std::{io, fmt};
 use std::error::Error as StdError;
 
 pub use protocol::Protocol;
 pub use transport::Transport;
 pub use processor::Processor;
+pub use exception::ApplicationException;
 
 pub mod rt {
     pub use ordered_float::OrderedFloat;
@@ -23,8 +31,14 @@ pub mod protocol;
 pub mod transport;
 pub mod server;
 pub mod processor;
+pub mod client;
+#[cfg(feature = "async")]
+pub mod asynk;
 pub mod proxy;
 pub mod virt;
+pub mod debug;
+pub mod value;
+pub mod descriptor;
 
 #[macro_use]
 mod customtraits;
@@ -33,6 +47,8 @@ mod codegen;
 mod impls;
 mod compiletest;
 
+pub mod exception;
+
 #[cfg(test)]
 mod mock;
 #[cfg(test)]
@@ -47,7 +63,13 @@ pub enum Error {
     /// (this usually indicates a bug in the library)
     ProtocolError(protocol::Error),
 
-    /// The server code threw a user-defined exception
+    /// The peer answered with an `ApplicationException`, e.g. because the
+    /// method is unknown to it or its handler failed
+    ApplicationException(ApplicationException),
+
+    /// No longer returned: exceptions sent by the peer are reported as
+    /// `ApplicationException`
+    #[deprecated(note = "exceptions are reported as `Error::ApplicationException`")]
     UserException,
 }
 
@@ -57,8 +79,20 @@ impl From<protocol::Error> for Error {
     }
 }
 
+impl From<ApplicationException> for Error {
+    fn from(err: ApplicationException) -> Error {
+        Error::ApplicationException(err)
+    }
+}
+
 impl From<io::Error> for Error {
+    /// Protocol errors raised by transports, like `LimitExceeded`, are
+    /// reported as `ProtocolError`.
     fn from(err: io::Error) -> Error {
+        if err.get_ref().map_or(false, |inner| inner.is::<protocol::Error>()) {
+            let inner = err.into_inner().unwrap().downcast::<protocol::Error>().unwrap();
+            return Error::ProtocolError(*inner);
+        }
         Error::TransportError(err)
     }
 }
@@ -72,7 +106,9 @@ impl StdError for Error {
         match *self {
             Error::TransportError(ref err) => Some(err),
             Error::ProtocolError(ref err) => Some(err),
-            _ => None
+            Error::ApplicationException(ref err) => Some(err),
+            #[allow(deprecated)]
+            Error::UserException => None,
         }
     }
 }
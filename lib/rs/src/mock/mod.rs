@@ -22,6 +22,10 @@ impl MockTransport {
             writer: Vec::new()
         }
     }
+
+    pub fn written(&self) -> &[u8] {
+        &self.writer
+    }
 }
 
 impl io::Write for MockTransport {
@@ -1,4 +1,9 @@
-use protocol::Protocol;
+use std::any::Any;
+use std::collections::BTreeMap;
+
+use exception::{ApplicationException, ApplicationExceptionKind};
+use protocol::{helpers, MessageType, Protocol, Type};
+use protocol::multiplexed_protocol::SEPARATOR;
 use transport::Transport;
 use Result;
 
@@ -6,3 +11,107 @@ pub trait Processor<P: Protocol, T: Transport> {
     fn process(&self, prot: &mut P, transport: &mut T) -> Result<()>;
 }
 
+/// Handles a single message whose header has already been read.
+///
+/// Implemented by every processor generated with `service!`.
+pub trait Dispatcher<P: Protocol, T: Transport> {
+    fn dispatch(&self, prot: &mut P, transport: &mut T,
+                name: &str, ty: MessageType, id: i32) -> Result<()>;
+}
+
+/// Answers the call `name` with `error`. Oneway calls get no answer, as
+/// there is nobody waiting for it.
+pub fn reply_exception<P, T>(prot: &mut P, transport: &mut T, name: &str,
+                             ty: MessageType, id: i32, error: &ApplicationException) -> Result<()>
+where P: Protocol, T: Transport {
+    if ty == MessageType::Oneway {
+        return Ok(());
+    }
+    helpers::send(prot, transport, name, MessageType::Exception, error, id)
+}
+
+/// Skips the arguments of a call to a method the processor doesn't know,
+/// and answers it with `UnknownMethod`.
+pub fn unknown_method<P, T>(prot: &mut P, transport: &mut T, name: &str,
+                            ty: MessageType, id: i32) -> Result<()>
+where P: Protocol, T: Transport {
+    try!(prot.skip(transport, Type::Struct));
+    try!(prot.read_message_end(transport));
+    let error = ApplicationException::new(ApplicationExceptionKind::UnknownMethod,
+                                          format!("unknown method {}", name));
+    reply_exception(prot, transport, name, ty, id, &error)
+}
+
+/// The `InternalError` a processor answers with when the handler of
+/// `name` panicked with `payload`.
+pub fn internal_error(name: &str, payload: Box<Any + Send>) -> ApplicationException {
+    let reason = match payload.downcast::<String>() {
+        Ok(s) => *s,
+        Err(payload) => match payload.downcast::<&'static str>() {
+            Ok(s) => s.to_string(),
+            Err(_) => "unknown panic".to_string(),
+        },
+    };
+    ApplicationException::new(ApplicationExceptionKind::InternalError,
+                              format!("internal error processing {}: {}", name, reason))
+}
+
+type BoxedDispatcher<P, T> = Box<Dispatcher<P, T> + Send + Sync>;
+
+/// A processor serving several services over one connection.
+///
+/// Calls are routed by the `Service:` prefix of the method name, which is
+/// stripped before the registered processor sees the message, so replies
+/// carry the bare method name. Unprefixed calls from clients unaware of
+/// multiplexing go to the default processor, if there is one.
+pub struct MultiplexedProcessor<P, T> {
+    services: BTreeMap<String, BoxedDispatcher<P, T>>,
+    default: Option<BoxedDispatcher<P, T>>,
+}
+
+impl<P: Protocol, T: Transport> MultiplexedProcessor<P, T> {
+    pub fn new() -> MultiplexedProcessor<P, T> {
+        MultiplexedProcessor { services: BTreeMap::new(), default: None }
+    }
+
+    /// Register `processor` for calls prefixed with `service_name`,
+    /// replacing any processor previously registered under that name.
+    pub fn register<S, D>(&mut self, service_name: S, processor: D)
+    where S: Into<String>, D: Dispatcher<P, T> + Send + Sync + 'static {
+        self.services.insert(service_name.into(), Box::new(processor));
+    }
+
+    /// Register `processor` for calls without a service prefix.
+    pub fn register_default<D>(&mut self, processor: D)
+    where D: Dispatcher<P, T> + Send + Sync + 'static {
+        self.default = Some(Box::new(processor));
+    }
+}
+
+impl<P: Protocol, T: Transport> Default for MultiplexedProcessor<P, T> {
+    fn default() -> MultiplexedProcessor<P, T> {
+        MultiplexedProcessor::new()
+    }
+}
+
+impl<P: Protocol, T: Transport> Dispatcher<P, T> for MultiplexedProcessor<P, T> {
+    fn dispatch(&self, prot: &mut P, transport: &mut T,
+                name: &str, ty: MessageType, id: i32) -> Result<()> {
+        let (processor, method) = match name.find(SEPARATOR) {
+            Some(pos) => (self.services.get(&name[..pos]), &name[pos + SEPARATOR.len()..]),
+            None => (self.default.as_ref(), name),
+        };
+
+        match processor {
+            Some(processor) => processor.dispatch(prot, transport, method, ty, id),
+            None => unknown_method(prot, transport, name, ty, id),
+        }
+    }
+}
+
+impl<P: Protocol, T: Transport> Processor<P, T> for MultiplexedProcessor<P, T> {
+    fn process(&self, prot: &mut P, transport: &mut T) -> Result<()> {
+        let (name, ty, id) = try!(prot.read_message_begin(transport));
+        self.dispatch(prot, transport, &name, ty, id)
+    }
+}
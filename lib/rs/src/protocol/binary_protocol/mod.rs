@@ -18,17 +18,46 @@
  */
 
 use protocol::{self, MessageType, Protocol, Type};
+use protocol::limits::{Budget, DEFAULT_LIMITS, Limits};
 use transport::Transport;
+use transport::slice::SliceTransport;
 use {Result, Error};
 
 use podio::{ReadPodExt, WritePodExt, BigEndian};
 
 static BINARY_PROTOCOL_VERSION_1: u16 = 0x8001;
 
-#[derive(Copy, Clone, Debug)]
-pub struct BinaryProtocol;
+/// The binary protocol, optionally accepting and emitting the legacy
+/// unversioned message header.
+///
+/// Strict messages start with the `0x8001` version and the message type,
+/// followed by the name. Legacy peers instead write the name first, followed
+/// by a single type byte. `strict_read` controls whether unversioned messages
+/// are rejected with `Error::BadVersion`, and `strict_write` whether the
+/// versioned header is written. The default is strict in both directions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BinaryProtocol {
+    pub strict_read: bool,
+    pub strict_write: bool,
+    budget: Budget,
+}
+
+impl Default for BinaryProtocol {
+    fn default() -> BinaryProtocol {
+        BinaryProtocol::new(true, true)
+    }
+}
 
 impl BinaryProtocol {
+    pub const fn new(strict_read: bool, strict_write: bool) -> BinaryProtocol {
+        BinaryProtocol { strict_read: strict_read, strict_write: strict_write, budget: Budget::new(DEFAULT_LIMITS) }
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> BinaryProtocol {
+        self.budget = Budget::new(limits);
+        self
+    }
+
     fn write_type<T: Transport>(&mut self, transport: &mut T, type_: Type) -> Result<()> {
         self.write_byte(transport, type_ as i8)
     }
@@ -50,9 +79,14 @@ impl Protocol for BinaryProtocol {
         message_type: MessageType,
         sequence_id: i32
     ) -> Result<()> {
-        let version = ((BINARY_PROTOCOL_VERSION_1 as i32) << 16) | message_type as i32;
-        try!(self.write_i32(transport, version));
-        try!(self.write_str(transport, name));
+        if self.strict_write {
+            let version = ((BINARY_PROTOCOL_VERSION_1 as i32) << 16) | message_type as i32;
+            try!(self.write_i32(transport, version));
+            try!(self.write_str(transport, name));
+        } else {
+            try!(self.write_str(transport, name));
+            try!(self.write_byte(transport, message_type as i8));
+        }
         self.write_i32(transport, sequence_id)
     }
 
@@ -159,13 +193,23 @@ impl Protocol for BinaryProtocol {
     }
 
     fn read_message_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(String, MessageType, i32)> {
+        self.budget.begin_message();
         let header = try!(self.read_i32(transport));
-        let version = (header >> 16) as u16;
-        if version != BINARY_PROTOCOL_VERSION_1 {
+        let (name, raw_type) = if header < 0 {
+            let version = (header >> 16) as u16;
+            if version != BINARY_PROTOCOL_VERSION_1 {
+                return Err(Error::from(protocol::Error::BadVersion));
+            };
+            (try!(self.read_string(transport)), header & 0xff)
+        } else if self.strict_read {
             return Err(Error::from(protocol::Error::BadVersion));
+        } else {
+            // Legacy header: the leading i32 is the length of the name.
+            let len = try!(self.budget.string_len(header as i64));
+            let bytes = try!(ReadPodExt::read_exact(transport, len));
+            let name = try!(String::from_utf8(bytes).map_err(|e| protocol::Error::from(e.utf8_error())));
+            (name, try!(self.read_byte(transport)) as i32)
         };
-        let name = try!(self.read_string(transport));
-        let raw_type = header & 0xff;
         let message_type = match MessageType::from_num(raw_type as u64) {
             Some(t) => t,
             None => return Err(Error::from(protocol::Error::ProtocolViolation)),
@@ -175,14 +219,17 @@ impl Protocol for BinaryProtocol {
     }
 
     fn read_message_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        self.budget.end_message();
         Ok(())
     }
 
     fn read_struct_begin<T: Transport>(&mut self, _transport: &mut T) -> Result<String> {
+        try!(self.budget.enter());
         Ok(String::new())
     }
 
     fn read_struct_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        self.budget.leave();
         Ok(())
     }
 
@@ -203,30 +250,39 @@ impl Protocol for BinaryProtocol {
         let key_type = try!(self.read_type(transport));
         let value_type = try!(self.read_type(transport));
         let size = try!(self.read_i32(transport));
+        try!(self.budget.container_len(size as i64));
+        try!(self.budget.enter());
         Ok((key_type, value_type, size))
     }
 
     fn read_map_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        self.budget.leave();
         Ok(())
     }
 
     fn read_list_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(Type, i32)> {
         let elem_type = try!(self.read_type(transport));
         let size = try!(self.read_i32(transport));
+        try!(self.budget.container_len(size as i64));
+        try!(self.budget.enter());
         Ok((elem_type, size))
     }
 
     fn read_list_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        self.budget.leave();
         Ok(())
     }
 
     fn read_set_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(Type, i32)> {
         let elem_type = try!(self.read_type(transport));
         let size = try!(self.read_i32(transport));
+        try!(self.budget.container_len(size as i64));
+        try!(self.budget.enter());
         Ok((elem_type, size))
     }
 
     fn read_set_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        self.budget.leave();
         Ok(())
     }
 
@@ -238,22 +294,27 @@ impl Protocol for BinaryProtocol {
     }
 
     fn read_byte<T: Transport>(&mut self, transport: &mut T) -> Result<i8> {
+        try!(self.budget.consume(1));
         Ok(try!(transport.read_i8()))
     }
 
     fn read_i16<T: Transport>(&mut self, transport: &mut T) -> Result<i16> {
+        try!(self.budget.consume(2));
         Ok(try!(transport.read_i16::<BigEndian>()))
     }
 
     fn read_i32<T: Transport>(&mut self, transport: &mut T) -> Result<i32> {
+        try!(self.budget.consume(4));
         Ok(try!(transport.read_i32::<BigEndian>()))
     }
 
     fn read_i64<T: Transport>(&mut self, transport: &mut T) -> Result<i64> {
+        try!(self.budget.consume(8));
         Ok(try!(transport.read_i64::<BigEndian>()))
     }
 
     fn read_double<T: Transport>(&mut self, transport: &mut T) -> Result<f64> {
+        try!(self.budget.consume(8));
         Ok(try!(transport.read_f64::<BigEndian>()))
     }
 
@@ -263,58 +324,23 @@ impl Protocol for BinaryProtocol {
     }
 
     fn read_binary<T: Transport>(&mut self, transport: &mut T) -> Result<Vec<u8>> {
-        let len = try!(self.read_i32(transport)) as usize;
+        let len = try!(self.read_i32(transport));
+        let len = try!(self.budget.string_len(len as i64));
         Ok(try!(ReadPodExt::read_exact(transport, len)))
     }
 
     fn skip<T: Transport>(&mut self, transport: &mut T, type_: Type) -> Result<()> {
-        match type_ {
-            Type::Bool => { try!(self.read_bool(transport)); }
-            Type::Byte => { try!(self.read_byte(transport)); }
-            Type::I16 => { try!(self.read_i16(transport)); }
-            Type::I32 => { try!(self.read_i32(transport)); }
-            Type::I64 => { try!(self.read_i64(transport)); }
-            Type::Double => { try!(self.read_double(transport)); }
-            Type::String => { try!(self.read_binary(transport)); }
-            Type::Struct => {
-                try!(self.read_struct_begin(transport));
-                loop {
-                    let (_, field_type, _) = try!(self.read_field_begin(transport));
-                    if field_type == Type::Stop {
-                        break;
-                    }
-                    try!(self.skip(transport, field_type));
-                    try!(self.read_field_end(transport));
-                }
-                try!(self.read_struct_end(transport));
-            }
-            Type::Map => {
-                let (key_type, value_type, size) = try!(self.read_map_begin(transport));
-                for _ in 0..size {
-                    try!(self.skip(transport, key_type));
-                    try!(self.skip(transport, value_type));
-                }
-                try!(self.read_map_end(transport));
-            }
-            Type::Set => {
-                let (elem_type, size) = try!(self.read_set_begin(transport));
-                for _ in 0..size {
-                    try!(self.skip(transport, elem_type));
-                }
-                try!(self.read_set_end(transport));
-            }
-            Type::List => {
-                let (elem_type, size) = try!(self.read_list_begin(transport));
-                for _ in 0..size {
-                    try!(self.skip(transport, elem_type));
-                }
-                try!(self.read_list_end(transport));
-            }
-            Type::Void => { }
-            Type::Stop => { }
-        };
+        protocol::helpers::skip(self, transport, type_)
+    }
 
-        Ok(())
+    fn limits(&self) -> Limits {
+        self.budget.limits
+    }
+
+    fn read_borrowed_binary<'a>(&mut self, transport: &mut SliceTransport<'a>) -> Result<&'a [u8]> {
+        let len = try!(self.read_i32(transport));
+        let len = try!(self.budget.string_len(len as i64));
+        Ok(try!(transport.take(len)))
     }
 }
 
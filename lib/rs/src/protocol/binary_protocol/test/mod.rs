@@ -17,16 +17,19 @@
  * under the License.
  */
 
+use std::io::Cursor;
+
 use super::BinaryProtocol;
 
 use mock::MockTransport;
-use protocol::{self, Protocol};
+use protocol::{self, Limit, Limits, Protocol};
+use transport::RwTransport;
 use Error;
 
 #[test]
 fn read_bool() {
     let transport = &mut MockTransport::new(vec!(0x00, 0x01, 0xff));
-    let mut protocol = BinaryProtocol;
+    let mut protocol = BinaryProtocol::default();
     assert_eq!(protocol.read_bool(transport).unwrap(), false);
     assert_eq!(protocol.read_bool(transport).unwrap(), true);
     assert_eq!(protocol.read_bool(transport).unwrap(), true);
@@ -35,7 +38,7 @@ fn read_bool() {
 #[test]
 fn read_byte() {
     let transport = &mut MockTransport::new(vec!(0xa4, 0x27));
-    let mut protocol = BinaryProtocol;
+    let mut protocol = BinaryProtocol::default();
     assert_eq!(protocol.read_byte(transport).unwrap(), -0x5c);
     assert_eq!(protocol.read_byte(transport).unwrap(), 0x27);
 }
@@ -43,7 +46,7 @@ fn read_byte() {
 #[test]
 fn read_i16() {
     let transport = &mut MockTransport::new(vec!(0xf2, 0xf8, 0xa1, 0x40));
-    let mut protocol = BinaryProtocol;
+    let mut protocol = BinaryProtocol::default();
     assert_eq!(protocol.read_i16(transport).unwrap(), -0x0d08);
     assert_eq!(protocol.read_i16(transport).unwrap(), -0x5ec0);
 }
@@ -51,7 +54,7 @@ fn read_i16() {
 #[test]
 fn read_i32() {
     let transport = &mut MockTransport::new(vec!(0x27, 0xd0, 0x39, 0x49, 0xe5, 0xd8, 0xfe, 0x8b));
-    let mut protocol = BinaryProtocol;
+    let mut protocol = BinaryProtocol::default();
     assert_eq!(protocol.read_i32(transport).unwrap(), 0x27d03949);
     assert_eq!(protocol.read_i32(transport).unwrap(), -0x1a270175);
 }
@@ -62,7 +65,7 @@ fn read_i64() {
         0x27, 0xd0, 0x39, 0x49, 0xe5, 0xd8, 0xfe, 0x8b,
         0xa7, 0x2e, 0x82, 0xea, 0xd1, 0x28, 0x0b, 0xe2,
     ));
-    let mut protocol = BinaryProtocol;
+    let mut protocol = BinaryProtocol::default();
     assert_eq!(protocol.read_i64(transport).unwrap(), 0x27d03949e5d8fe8b);
     assert_eq!(protocol.read_i64(transport).unwrap(), -0x58d17d152ed7f41e);
 }
@@ -73,7 +76,7 @@ fn read_double() {
         0x40, 0xa9, 0x5e, 0xaf, 0x39, 0x4b, 0x7b, 0x29,
         0xbf, 0xe9, 0x3a, 0xe4, 0x21, 0xd3, 0x0e, 0x85,
     ));
-    let mut protocol = BinaryProtocol;
+    let mut protocol = BinaryProtocol::default();
     assert_eq!(protocol.read_double(transport).unwrap(), 3247.342234);
     assert_eq!(protocol.read_double(transport).unwrap(), -0.78843886);
 }
@@ -85,7 +88,7 @@ fn read_string() {
         0x00, 0x00, 0x00, 0x04, 0x41, 0x73, 0x64, 0x66,
         0x00, 0x00, 0x00, 0x0d, 0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x2c, 0x20, 0x57, 0x6f, 0x72, 0x6c, 0x64, 0x21,
     ));
-    let mut protocol = BinaryProtocol;
+    let mut protocol = BinaryProtocol::default();
     assert_eq!(&protocol.read_string(transport).unwrap(), "");
     assert_eq!(&protocol.read_string(transport).unwrap(), "Asdf");
     assert_eq!(&protocol.read_string(transport).unwrap(), "Hello, World!");
@@ -98,7 +101,7 @@ fn read_binary() {
         0x00, 0x00, 0x00, 0x04, 0x41, 0x73, 0x64, 0x66,
         0x00, 0x00, 0x00, 0x0d, 0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x2c, 0x20, 0x57, 0x6f, 0x72, 0x6c, 0x64, 0x21,
     ));
-    let mut protocol = BinaryProtocol;
+    let mut protocol = BinaryProtocol::default();
     assert_eq!(&protocol.read_binary(transport).unwrap(), &[]);
     assert_eq!(&protocol.read_binary(transport).unwrap(), &[0x41, 0x73, 0x64, 0x66]);
     assert_eq!(&protocol.read_binary(transport).unwrap(), &[0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x2c, 0x20, 0x57, 0x6f, 0x72, 0x6c, 0x64, 0x21]);
@@ -107,7 +110,7 @@ fn read_binary() {
 #[test]
 fn read_set_begin() {
     let transport = &mut MockTransport::new(vec!(0x0b, 0x00, 0x00, 0x01, 0x0f));
-    let mut protocol = BinaryProtocol;
+    let mut protocol = BinaryProtocol::default();
     assert_eq!(
         protocol.read_set_begin(transport).unwrap(),
         (protocol::Type::String, 0x0000010f)
@@ -117,7 +120,7 @@ fn read_set_begin() {
 #[test]
 fn read_list_begin() {
     let transport = &mut MockTransport::new(vec!(0x0b, 0x00, 0x00, 0x01, 0x0f));
-    let mut protocol = BinaryProtocol;
+    let mut protocol = BinaryProtocol::default();
     assert_eq!(
         protocol.read_list_begin(transport).unwrap(),
         (protocol::Type::String, 0x0000010f)
@@ -127,7 +130,7 @@ fn read_list_begin() {
 #[test]
 fn read_map_begin() {
     let transport = &mut MockTransport::new(vec!(0x0b, 0x08, 0x00, 0x00, 0x01, 0x0f));
-    let mut protocol = BinaryProtocol;
+    let mut protocol = BinaryProtocol::default();
     assert_eq!(
         protocol.read_map_begin(transport).unwrap(),
         (protocol::Type::String, protocol::Type::I32, 0x0000010f)
@@ -137,7 +140,7 @@ fn read_map_begin() {
 #[test]
 fn read_field_begin() {
     let transport = &mut MockTransport::new(vec!(0x00, 0x0d, 0x14, 0x0e));
-    let mut protocol = BinaryProtocol;
+    let mut protocol = BinaryProtocol::default();
     assert_eq!(
         protocol.read_field_begin(transport).unwrap(),
         ("".to_string(), protocol::Type::Stop, 0)
@@ -155,7 +158,7 @@ fn read_message_begin() {
         0x00, 0x00, 0x00, 0x03, 0x66, 0x6f, 0x6f,
         0x00, 0x02, 0x47, 0x1e
     ));
-    let mut protocol = BinaryProtocol;
+    let mut protocol = BinaryProtocol::default();
     assert_eq!(
         protocol.read_message_begin(transport).unwrap(),
         ("foo".to_string(), protocol::MessageType::Call, 0x0002471e)
@@ -169,7 +172,7 @@ fn read_message_begin_bad_version() {
         0x00, 0x00, 0x00, 0x03, 0x66, 0x6f, 0x6f,
         0x00, 0x02, 0x47, 0x1e
     ));
-    let mut protocol = BinaryProtocol;
+    let mut protocol = BinaryProtocol::default();
     let err = protocol.read_message_begin(transport).unwrap_err();
     match err {
         Error::ProtocolError(e) => assert_eq!(e, protocol::Error::BadVersion),
@@ -184,7 +187,7 @@ fn read_message_begin_invalid_message_type() {
         0x00, 0x00, 0x00, 0x03, 0x66, 0x6f, 0x6f,
         0x00, 0x02, 0x47, 0x1e
     ));
-    let mut protocol = BinaryProtocol;
+    let mut protocol = BinaryProtocol::default();
     let err = protocol.read_message_begin(transport).unwrap_err();
     match err {
         Error::ProtocolError(e) => assert_eq!(e, protocol::Error::ProtocolViolation),
@@ -192,3 +195,125 @@ fn read_message_begin_invalid_message_type() {
     }
 }
 
+
+#[test]
+fn write_message_begin_non_strict() {
+    let mut protocol = BinaryProtocol::new(true, false);
+    let mut transport = RwTransport(Cursor::new(Vec::new()));
+    protocol.write_message_begin(&mut transport, "foo", protocol::MessageType::Reply, 0x0002471e).unwrap();
+    assert_eq!(
+        (transport.0).into_inner(),
+        vec!(
+            0x00, 0x00, 0x00, 0x03, 0x66, 0x6f, 0x6f,
+            0x02,
+            0x00, 0x02, 0x47, 0x1e
+        )
+    );
+}
+
+#[test]
+fn read_message_begin_non_strict() {
+    let transport = &mut MockTransport::new(vec!(
+        0x00, 0x00, 0x00, 0x03, 0x66, 0x6f, 0x6f,
+        0x01,
+        0x00, 0x02, 0x47, 0x1e
+    ));
+    let mut protocol = BinaryProtocol::new(false, true);
+    assert_eq!(
+        protocol.read_message_begin(transport).unwrap(),
+        ("foo".to_string(), protocol::MessageType::Call, 0x0002471e)
+    );
+}
+
+#[test]
+fn read_message_begin_non_strict_accepts_versioned() {
+    let transport = &mut MockTransport::new(vec!(
+        0x80, 0x01, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x03, 0x66, 0x6f, 0x6f,
+        0x00, 0x02, 0x47, 0x1e
+    ));
+    let mut protocol = BinaryProtocol::new(false, true);
+    assert_eq!(
+        protocol.read_message_begin(transport).unwrap(),
+        ("foo".to_string(), protocol::MessageType::Call, 0x0002471e)
+    );
+}
+
+#[test]
+fn read_message_begin_strict_rejects_unversioned() {
+    let transport = &mut MockTransport::new(vec!(
+        0x00, 0x00, 0x00, 0x03, 0x66, 0x6f, 0x6f,
+        0x01,
+        0x00, 0x02, 0x47, 0x1e
+    ));
+    let mut protocol = BinaryProtocol::default();
+    let err = protocol.read_message_begin(transport).unwrap_err();
+    match err {
+        Error::ProtocolError(e) => assert_eq!(e, protocol::Error::BadVersion),
+        e => panic!("Expected a protocol error, got {:?}", e)
+    }
+}
+
+fn expect_error<R: ::std::fmt::Debug>(result: ::Result<R>, expected: protocol::Error) {
+    match result {
+        Err(Error::ProtocolError(e)) => assert_eq!(e, expected),
+        other => panic!("Expected {:?}, got {:?}", expected, other)
+    }
+}
+
+#[test]
+fn read_binary_negative_length() {
+    let transport = &mut MockTransport::new(vec!(0xff, 0xff, 0xff, 0xfe));
+    let mut protocol = BinaryProtocol::default();
+    expect_error(protocol.read_binary(transport), protocol::Error::ProtocolViolation);
+}
+
+#[test]
+fn read_binary_too_long() {
+    let transport = &mut MockTransport::new(vec!(0x00, 0x00, 0x00, 0x05, 0x61, 0x62, 0x63, 0x64, 0x65));
+    let mut limits = Limits::default();
+    limits.max_string_len = 4;
+    let mut protocol = BinaryProtocol::default().with_limits(limits);
+    expect_error(protocol.read_binary(transport), protocol::Error::LimitExceeded(Limit::StringLength));
+}
+
+#[test]
+fn read_list_too_large() {
+    let transport = &mut MockTransport::new(vec!(0x08, 0x7f, 0xff, 0xff, 0xff));
+    let mut limits = Limits::default();
+    limits.max_container_len = 1000;
+    let mut protocol = BinaryProtocol::default().with_limits(limits);
+    expect_error(protocol.read_list_begin(transport), protocol::Error::LimitExceeded(Limit::ContainerSize));
+}
+
+#[test]
+fn skip_too_deep() {
+    // A list of lists of lists of lists, each holding one element.
+    let mut bytes = Vec::new();
+    for _ in 0..4 {
+        bytes.extend_from_slice(&[0x0f, 0x00, 0x00, 0x00, 0x01]);
+    }
+    let mut limits = Limits::default();
+    limits.max_depth = 3;
+    let mut protocol = BinaryProtocol::default().with_limits(limits);
+    let transport = &mut MockTransport::new(bytes);
+    expect_error(protocol.skip(transport, protocol::Type::List), protocol::Error::LimitExceeded(Limit::Depth));
+}
+
+#[test]
+fn read_message_too_large() {
+    let transport = &mut MockTransport::new(vec!(
+        0x80, 0x01, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x03, 0x66, 0x6f, 0x6f,
+        0x00, 0x02, 0x47, 0x1e,
+        0x0a, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00
+    ));
+    let mut limits = Limits::default();
+    limits.max_message_size = 20;
+    let mut protocol = BinaryProtocol::default().with_limits(limits);
+    protocol.read_message_begin(transport).unwrap();
+    protocol.read_struct_begin(transport).unwrap();
+    protocol.read_field_begin(transport).unwrap();
+    expect_error(protocol.read_i64(transport), protocol::Error::LimitExceeded(Limit::MessageSize));
+}
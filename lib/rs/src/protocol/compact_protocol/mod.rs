@@ -0,0 +1,496 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use protocol::{self, MessageType, Protocol, Type};
+use protocol::limits::{Budget, Limits};
+use transport::Transport;
+use transport::slice::SliceTransport;
+use {Result, Error};
+
+use podio::{ReadPodExt, WritePodExt, LittleEndian};
+
+static COMPACT_PROTOCOL_ID: u8 = 0x82;
+static COMPACT_PROTOCOL_VERSION: u8 = 1;
+static COMPACT_VERSION_MASK: u8 = 0x1f;
+static COMPACT_TYPE_MASK: u8 = 0xe0;
+static COMPACT_TYPE_SHIFT: u8 = 5;
+
+/// Type identifiers as they appear on the wire in the compact protocol.
+mod compact_type {
+    pub const STOP: u8 = 0x00;
+    pub const BOOLEAN_TRUE: u8 = 0x01;
+    pub const BOOLEAN_FALSE: u8 = 0x02;
+    pub const BYTE: u8 = 0x03;
+    pub const I16: u8 = 0x04;
+    pub const I32: u8 = 0x05;
+    pub const I64: u8 = 0x06;
+    pub const DOUBLE: u8 = 0x07;
+    pub const BINARY: u8 = 0x08;
+    pub const LIST: u8 = 0x09;
+    pub const SET: u8 = 0x0a;
+    pub const MAP: u8 = 0x0b;
+    pub const STRUCT: u8 = 0x0c;
+}
+
+/// An implementation of Thrift's compact protocol (TCompactProtocol).
+///
+/// Unlike `BinaryProtocol` this protocol is stateful: field ids are written as
+/// deltas from the previous field of the enclosing struct and boolean fields
+/// are folded into their field header. A fresh instance should therefore be
+/// used for every connection.
+#[derive(Clone, Debug, Default)]
+pub struct CompactProtocol {
+    last_field_id: i16,
+    last_field_id_stack: Vec<i16>,
+    pending_write_bool_field: Option<i16>,
+    pending_read_bool_value: Option<bool>,
+    budget: Budget,
+}
+
+impl CompactProtocol {
+    pub fn new() -> CompactProtocol {
+        CompactProtocol::default()
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> CompactProtocol {
+        self.budget = Budget::new(limits);
+        self
+    }
+
+    fn compact_type(type_: Type) -> u8 {
+        match type_ {
+            Type::Stop => compact_type::STOP,
+            Type::Bool => compact_type::BOOLEAN_TRUE,
+            Type::Byte => compact_type::BYTE,
+            Type::I16 => compact_type::I16,
+            Type::I32 => compact_type::I32,
+            Type::I64 => compact_type::I64,
+            Type::Double => compact_type::DOUBLE,
+            Type::String => compact_type::BINARY,
+            Type::List => compact_type::LIST,
+            Type::Set => compact_type::SET,
+            Type::Map => compact_type::MAP,
+            Type::Struct => compact_type::STRUCT,
+            // Void never appears on the wire; it is only used for return types.
+            Type::Void => compact_type::STOP,
+        }
+    }
+
+    fn thrift_type(raw: u8) -> Result<Type> {
+        match raw & 0x0f {
+            compact_type::STOP => Ok(Type::Stop),
+            compact_type::BOOLEAN_TRUE | compact_type::BOOLEAN_FALSE => Ok(Type::Bool),
+            compact_type::BYTE => Ok(Type::Byte),
+            compact_type::I16 => Ok(Type::I16),
+            compact_type::I32 => Ok(Type::I32),
+            compact_type::I64 => Ok(Type::I64),
+            compact_type::DOUBLE => Ok(Type::Double),
+            compact_type::BINARY => Ok(Type::String),
+            compact_type::LIST => Ok(Type::List),
+            compact_type::SET => Ok(Type::Set),
+            compact_type::MAP => Ok(Type::Map),
+            compact_type::STRUCT => Ok(Type::Struct),
+            _ => Err(Error::from(protocol::Error::ProtocolViolation)),
+        }
+    }
+
+    fn write_u8<T: Transport>(&mut self, transport: &mut T, value: u8) -> Result<()> {
+        Ok(try!(transport.write_u8(value)))
+    }
+
+    fn read_u8<T: Transport>(&mut self, transport: &mut T) -> Result<u8> {
+        try!(self.budget.consume(1));
+        Ok(try!(transport.read_u8()))
+    }
+
+    fn write_varint<T: Transport>(&mut self, transport: &mut T, mut value: u64) -> Result<()> {
+        let mut buf = [0u8; 10];
+        let mut len = 0;
+        loop {
+            if value & !0x7f == 0 {
+                buf[len] = value as u8;
+                len += 1;
+                break;
+            }
+            buf[len] = ((value & 0x7f) | 0x80) as u8;
+            len += 1;
+            value >>= 7;
+        }
+        Ok(try!(transport.write_all(&buf[..len])))
+    }
+
+    fn read_varint<T: Transport>(&mut self, transport: &mut T) -> Result<u64> {
+        let mut result = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = try!(self.read_u8(transport));
+            result |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+            shift += 7;
+            if shift >= 64 {
+                return Err(Error::from(protocol::Error::ProtocolViolation));
+            }
+        }
+    }
+
+    fn write_field_header<T: Transport>(&mut self, transport: &mut T,
+                                        compact_type: u8, field_id: i16) -> Result<()> {
+        let delta = field_id.wrapping_sub(self.last_field_id);
+        if field_id > self.last_field_id && delta <= 15 {
+            try!(self.write_u8(transport, ((delta as u8) << 4) | compact_type));
+        } else {
+            try!(self.write_u8(transport, compact_type));
+            try!(self.write_i16(transport, field_id));
+        }
+        self.last_field_id = field_id;
+        Ok(())
+    }
+
+    fn write_collection_begin<T: Transport>(&mut self, transport: &mut T,
+                                            elem_type: Type, size: usize) -> Result<()> {
+        let elem_type = CompactProtocol::compact_type(elem_type);
+        if size <= 14 {
+            self.write_u8(transport, ((size as u8) << 4) | elem_type)
+        } else {
+            try!(self.write_u8(transport, 0xf0 | elem_type));
+            self.write_varint(transport, size as u64)
+        }
+    }
+
+    fn read_collection_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(Type, i32)> {
+        let header = try!(self.read_u8(transport));
+        let elem_type = try!(CompactProtocol::thrift_type(header));
+        let size = match header >> 4 {
+            0x0f => try!(self.read_size(transport)),
+            size => size as i32,
+        };
+        try!(self.budget.enter());
+        Ok((elem_type, size))
+    }
+
+    fn read_size<T: Transport>(&mut self, transport: &mut T) -> Result<i32> {
+        let size = try!(self.read_varint(transport));
+        if size > ::std::i32::MAX as u64 {
+            return Err(Error::from(protocol::Error::ProtocolViolation));
+        }
+        try!(self.budget.container_len(size as i64));
+        Ok(size as i32)
+    }
+}
+
+fn zigzag_encode(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn zigzag_decode(n: u64) -> i64 {
+    ((n >> 1) as i64) ^ -((n & 1) as i64)
+}
+
+impl Protocol for CompactProtocol {
+    fn write_message_begin<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        name: &str,
+        message_type: MessageType,
+        sequence_id: i32
+    ) -> Result<()> {
+        try!(self.write_u8(transport, COMPACT_PROTOCOL_ID));
+        let version = (COMPACT_PROTOCOL_VERSION & COMPACT_VERSION_MASK) |
+            (((message_type as u8) << COMPACT_TYPE_SHIFT) & COMPACT_TYPE_MASK);
+        try!(self.write_u8(transport, version));
+        try!(self.write_varint(transport, sequence_id as u32 as u64));
+        self.write_str(transport, name)
+    }
+
+    fn write_message_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_struct_begin<T: Transport>(&mut self, _transport: &mut T, _name: &str) -> Result<()> {
+        self.last_field_id_stack.push(self.last_field_id);
+        self.last_field_id = 0;
+        Ok(())
+    }
+
+    fn write_struct_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        self.last_field_id = self.last_field_id_stack.pop().unwrap_or(0);
+        Ok(())
+    }
+
+    fn write_field_begin<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        _name: &str,
+        field_type: Type,
+        field_id: i16
+    ) -> Result<()> {
+        if field_type == Type::Bool {
+            // The value is folded into the field header by `write_bool`.
+            self.pending_write_bool_field = Some(field_id);
+            Ok(())
+        } else {
+            self.write_field_header(transport, CompactProtocol::compact_type(field_type), field_id)
+        }
+    }
+
+    fn write_field_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_field_stop<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.write_u8(transport, compact_type::STOP)
+    }
+
+    fn write_map_begin<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        key_type: Type,
+        value_type: Type,
+        size: usize
+    ) -> Result<()> {
+        if size == 0 {
+            self.write_u8(transport, 0)
+        } else {
+            try!(self.write_varint(transport, size as u64));
+            let types = (CompactProtocol::compact_type(key_type) << 4) |
+                CompactProtocol::compact_type(value_type);
+            self.write_u8(transport, types)
+        }
+    }
+
+    fn write_map_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_list_begin<T: Transport>(&mut self, transport: &mut T, elem_type: Type, size: usize) -> Result<()> {
+        self.write_collection_begin(transport, elem_type, size)
+    }
+
+    fn write_list_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_set_begin<T: Transport>(&mut self, transport: &mut T, elem_type: Type, size: usize) -> Result<()> {
+        self.write_collection_begin(transport, elem_type, size)
+    }
+
+    fn write_set_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_bool<T: Transport>(&mut self, transport: &mut T, value: bool) -> Result<()> {
+        let compact_type = if value { compact_type::BOOLEAN_TRUE } else { compact_type::BOOLEAN_FALSE };
+        match self.pending_write_bool_field.take() {
+            Some(field_id) => self.write_field_header(transport, compact_type, field_id),
+            None => self.write_u8(transport, compact_type),
+        }
+    }
+
+    fn write_byte<T: Transport>(&mut self, transport: &mut T, value: i8) -> Result<()> {
+        Ok(try!(transport.write_i8(value)))
+    }
+
+    fn write_i16<T: Transport>(&mut self, transport: &mut T, value: i16) -> Result<()> {
+        self.write_varint(transport, zigzag_encode(value as i64))
+    }
+
+    fn write_i32<T: Transport>(&mut self, transport: &mut T, value: i32) -> Result<()> {
+        self.write_varint(transport, zigzag_encode(value as i64))
+    }
+
+    fn write_i64<T: Transport>(&mut self, transport: &mut T, value: i64) -> Result<()> {
+        self.write_varint(transport, zigzag_encode(value))
+    }
+
+    fn write_double<T: Transport>(&mut self, transport: &mut T, value: f64) -> Result<()> {
+        Ok(try!(transport.write_f64::<LittleEndian>(value)))
+    }
+
+    fn write_str<T: Transport>(&mut self, transport: &mut T, value: &str) -> Result<()> {
+        self.write_binary(transport, value.as_bytes())
+    }
+
+    fn write_string<T: Transport>(&mut self, transport: &mut T, value: &String) -> Result<()> {
+        self.write_binary(transport, (&value[..]).as_bytes())
+    }
+
+    fn write_binary<T: Transport>(&mut self, transport: &mut T, value: &[u8]) -> Result<()> {
+        try!(self.write_varint(transport, value.len() as u64));
+        Ok(try!(transport.write_all(value)))
+    }
+
+    fn read_message_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(String, MessageType, i32)> {
+        // Drop whatever nesting an earlier message that failed to decode left.
+        self.budget.begin_message();
+        self.last_field_id_stack.clear();
+        self.pending_read_bool_value = None;
+        let protocol_id = try!(self.read_u8(transport));
+        if protocol_id != COMPACT_PROTOCOL_ID {
+            return Err(Error::from(protocol::Error::BadVersion));
+        }
+        let version_and_type = try!(self.read_u8(transport));
+        if version_and_type & COMPACT_VERSION_MASK != COMPACT_PROTOCOL_VERSION {
+            return Err(Error::from(protocol::Error::BadVersion));
+        }
+        let raw_type = (version_and_type & COMPACT_TYPE_MASK) >> COMPACT_TYPE_SHIFT;
+        let message_type = match MessageType::from_num(raw_type as u64) {
+            Some(t) => t,
+            None => return Err(Error::from(protocol::Error::ProtocolViolation)),
+        };
+        let sequence_id = try!(self.read_varint(transport)) as u32 as i32;
+        let name = try!(self.read_string(transport));
+        Ok((name, message_type, sequence_id))
+    }
+
+    fn read_message_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        self.budget.end_message();
+        Ok(())
+    }
+
+    fn read_struct_begin<T: Transport>(&mut self, _transport: &mut T) -> Result<String> {
+        try!(self.budget.enter());
+        self.last_field_id_stack.push(self.last_field_id);
+        self.last_field_id = 0;
+        Ok(String::new())
+    }
+
+    fn read_struct_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        self.budget.leave();
+        self.last_field_id = self.last_field_id_stack.pop().unwrap_or(0);
+        Ok(())
+    }
+
+    fn read_field_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(String, Type, i16)> {
+        let header = try!(self.read_u8(transport));
+        let field_type = try!(CompactProtocol::thrift_type(header));
+        if field_type == Type::Stop {
+            return Ok((String::new(), Type::Stop, 0));
+        }
+
+        let field_id = match header >> 4 {
+            0 => try!(self.read_i16(transport)),
+            delta => self.last_field_id.wrapping_add(delta as i16),
+        };
+        self.last_field_id = field_id;
+
+        if field_type == Type::Bool {
+            self.pending_read_bool_value = Some(header & 0x0f == compact_type::BOOLEAN_TRUE);
+        }
+
+        Ok((String::new(), field_type, field_id))
+    }
+
+    fn read_field_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_map_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(Type, Type, i32)> {
+        let size = try!(self.read_size(transport));
+        let (key_type, value_type) = if size == 0 {
+            (Type::Stop, Type::Stop)
+        } else {
+            let types = try!(self.read_u8(transport));
+            (try!(CompactProtocol::thrift_type(types >> 4)), try!(CompactProtocol::thrift_type(types)))
+        };
+        try!(self.budget.enter());
+        Ok((key_type, value_type, size))
+    }
+
+    fn read_map_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        self.budget.leave();
+        Ok(())
+    }
+
+    fn read_list_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(Type, i32)> {
+        self.read_collection_begin(transport)
+    }
+
+    fn read_list_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        self.budget.leave();
+        Ok(())
+    }
+
+    fn read_set_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(Type, i32)> {
+        self.read_collection_begin(transport)
+    }
+
+    fn read_set_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        self.budget.leave();
+        Ok(())
+    }
+
+    fn read_bool<T: Transport>(&mut self, transport: &mut T) -> Result<bool> {
+        match self.pending_read_bool_value.take() {
+            Some(value) => Ok(value),
+            None => Ok(try!(self.read_u8(transport)) == compact_type::BOOLEAN_TRUE),
+        }
+    }
+
+    fn read_byte<T: Transport>(&mut self, transport: &mut T) -> Result<i8> {
+        try!(self.budget.consume(1));
+        Ok(try!(transport.read_i8()))
+    }
+
+    fn read_i16<T: Transport>(&mut self, transport: &mut T) -> Result<i16> {
+        Ok(zigzag_decode(try!(self.read_varint(transport))) as i16)
+    }
+
+    fn read_i32<T: Transport>(&mut self, transport: &mut T) -> Result<i32> {
+        Ok(zigzag_decode(try!(self.read_varint(transport))) as i32)
+    }
+
+    fn read_i64<T: Transport>(&mut self, transport: &mut T) -> Result<i64> {
+        Ok(zigzag_decode(try!(self.read_varint(transport))))
+    }
+
+    fn read_double<T: Transport>(&mut self, transport: &mut T) -> Result<f64> {
+        try!(self.budget.consume(8));
+        Ok(try!(transport.read_f64::<LittleEndian>()))
+    }
+
+    fn read_string<T: Transport>(&mut self, transport: &mut T) -> Result<String> {
+        let bytes = try!(self.read_binary(transport));
+        Ok(try!(String::from_utf8(bytes).map_err(|e| protocol::Error::from(e.utf8_error()))))
+    }
+
+    fn read_binary<T: Transport>(&mut self, transport: &mut T) -> Result<Vec<u8>> {
+        let len = try!(self.read_varint(transport));
+        let len = try!(self.budget.string_len(len as i64));
+        Ok(try!(ReadPodExt::read_exact(transport, len)))
+    }
+
+    fn skip<T: Transport>(&mut self, transport: &mut T, type_: Type) -> Result<()> {
+        protocol::helpers::skip(self, transport, type_)
+    }
+
+    fn limits(&self) -> Limits {
+        self.budget.limits
+    }
+
+    fn read_borrowed_binary<'a>(&mut self, transport: &mut SliceTransport<'a>) -> Result<&'a [u8]> {
+        let len = try!(self.read_varint(transport));
+        let len = try!(self.budget.string_len(len as i64));
+        Ok(try!(transport.take(len)))
+    }
+}
+
+#[cfg(test)]
+pub mod test;
@@ -0,0 +1,208 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::io::Cursor;
+
+use super::CompactProtocol;
+
+use mock::MockTransport;
+use protocol::{self, Limit, Limits, Protocol, Type, MessageType};
+use transport::RwTransport;
+use Error;
+
+fn written<F>(f: F) -> Vec<u8> where F: FnOnce(&mut CompactProtocol, &mut RwTransport<Cursor<Vec<u8>>>) {
+    let mut transport = RwTransport(Cursor::new(Vec::new()));
+    let mut protocol = CompactProtocol::new();
+    f(&mut protocol, &mut transport);
+    (transport.0).into_inner()
+}
+
+#[test]
+fn read_i32() {
+    let transport = &mut MockTransport::new(vec!(0x00, 0x01, 0x02, 0xac, 0x02, 0xfe, 0xff, 0xff, 0xff, 0x0f));
+    let mut protocol = CompactProtocol::new();
+    assert_eq!(protocol.read_i32(transport).unwrap(), 0);
+    assert_eq!(protocol.read_i32(transport).unwrap(), -1);
+    assert_eq!(protocol.read_i32(transport).unwrap(), 1);
+    assert_eq!(protocol.read_i32(transport).unwrap(), 150);
+    assert_eq!(protocol.read_i32(transport).unwrap(), 0x7fffffff);
+}
+
+#[test]
+fn read_i64() {
+    let transport = &mut MockTransport::new(vec!(
+        0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01,
+    ));
+    let mut protocol = CompactProtocol::new();
+    assert_eq!(protocol.read_i64(transport).unwrap(), 0x7fffffffffffffff);
+    assert_eq!(protocol.read_i64(transport).unwrap(), -0x8000000000000000);
+}
+
+#[test]
+fn write_integers() {
+    let bytes = written(|p, t| {
+        p.write_i16(t, -3).unwrap();
+        p.write_i32(t, 300).unwrap();
+        p.write_i64(t, -1).unwrap();
+    });
+    assert_eq!(bytes, vec!(0x05, 0xd8, 0x04, 0x01));
+}
+
+#[test]
+fn read_double() {
+    let transport = &mut MockTransport::new(vec!(0x29, 0x7b, 0x4b, 0x39, 0xaf, 0x5e, 0xa9, 0x40));
+    let mut protocol = CompactProtocol::new();
+    assert_eq!(protocol.read_double(transport).unwrap(), 3247.342234);
+}
+
+#[test]
+fn read_string() {
+    let transport = &mut MockTransport::new(vec!(0x00, 0x04, 0x41, 0x73, 0x64, 0x66));
+    let mut protocol = CompactProtocol::new();
+    assert_eq!(&protocol.read_string(transport).unwrap(), "");
+    assert_eq!(&protocol.read_string(transport).unwrap(), "Asdf");
+}
+
+#[test]
+fn write_field_headers() {
+    let bytes = written(|p, t| {
+        p.write_struct_begin(t, "").unwrap();
+        p.write_field_begin(t, "a", Type::I32, 1).unwrap();
+        p.write_i32(t, 1).unwrap();
+        p.write_field_begin(t, "b", Type::Bool, 2).unwrap();
+        p.write_bool(t, false).unwrap();
+        p.write_field_begin(t, "c", Type::String, 40).unwrap();
+        p.write_str(t, "").unwrap();
+        p.write_field_begin(t, "d", Type::Byte, 3).unwrap();
+        p.write_byte(t, 7).unwrap();
+        p.write_field_stop(t).unwrap();
+        p.write_struct_end(t).unwrap();
+    });
+    assert_eq!(bytes, vec!(0x15, 0x02, 0x12, 0x08, 0x50, 0x00, 0x03, 0x06, 0x07, 0x00));
+}
+
+#[test]
+fn read_field_begin() {
+    let transport = &mut MockTransport::new(vec!(0x15, 0x21, 0x08, 0x50, 0x00));
+    let mut protocol = CompactProtocol::new();
+    protocol.read_struct_begin(transport).unwrap();
+    assert_eq!(protocol.read_field_begin(transport).unwrap(), ("".to_string(), Type::I32, 1));
+    assert_eq!(protocol.read_field_begin(transport).unwrap(), ("".to_string(), Type::Bool, 3));
+    assert_eq!(protocol.read_bool(transport).unwrap(), true);
+    assert_eq!(protocol.read_field_begin(transport).unwrap(), ("".to_string(), Type::String, 40));
+    assert_eq!(protocol.read_field_begin(transport).unwrap(), ("".to_string(), Type::Stop, 0));
+}
+
+#[test]
+fn nested_struct_restores_field_id() {
+    let bytes = written(|p, t| {
+        p.write_struct_begin(t, "").unwrap();
+        p.write_field_begin(t, "a", Type::Struct, 5).unwrap();
+        p.write_struct_begin(t, "").unwrap();
+        p.write_field_begin(t, "b", Type::I32, 1).unwrap();
+        p.write_i32(t, 0).unwrap();
+        p.write_field_stop(t).unwrap();
+        p.write_struct_end(t).unwrap();
+        p.write_field_begin(t, "c", Type::I32, 6).unwrap();
+        p.write_i32(t, 0).unwrap();
+        p.write_field_stop(t).unwrap();
+        p.write_struct_end(t).unwrap();
+    });
+    assert_eq!(bytes, vec!(0x5c, 0x15, 0x00, 0x00, 0x15, 0x00, 0x00));
+}
+
+#[test]
+fn collection_headers() {
+    let bytes = written(|p, t| {
+        p.write_list_begin(t, Type::I32, 3).unwrap();
+        p.write_set_begin(t, Type::String, 20).unwrap();
+        p.write_map_begin(t, Type::String, Type::I64, 0).unwrap();
+        p.write_map_begin(t, Type::String, Type::I64, 2).unwrap();
+    });
+    assert_eq!(bytes, vec!(0x35, 0xf8, 0x14, 0x00, 0x02, 0x86));
+
+    let transport = &mut MockTransport::new(bytes);
+    let mut protocol = CompactProtocol::new();
+    assert_eq!(protocol.read_list_begin(transport).unwrap(), (Type::I32, 3));
+    assert_eq!(protocol.read_set_begin(transport).unwrap(), (Type::String, 20));
+    assert_eq!(protocol.read_map_begin(transport).unwrap().2, 0);
+    assert_eq!(protocol.read_map_begin(transport).unwrap(), (Type::String, Type::I64, 2));
+}
+
+#[test]
+fn message_begin_roundtrip() {
+    let bytes = written(|p, t| {
+        p.write_message_begin(t, "foo", MessageType::Reply, 0x0002471e).unwrap();
+    });
+    assert_eq!(bytes, vec!(0x82, 0x41, 0x9e, 0x8e, 0x09, 0x03, 0x66, 0x6f, 0x6f));
+
+    let transport = &mut MockTransport::new(bytes);
+    let mut protocol = CompactProtocol::new();
+    assert_eq!(
+        protocol.read_message_begin(transport).unwrap(),
+        ("foo".to_string(), MessageType::Reply, 0x0002471e)
+    );
+}
+
+#[test]
+fn read_message_begin_bad_protocol_id() {
+    let transport = &mut MockTransport::new(vec!(0x80, 0x01, 0x00, 0x00));
+    let mut protocol = CompactProtocol::new();
+    match protocol.read_message_begin(transport).unwrap_err() {
+        Error::ProtocolError(e) => assert_eq!(e, protocol::Error::BadVersion),
+        e => panic!("Expected a protocol error, got {:?}", e)
+    }
+}
+
+#[test]
+fn read_binary_too_long() {
+    let transport = &mut MockTransport::new(vec!(0x05, 0x61, 0x62, 0x63, 0x64, 0x65));
+    let mut limits = Limits::default();
+    limits.max_string_len = 4;
+    let mut protocol = CompactProtocol::new().with_limits(limits);
+    match protocol.read_binary(transport).unwrap_err() {
+        Error::ProtocolError(e) => assert_eq!(e, protocol::Error::LimitExceeded(Limit::StringLength)),
+        e => panic!("Expected a protocol error, got {:?}", e)
+    }
+}
+
+#[test]
+fn read_list_size_overflow() {
+    // A size varint larger than i32::MAX.
+    let transport = &mut MockTransport::new(vec!(0xf5, 0xff, 0xff, 0xff, 0xff, 0x0f));
+    let mut protocol = CompactProtocol::new();
+    match protocol.read_list_begin(transport).unwrap_err() {
+        Error::ProtocolError(e) => assert_eq!(e, protocol::Error::ProtocolViolation),
+        e => panic!("Expected a protocol error, got {:?}", e)
+    }
+}
+
+#[test]
+fn skip_too_deep() {
+    // Structs nested in field 1 of each other, without end.
+    let transport = &mut MockTransport::new(vec![0x1c; 100]);
+    let mut limits = Limits::default();
+    limits.max_depth = 10;
+    let mut protocol = CompactProtocol::new().with_limits(limits);
+    match protocol.skip(transport, Type::Struct).unwrap_err() {
+        Error::ProtocolError(e) => assert_eq!(e, protocol::Error::LimitExceeded(Limit::Depth)),
+        e => panic!("Expected a protocol error, got {:?}", e)
+    }
+}
@@ -0,0 +1,344 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::fmt::Write as FmtWrite;
+
+use protocol::{self, MessageType, Protocol, Type};
+use transport::Transport;
+use {Result, Error};
+
+static INDENT: &'static str = "  ";
+
+#[derive(Copy, Clone, Debug)]
+enum Frame {
+    Struct,
+    List,
+    Set,
+    Map { key_next: bool },
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Scope {
+    frame: Frame,
+    empty: bool,
+}
+
+/// A write-only protocol rendering values as indented, IDL-like text.
+///
+/// Structs are rendered with their field ids and names, e.g.
+///
+/// ```text
+/// Work {
+///   1: num1 = 15,
+///   3: op = 4,
+/// }
+/// ```
+///
+/// which makes it useful for diagnosing wire-compatibility issues. See
+/// `debug::pretty` for a convenient entry point. All read methods fail with
+/// `Error::NotImplemented`.
+#[derive(Clone, Debug, Default)]
+pub struct DebugProtocol {
+    scopes: Vec<Scope>,
+}
+
+impl DebugProtocol {
+    pub fn new() -> DebugProtocol {
+        DebugProtocol::default()
+    }
+
+    fn write_raw<T: Transport>(&mut self, transport: &mut T, text: &str) -> Result<()> {
+        Ok(try!(transport.write_all(text.as_bytes())))
+    }
+
+    fn indent(&self) -> String {
+        let mut indent = String::new();
+        for _ in 0..self.scopes.len() {
+            indent.push_str(INDENT);
+        }
+        indent
+    }
+
+    /// Starts a new line in the current scope, opening it if this is its first child.
+    fn new_line<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        let mut text = String::new();
+        if let Some(scope) = self.scopes.last_mut() {
+            if scope.empty {
+                scope.empty = false;
+                text.push('\n');
+            }
+        }
+        text.push_str(&self.indent());
+        self.write_raw(transport, &text)
+    }
+
+    fn begin_value<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        match self.scopes.last().map(|scope| scope.frame) {
+            Some(Frame::List) | Some(Frame::Set) | Some(Frame::Map { key_next: true }) => {
+                self.new_line(transport)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn end_value<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        let separator = match self.scopes.last_mut() {
+            Some(&mut Scope { frame: Frame::List, .. }) |
+            Some(&mut Scope { frame: Frame::Set, .. }) => ",\n",
+            Some(&mut Scope { frame: Frame::Map { ref mut key_next }, .. }) => {
+                *key_next = !*key_next;
+                if *key_next { ",\n" } else { " => " }
+            }
+            _ => "",
+        };
+        self.write_raw(transport, separator)
+    }
+
+    fn write_value<T: Transport>(&mut self, transport: &mut T, text: &str) -> Result<()> {
+        try!(self.begin_value(transport));
+        try!(self.write_raw(transport, text));
+        self.end_value(transport)
+    }
+
+    fn open<T: Transport>(&mut self, transport: &mut T, text: &str, frame: Frame) -> Result<()> {
+        try!(self.begin_value(transport));
+        try!(self.write_raw(transport, text));
+        self.scopes.push(Scope { frame: frame, empty: true });
+        Ok(())
+    }
+
+    fn close<T: Transport>(&mut self, transport: &mut T, text: &str) -> Result<()> {
+        let scope = match self.scopes.pop() {
+            Some(scope) => scope,
+            None => return Err(Error::from(protocol::Error::ProtocolViolation)),
+        };
+        let text = if scope.empty {
+            String::from(text)
+        } else {
+            self.indent() + text
+        };
+        try!(self.write_raw(transport, &text));
+        self.end_value(transport)
+    }
+}
+
+fn not_implemented<R>() -> Result<R> {
+    Err(Error::from(protocol::Error::NotImplemented))
+}
+
+impl Protocol for DebugProtocol {
+    fn write_message_begin<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        name: &str,
+        message_type: MessageType,
+        sequence_id: i32
+    ) -> Result<()> {
+        self.write_raw(transport, &format!("{} {}, seqid {}: ", message_type, name, sequence_id))
+    }
+
+    fn write_message_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_struct_begin<T: Transport>(&mut self, transport: &mut T, name: &str) -> Result<()> {
+        self.open(transport, &format!("{} {{", name), Frame::Struct)
+    }
+
+    fn write_struct_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.close(transport, "}")
+    }
+
+    fn write_field_begin<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        name: &str,
+        _field_type: Type,
+        field_id: i16
+    ) -> Result<()> {
+        try!(self.new_line(transport));
+        self.write_raw(transport, &format!("{}: {} = ", field_id, name))
+    }
+
+    fn write_field_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.write_raw(transport, ",\n")
+    }
+
+    fn write_field_stop<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_map_begin<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        _key_type: Type,
+        _value_type: Type,
+        _size: usize
+    ) -> Result<()> {
+        self.open(transport, "{", Frame::Map { key_next: true })
+    }
+
+    fn write_map_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.close(transport, "}")
+    }
+
+    fn write_list_begin<T: Transport>(&mut self, transport: &mut T, _elem_type: Type, _size: usize) -> Result<()> {
+        self.open(transport, "[", Frame::List)
+    }
+
+    fn write_list_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.close(transport, "]")
+    }
+
+    fn write_set_begin<T: Transport>(&mut self, transport: &mut T, _elem_type: Type, _size: usize) -> Result<()> {
+        self.open(transport, "{", Frame::Set)
+    }
+
+    fn write_set_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.close(transport, "}")
+    }
+
+    fn write_bool<T: Transport>(&mut self, transport: &mut T, value: bool) -> Result<()> {
+        self.write_value(transport, if value { "true" } else { "false" })
+    }
+
+    fn write_byte<T: Transport>(&mut self, transport: &mut T, value: i8) -> Result<()> {
+        self.write_value(transport, &value.to_string())
+    }
+
+    fn write_i16<T: Transport>(&mut self, transport: &mut T, value: i16) -> Result<()> {
+        self.write_value(transport, &value.to_string())
+    }
+
+    fn write_i32<T: Transport>(&mut self, transport: &mut T, value: i32) -> Result<()> {
+        self.write_value(transport, &value.to_string())
+    }
+
+    fn write_i64<T: Transport>(&mut self, transport: &mut T, value: i64) -> Result<()> {
+        self.write_value(transport, &value.to_string())
+    }
+
+    fn write_double<T: Transport>(&mut self, transport: &mut T, value: f64) -> Result<()> {
+        self.write_value(transport, &format!("{:?}", value))
+    }
+
+    fn write_str<T: Transport>(&mut self, transport: &mut T, value: &str) -> Result<()> {
+        self.write_value(transport, &format!("{:?}", value))
+    }
+
+    fn write_string<T: Transport>(&mut self, transport: &mut T, value: &String) -> Result<()> {
+        self.write_str(transport, value)
+    }
+
+    fn write_binary<T: Transport>(&mut self, transport: &mut T, value: &[u8]) -> Result<()> {
+        let mut hex = String::with_capacity(2 + value.len() * 2);
+        hex.push_str("0x");
+        for byte in value {
+            let _ = write!(hex, "{:02x}", byte);
+        }
+        self.write_value(transport, &hex)
+    }
+
+    fn read_message_begin<T: Transport>(&mut self, _transport: &mut T) -> Result<(String, MessageType, i32)> {
+        not_implemented()
+    }
+
+    fn read_message_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        not_implemented()
+    }
+
+    fn read_struct_begin<T: Transport>(&mut self, _transport: &mut T) -> Result<String> {
+        not_implemented()
+    }
+
+    fn read_struct_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        not_implemented()
+    }
+
+    fn read_field_begin<T: Transport>(&mut self, _transport: &mut T) -> Result<(String, Type, i16)> {
+        not_implemented()
+    }
+
+    fn read_field_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        not_implemented()
+    }
+
+    fn read_map_begin<T: Transport>(&mut self, _transport: &mut T) -> Result<(Type, Type, i32)> {
+        not_implemented()
+    }
+
+    fn read_map_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        not_implemented()
+    }
+
+    fn read_list_begin<T: Transport>(&mut self, _transport: &mut T) -> Result<(Type, i32)> {
+        not_implemented()
+    }
+
+    fn read_list_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        not_implemented()
+    }
+
+    fn read_set_begin<T: Transport>(&mut self, _transport: &mut T) -> Result<(Type, i32)> {
+        not_implemented()
+    }
+
+    fn read_set_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        not_implemented()
+    }
+
+    fn read_bool<T: Transport>(&mut self, _transport: &mut T) -> Result<bool> {
+        not_implemented()
+    }
+
+    fn read_byte<T: Transport>(&mut self, _transport: &mut T) -> Result<i8> {
+        not_implemented()
+    }
+
+    fn read_i16<T: Transport>(&mut self, _transport: &mut T) -> Result<i16> {
+        not_implemented()
+    }
+
+    fn read_i32<T: Transport>(&mut self, _transport: &mut T) -> Result<i32> {
+        not_implemented()
+    }
+
+    fn read_i64<T: Transport>(&mut self, _transport: &mut T) -> Result<i64> {
+        not_implemented()
+    }
+
+    fn read_double<T: Transport>(&mut self, _transport: &mut T) -> Result<f64> {
+        not_implemented()
+    }
+
+    fn read_string<T: Transport>(&mut self, _transport: &mut T) -> Result<String> {
+        not_implemented()
+    }
+
+    fn read_binary<T: Transport>(&mut self, _transport: &mut T) -> Result<Vec<u8>> {
+        not_implemented()
+    }
+
+    fn skip<T: Transport>(&mut self, _transport: &mut T, _type_: Type) -> Result<()> {
+        not_implemented()
+    }
+}
+
+#[cfg(test)]
+pub mod test;
@@ -0,0 +1,118 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::io::Cursor;
+
+use super::DebugProtocol;
+
+use mock::MockTransport;
+use protocol::{self, Protocol, Type, MessageType};
+use transport::RwTransport;
+use Error;
+
+fn written<F>(f: F) -> String where F: FnOnce(&mut DebugProtocol, &mut RwTransport<Cursor<Vec<u8>>>) {
+    let mut transport = RwTransport(Cursor::new(Vec::new()));
+    let mut protocol = DebugProtocol::new();
+    f(&mut protocol, &mut transport);
+    String::from_utf8((transport.0).into_inner()).unwrap()
+}
+
+#[test]
+fn write_message() {
+    let text = written(|p, t| {
+        p.write_message_begin(t, "add", MessageType::Call, 7).unwrap();
+        p.write_struct_begin(t, "add_args").unwrap();
+        p.write_field_begin(t, "num1", Type::I32, 1).unwrap();
+        p.write_i32(t, 15).unwrap();
+        p.write_field_end(t).unwrap();
+        p.write_field_stop(t).unwrap();
+        p.write_struct_end(t).unwrap();
+        p.write_message_end(t).unwrap();
+    });
+    assert_eq!(text, "Call add, seqid 7: add_args {\n  1: num1 = 15,\n}");
+}
+
+#[test]
+fn write_empty_containers() {
+    let text = written(|p, t| {
+        p.write_struct_begin(t, "Empty").unwrap();
+        p.write_field_begin(t, "list", Type::List, 1).unwrap();
+        p.write_list_begin(t, Type::I32, 0).unwrap();
+        p.write_list_end(t).unwrap();
+        p.write_field_end(t).unwrap();
+        p.write_field_begin(t, "inner", Type::Struct, 2).unwrap();
+        p.write_struct_begin(t, "Inner").unwrap();
+        p.write_field_stop(t).unwrap();
+        p.write_struct_end(t).unwrap();
+        p.write_field_end(t).unwrap();
+        p.write_field_stop(t).unwrap();
+        p.write_struct_end(t).unwrap();
+    });
+    assert_eq!(text, "Empty {\n  1: list = [],\n  2: inner = Inner {},\n}");
+}
+
+#[test]
+fn write_map() {
+    let text = written(|p, t| {
+        p.write_map_begin(t, Type::String, Type::Double, 2).unwrap();
+        p.write_str(t, "a").unwrap();
+        p.write_double(t, 1.0).unwrap();
+        p.write_str(t, "b\n").unwrap();
+        p.write_double(t, -0.5).unwrap();
+        p.write_map_end(t).unwrap();
+    });
+    assert_eq!(text, "{\n  \"a\" => 1.0,\n  \"b\\n\" => -0.5,\n}");
+}
+
+#[test]
+fn write_nested_list_and_set() {
+    let text = written(|p, t| {
+        p.write_list_begin(t, Type::Set, 1).unwrap();
+        p.write_set_begin(t, Type::Bool, 2).unwrap();
+        p.write_bool(t, false).unwrap();
+        p.write_bool(t, true).unwrap();
+        p.write_set_end(t).unwrap();
+        p.write_list_end(t).unwrap();
+    });
+    assert_eq!(text, "[\n  {\n    false,\n    true,\n  },\n]");
+}
+
+#[test]
+fn write_binary_as_hex() {
+    let text = written(|p, t| p.write_binary(t, &[0x00, 0xab, 0x10]).unwrap());
+    assert_eq!(text, "0x00ab10");
+}
+
+#[test]
+fn unbalanced_end_is_violation() {
+    let mut protocol = DebugProtocol::new();
+    match protocol.write_struct_end(&mut MockTransport::new(vec![])) {
+        Err(Error::ProtocolError(protocol::Error::ProtocolViolation)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn reads_are_not_implemented() {
+    let mut protocol = DebugProtocol::new();
+    match protocol.read_i32(&mut MockTransport::new(vec![])) {
+        Err(Error::ProtocolError(protocol::Error::NotImplemented)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
@@ -0,0 +1,277 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use protocol::{MessageType, Protocol, Type};
+use protocol::binary_protocol::BinaryProtocol;
+use protocol::compact_protocol::CompactProtocol;
+use protocol::limits::Limits;
+use transport::Transport;
+use transport::slice::SliceTransport;
+use transport::header::ProtocolId;
+use Result;
+
+macro_rules! delegate {
+    ($s:ident.$method:ident($($arg:expr),*)) => {
+        match $s.current {
+            ProtocolId::Binary => $s.binary.$method($($arg),*),
+            ProtocolId::Compact => $s.compact.$method($($arg),*),
+        }
+    }
+}
+
+/// The protocol used over `transport::header::HeaderTransport`.
+///
+/// Each message is encoded with the binary or compact protocol, as selected
+/// by the protocol id of the transport: a received frame dictates how it is
+/// decoded, and the id configured on the transport (or mirrored from the
+/// last request) how outgoing messages are encoded. The sequence id of each
+/// outgoing message is copied into its frame. Over any other transport this
+/// behaves like `BinaryProtocol`.
+#[derive(Clone, Debug, Default)]
+pub struct HeaderProtocol {
+    current: ProtocolId,
+    binary: BinaryProtocol,
+    compact: CompactProtocol,
+}
+
+impl HeaderProtocol {
+    pub fn new() -> HeaderProtocol {
+        HeaderProtocol::default()
+    }
+
+    pub fn with_limits(self, limits: Limits) -> HeaderProtocol {
+        HeaderProtocol {
+            current: self.current,
+            binary: self.binary.with_limits(limits),
+            compact: self.compact.with_limits(limits),
+        }
+    }
+}
+
+impl Protocol for HeaderProtocol {
+    fn write_message_begin<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        name: &str,
+        message_type: MessageType,
+        sequence_id: i32
+    ) -> Result<()> {
+        self.current = match transport.header() {
+            Some(header) => {
+                header.sequence_id = sequence_id;
+                header.protocol_id
+            }
+            None => ProtocolId::Binary,
+        };
+        delegate!(self.write_message_begin(transport, name, message_type, sequence_id))
+    }
+
+    fn read_message_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(String, MessageType, i32)> {
+        try!(transport.prepare_read());
+        self.current = transport.header().map_or(ProtocolId::Binary, |header| header.protocol_id);
+        delegate!(self.read_message_begin(transport))
+    }
+
+    fn write_message_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        delegate!(self.write_message_end(transport))
+    }
+
+    fn write_struct_begin<T: Transport>(&mut self, transport: &mut T, name: &str) -> Result<()> {
+        delegate!(self.write_struct_begin(transport, name))
+    }
+
+    fn write_struct_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        delegate!(self.write_struct_end(transport))
+    }
+
+    fn write_field_begin<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        name: &str,
+        field_type: Type,
+        field_id: i16
+    ) -> Result<()> {
+        delegate!(self.write_field_begin(transport, name, field_type, field_id))
+    }
+
+    fn write_field_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        delegate!(self.write_field_end(transport))
+    }
+
+    fn write_field_stop<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        delegate!(self.write_field_stop(transport))
+    }
+
+    fn write_map_begin<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        key_type: Type,
+        value_type: Type,
+        size: usize
+    ) -> Result<()> {
+        delegate!(self.write_map_begin(transport, key_type, value_type, size))
+    }
+
+    fn write_map_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        delegate!(self.write_map_end(transport))
+    }
+
+    fn write_list_begin<T: Transport>(&mut self, transport: &mut T, elem_type: Type, size: usize) -> Result<()> {
+        delegate!(self.write_list_begin(transport, elem_type, size))
+    }
+
+    fn write_list_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        delegate!(self.write_list_end(transport))
+    }
+
+    fn write_set_begin<T: Transport>(&mut self, transport: &mut T, elem_type: Type, size: usize) -> Result<()> {
+        delegate!(self.write_set_begin(transport, elem_type, size))
+    }
+
+    fn write_set_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        delegate!(self.write_set_end(transport))
+    }
+
+    fn write_bool<T: Transport>(&mut self, transport: &mut T, value: bool) -> Result<()> {
+        delegate!(self.write_bool(transport, value))
+    }
+
+    fn write_byte<T: Transport>(&mut self, transport: &mut T, value: i8) -> Result<()> {
+        delegate!(self.write_byte(transport, value))
+    }
+
+    fn write_i16<T: Transport>(&mut self, transport: &mut T, value: i16) -> Result<()> {
+        delegate!(self.write_i16(transport, value))
+    }
+
+    fn write_i32<T: Transport>(&mut self, transport: &mut T, value: i32) -> Result<()> {
+        delegate!(self.write_i32(transport, value))
+    }
+
+    fn write_i64<T: Transport>(&mut self, transport: &mut T, value: i64) -> Result<()> {
+        delegate!(self.write_i64(transport, value))
+    }
+
+    fn write_double<T: Transport>(&mut self, transport: &mut T, value: f64) -> Result<()> {
+        delegate!(self.write_double(transport, value))
+    }
+
+    fn write_str<T: Transport>(&mut self, transport: &mut T, value: &str) -> Result<()> {
+        delegate!(self.write_str(transport, value))
+    }
+
+    fn write_string<T: Transport>(&mut self, transport: &mut T, value: &String) -> Result<()> {
+        delegate!(self.write_string(transport, value))
+    }
+
+    fn write_binary<T: Transport>(&mut self, transport: &mut T, value: &[u8]) -> Result<()> {
+        delegate!(self.write_binary(transport, value))
+    }
+
+    fn read_message_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        delegate!(self.read_message_end(transport))
+    }
+
+    fn read_struct_begin<T: Transport>(&mut self, transport: &mut T) -> Result<String> {
+        delegate!(self.read_struct_begin(transport))
+    }
+
+    fn read_struct_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        delegate!(self.read_struct_end(transport))
+    }
+
+    fn read_field_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(String, Type, i16)> {
+        delegate!(self.read_field_begin(transport))
+    }
+
+    fn read_field_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        delegate!(self.read_field_end(transport))
+    }
+
+    fn read_map_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(Type, Type, i32)> {
+        delegate!(self.read_map_begin(transport))
+    }
+
+    fn read_map_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        delegate!(self.read_map_end(transport))
+    }
+
+    fn read_list_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(Type, i32)> {
+        delegate!(self.read_list_begin(transport))
+    }
+
+    fn read_list_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        delegate!(self.read_list_end(transport))
+    }
+
+    fn read_set_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(Type, i32)> {
+        delegate!(self.read_set_begin(transport))
+    }
+
+    fn read_set_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        delegate!(self.read_set_end(transport))
+    }
+
+    fn read_bool<T: Transport>(&mut self, transport: &mut T) -> Result<bool> {
+        delegate!(self.read_bool(transport))
+    }
+
+    fn read_byte<T: Transport>(&mut self, transport: &mut T) -> Result<i8> {
+        delegate!(self.read_byte(transport))
+    }
+
+    fn read_i16<T: Transport>(&mut self, transport: &mut T) -> Result<i16> {
+        delegate!(self.read_i16(transport))
+    }
+
+    fn read_i32<T: Transport>(&mut self, transport: &mut T) -> Result<i32> {
+        delegate!(self.read_i32(transport))
+    }
+
+    fn read_i64<T: Transport>(&mut self, transport: &mut T) -> Result<i64> {
+        delegate!(self.read_i64(transport))
+    }
+
+    fn read_double<T: Transport>(&mut self, transport: &mut T) -> Result<f64> {
+        delegate!(self.read_double(transport))
+    }
+
+    fn read_string<T: Transport>(&mut self, transport: &mut T) -> Result<String> {
+        delegate!(self.read_string(transport))
+    }
+
+    fn read_binary<T: Transport>(&mut self, transport: &mut T) -> Result<Vec<u8>> {
+        delegate!(self.read_binary(transport))
+    }
+
+    fn skip<T: Transport>(&mut self, transport: &mut T, type_: Type) -> Result<()> {
+        delegate!(self.skip(transport, type_))
+    }
+
+    fn limits(&self) -> Limits {
+        delegate!(self.limits())
+    }
+
+    fn read_borrowed_binary<'a>(&mut self, transport: &mut SliceTransport<'a>) -> Result<&'a [u8]> {
+        delegate!(self.read_borrowed_binary(transport))
+    }
+}
+
+#[cfg(test)]
+pub mod test;
@@ -0,0 +1,62 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::io::{Cursor, Write};
+
+use super::HeaderProtocol;
+
+use protocol::{MessageType, Protocol};
+use transport::Transport;
+use transport::header::{HeaderTransport, ProtocolId};
+
+fn round_trip(protocol_id: ProtocolId) -> (Vec<u8>, HeaderTransport<Cursor<Vec<u8>>>) {
+    let mut protocol = HeaderProtocol::new();
+    let mut transport = HeaderTransport::with_protocol(Cursor::new(Vec::new()), protocol_id);
+    protocol.write_message_begin(&mut transport, "ping", MessageType::Call, 9).unwrap();
+    protocol.write_i32(&mut transport, 300).unwrap();
+    protocol.write_message_end(&mut transport).unwrap();
+    transport.flush().unwrap();
+    let written = transport.into_inner().into_inner();
+
+    let mut protocol = HeaderProtocol::new();
+    let mut transport = HeaderTransport::new(Cursor::new(written.clone()));
+    assert_eq!(
+        protocol.read_message_begin(&mut transport).unwrap(),
+        (String::from("ping"), MessageType::Call, 9)
+    );
+    assert_eq!(protocol.read_i32(&mut transport).unwrap(), 300);
+    protocol.read_message_end(&mut transport).unwrap();
+    (written, transport)
+}
+
+#[test]
+fn binary_round_trip() {
+    let (written, mut transport) = round_trip(ProtocolId::Binary);
+    assert_eq!(&written[18..20], &[0x80, 0x01]);
+    assert_eq!(transport.header().unwrap().protocol_id, ProtocolId::Binary);
+}
+
+#[test]
+fn compact_round_trip() {
+    let (written, mut transport) = round_trip(ProtocolId::Compact);
+    assert_eq!(written[18], 0x82);
+    let header = transport.header().unwrap();
+    assert_eq!(header.protocol_id, ProtocolId::Compact);
+    assert_eq!(header.sequence_id, 9);
+}
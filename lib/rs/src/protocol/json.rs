@@ -0,0 +1,149 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+//! Pieces shared by the JSON based protocols.
+
+/// Tracks the separators that are due before the next value is written or read.
+#[derive(Copy, Clone, Debug)]
+pub enum Context {
+    /// Top level: values are not separated.
+    Base,
+    /// Inside a JSON array: values are separated by commas.
+    List { first: bool },
+    /// Inside a JSON object: keys and values alternate, separated by colons and commas.
+    Pair { first: bool, colon: bool },
+}
+
+impl Context {
+    /// Returns the separator due before the next value and advances the context.
+    pub fn next(&mut self) -> Option<u8> {
+        match *self {
+            Context::Base => None,
+            Context::List { ref mut first } => {
+                if *first {
+                    *first = false;
+                    None
+                } else {
+                    Some(b',')
+                }
+            }
+            Context::Pair { ref mut first, ref mut colon } => {
+                if *first {
+                    *first = false;
+                    *colon = true;
+                    None
+                } else {
+                    let separator = if *colon { b':' } else { b',' };
+                    *colon = !*colon;
+                    Some(separator)
+                }
+            }
+        }
+    }
+
+    /// Whether numbers must be quoted, which is the case for JSON object keys.
+    pub fn escape_num(&self) -> bool {
+        match *self {
+            Context::Pair { colon, .. } => colon,
+            _ => false,
+        }
+    }
+}
+
+/// Writes `value` as a quoted JSON string, escaping as required.
+pub fn escape_string(value: &[u8], out: &mut Vec<u8>) {
+    out.push(b'"');
+    for &byte in value {
+        match byte {
+            b'"' => out.extend_from_slice(b"\\\""),
+            b'\\' => out.extend_from_slice(b"\\\\"),
+            0x08 => out.extend_from_slice(b"\\b"),
+            0x0c => out.extend_from_slice(b"\\f"),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            b'\r' => out.extend_from_slice(b"\\r"),
+            b'\t' => out.extend_from_slice(b"\\t"),
+            byte if byte < 0x20 => out.extend_from_slice(format!("\\u{:04x}", byte).as_bytes()),
+            byte => out.push(byte),
+        }
+    }
+    out.push(b'"');
+}
+
+static BASE64_ALPHABET: &'static [u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes without padding, like the other Thrift JSON implementations.
+pub fn base64_encode(input: &[u8], out: &mut Vec<u8>) {
+    for chunk in input.chunks(3) {
+        let b0 = chunk[0] as u32;
+        let b1 = if chunk.len() > 1 { chunk[1] as u32 } else { 0 };
+        let b2 = if chunk.len() > 2 { chunk[2] as u32 } else { 0 };
+        let triple = (b0 << 16) | (b1 << 8) | b2;
+        out.push(BASE64_ALPHABET[(triple >> 18) as usize & 0x3f]);
+        out.push(BASE64_ALPHABET[(triple >> 12) as usize & 0x3f]);
+        if chunk.len() > 1 {
+            out.push(BASE64_ALPHABET[(triple >> 6) as usize & 0x3f]);
+        }
+        if chunk.len() > 2 {
+            out.push(BASE64_ALPHABET[triple as usize & 0x3f]);
+        }
+    }
+}
+
+/// Decodes base64 with or without trailing padding.
+pub fn base64_decode(input: &[u8]) -> Option<Vec<u8>> {
+    fn value(byte: u8) -> Option<u32> {
+        match byte {
+            b'A'..=b'Z' => Some((byte - b'A') as u32),
+            b'a'..=b'z' => Some((byte - b'a' + 26) as u32),
+            b'0'..=b'9' => Some((byte - b'0' + 52) as u32),
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
+        }
+    }
+
+    let mut end = input.len();
+    while end > 0 && input[end - 1] == b'=' {
+        end -= 1;
+    }
+    let input = &input[..end];
+    if input.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    for chunk in input.chunks(4) {
+        let mut quad = 0u32;
+        for (i, &byte) in chunk.iter().enumerate() {
+            match value(byte) {
+                Some(bits) => quad |= bits << (18 - 6 * i),
+                None => return None,
+            }
+        }
+        out.push((quad >> 16) as u8);
+        if chunk.len() > 2 {
+            out.push((quad >> 8) as u8);
+        }
+        if chunk.len() > 3 {
+            out.push(quad as u8);
+        }
+    }
+    Some(out)
+}
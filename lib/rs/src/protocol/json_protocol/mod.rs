@@ -0,0 +1,673 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::char;
+
+use protocol::{self, MessageType, Protocol, Type};
+use protocol::json::{self, Context};
+use protocol::limits::{Budget, Limit, Limits};
+use transport::Transport;
+use {Result, Error};
+
+use podio::{ReadPodExt, WritePodExt};
+
+static JSON_PROTOCOL_VERSION: i64 = 1;
+
+static NAN: &'static str = "NaN";
+static INFINITY: &'static str = "Infinity";
+static NEGATIVE_INFINITY: &'static str = "-Infinity";
+
+/// An implementation of Thrift's JSON protocol (TJSONProtocol).
+///
+/// The wire format is compatible with the other Thrift implementations: fields
+/// are keyed by id and tagged with their type (`{"1":{"i32":5}}`), binaries are
+/// base64 encoded and messages are encoded as `[1,"name",type,seqid,{...}]`.
+/// The protocol keeps track of JSON nesting and a byte of lookahead, so a fresh
+/// instance should be used for every connection.
+#[derive(Clone, Debug)]
+pub struct JsonProtocol {
+    contexts: Vec<Context>,
+    lookahead: Option<u8>,
+    budget: Budget,
+}
+
+impl Default for JsonProtocol {
+    fn default() -> JsonProtocol {
+        JsonProtocol { contexts: vec![Context::Base], lookahead: None, budget: Budget::default() }
+    }
+}
+
+impl JsonProtocol {
+    pub fn new() -> JsonProtocol {
+        JsonProtocol::default()
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> JsonProtocol {
+        self.budget = Budget::new(limits);
+        self
+    }
+
+    fn context(&mut self) -> &mut Context {
+        self.contexts.last_mut().expect("JSON context stack is never empty")
+    }
+
+    fn push_context(&mut self, context: Context) {
+        self.contexts.push(context);
+    }
+
+    fn pop_context(&mut self) -> Result<()> {
+        if self.contexts.len() > 1 {
+            self.contexts.pop();
+            Ok(())
+        } else {
+            Err(Error::from(protocol::Error::ProtocolViolation))
+        }
+    }
+
+    fn escape_num(&mut self) -> bool {
+        self.context().escape_num()
+    }
+
+    // Writing
+
+    fn write_raw<T: Transport>(&mut self, transport: &mut T, bytes: &[u8]) -> Result<()> {
+        Ok(try!(transport.write_all(bytes)))
+    }
+
+    fn write_context<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        match self.context().next() {
+            Some(separator) => Ok(try!(transport.write_u8(separator))),
+            None => Ok(()),
+        }
+    }
+
+    fn write_json_string<T: Transport>(&mut self, transport: &mut T, value: &[u8]) -> Result<()> {
+        try!(self.write_context(transport));
+        let mut escaped = Vec::with_capacity(value.len() + 2);
+        json::escape_string(value, &mut escaped);
+        self.write_raw(transport, &escaped)
+    }
+
+    fn write_json_integer<T: Transport>(&mut self, transport: &mut T, value: i64) -> Result<()> {
+        try!(self.write_context(transport));
+        let formatted = if self.escape_num() {
+            format!("\"{}\"", value)
+        } else {
+            value.to_string()
+        };
+        self.write_raw(transport, formatted.as_bytes())
+    }
+
+    fn write_json_double<T: Transport>(&mut self, transport: &mut T, value: f64) -> Result<()> {
+        try!(self.write_context(transport));
+        let formatted = if value.is_nan() {
+            format!("\"{}\"", NAN)
+        } else if value.is_infinite() {
+            format!("\"{}\"", if value > 0.0 { INFINITY } else { NEGATIVE_INFINITY })
+        } else if self.escape_num() {
+            format!("\"{:?}\"", value)
+        } else {
+            format!("{:?}", value)
+        };
+        self.write_raw(transport, formatted.as_bytes())
+    }
+
+    fn write_json_base64<T: Transport>(&mut self, transport: &mut T, value: &[u8]) -> Result<()> {
+        try!(self.write_context(transport));
+        let mut encoded = Vec::with_capacity(value.len() * 4 / 3 + 4);
+        encoded.push(b'"');
+        json::base64_encode(value, &mut encoded);
+        encoded.push(b'"');
+        self.write_raw(transport, &encoded)
+    }
+
+    fn write_json_object_begin<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        try!(self.write_context(transport));
+        try!(self.write_raw(transport, b"{"));
+        self.push_context(Context::Pair { first: true, colon: true });
+        Ok(())
+    }
+
+    fn write_json_object_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        try!(self.pop_context());
+        self.write_raw(transport, b"}")
+    }
+
+    fn write_json_array_begin<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        try!(self.write_context(transport));
+        try!(self.write_raw(transport, b"["));
+        self.push_context(Context::List { first: true });
+        Ok(())
+    }
+
+    fn write_json_array_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        try!(self.pop_context());
+        self.write_raw(transport, b"]")
+    }
+
+    // Reading
+
+    fn read_raw<T: Transport>(&mut self, transport: &mut T) -> Result<u8> {
+        match self.lookahead.take() {
+            Some(byte) => Ok(byte),
+            None => {
+                try!(self.budget.consume(1));
+                Ok(try!(transport.read_u8()))
+            }
+        }
+    }
+
+    fn peek<T: Transport>(&mut self, transport: &mut T) -> Result<u8> {
+        let byte = try!(self.read_raw(transport));
+        self.lookahead = Some(byte);
+        Ok(byte)
+    }
+
+    fn expect<T: Transport>(&mut self, transport: &mut T, expected: u8) -> Result<()> {
+        if try!(self.read_raw(transport)) == expected {
+            Ok(())
+        } else {
+            Err(Error::from(protocol::Error::ProtocolViolation))
+        }
+    }
+
+    fn read_context<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        match self.context().next() {
+            Some(separator) => self.expect(transport, separator),
+            None => Ok(()),
+        }
+    }
+
+    fn read_json_string<T: Transport>(&mut self, transport: &mut T, skip_context: bool) -> Result<Vec<u8>> {
+        if !skip_context {
+            try!(self.read_context(transport));
+        }
+        try!(self.expect(transport, b'"'));
+
+        let mut bytes = Vec::new();
+        let mut high_surrogate: Option<u32> = None;
+        loop {
+            if bytes.len() > self.budget.limits.max_string_len {
+                return Err(Error::from(protocol::Error::LimitExceeded(Limit::StringLength)));
+            }
+            let byte = try!(self.read_raw(transport));
+            if byte == b'"' {
+                break;
+            }
+            if byte != b'\\' {
+                bytes.push(byte);
+                continue;
+            }
+
+            let escaped = try!(self.read_raw(transport));
+            let unescaped = match escaped {
+                b'"' => b'"',
+                b'\\' => b'\\',
+                b'/' => b'/',
+                b'b' => 0x08,
+                b'f' => 0x0c,
+                b'n' => b'\n',
+                b'r' => b'\r',
+                b't' => b'\t',
+                b'u' => {
+                    let code = try!(self.read_json_hex(transport));
+                    let code = match (high_surrogate.take(), code) {
+                        (None, 0xd800..=0xdbff) => {
+                            high_surrogate = Some(code);
+                            continue;
+                        }
+                        (Some(high), 0xdc00..=0xdfff) => {
+                            0x10000 + ((high - 0xd800) << 10) + (code - 0xdc00)
+                        }
+                        (None, code) => code,
+                        (Some(_), _) => return Err(Error::from(protocol::Error::ProtocolViolation)),
+                    };
+                    let c = match char::from_u32(code) {
+                        Some(c) => c,
+                        None => return Err(Error::from(protocol::Error::ProtocolViolation)),
+                    };
+                    let mut buf = [0u8; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                    continue;
+                }
+                _ => return Err(Error::from(protocol::Error::ProtocolViolation)),
+            };
+            bytes.push(unescaped);
+        }
+
+        if high_surrogate.is_some() {
+            return Err(Error::from(protocol::Error::ProtocolViolation));
+        }
+        Ok(bytes)
+    }
+
+    fn read_json_hex<T: Transport>(&mut self, transport: &mut T) -> Result<u32> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = match try!(self.read_raw(transport)) {
+                byte @ b'0'..=b'9' => byte - b'0',
+                byte @ b'a'..=b'f' => byte - b'a' + 10,
+                byte @ b'A'..=b'F' => byte - b'A' + 10,
+                _ => return Err(Error::from(protocol::Error::ProtocolViolation)),
+            };
+            code = (code << 4) | digit as u32;
+        }
+        Ok(code)
+    }
+
+    fn read_json_numeric_chars<T: Transport>(&mut self, transport: &mut T) -> Result<String> {
+        let mut chars = String::new();
+        loop {
+            match self.peek(transport) {
+                Ok(byte @ b'0'..=b'9') | Ok(byte @ b'+') | Ok(byte @ b'-') |
+                Ok(byte @ b'.') | Ok(byte @ b'e') | Ok(byte @ b'E') => {
+                    self.lookahead = None;
+                    chars.push(byte as char);
+                }
+                Ok(_) => return Ok(chars),
+                // A number may legitimately be the last thing on the transport.
+                Err(_) if !chars.is_empty() => return Ok(chars),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn read_json_integer<T: Transport>(&mut self, transport: &mut T) -> Result<i64> {
+        try!(self.read_context(transport));
+        let escape = self.escape_num();
+        if escape {
+            try!(self.expect(transport, b'"'));
+        }
+        let chars = try!(self.read_json_numeric_chars(transport));
+        if escape {
+            try!(self.expect(transport, b'"'));
+        }
+        chars.parse().map_err(|_| Error::from(protocol::Error::ProtocolViolation))
+    }
+
+    fn read_json_double<T: Transport>(&mut self, transport: &mut T) -> Result<f64> {
+        try!(self.read_context(transport));
+        if try!(self.peek(transport)) == b'"' {
+            let bytes = try!(self.read_json_string(transport, true));
+            let value = match &bytes[..] {
+                b"NaN" => ::std::f64::NAN,
+                b"Infinity" => ::std::f64::INFINITY,
+                b"-Infinity" => ::std::f64::NEG_INFINITY,
+                _ if self.escape_num() => {
+                    let string = try!(::std::str::from_utf8(&bytes).map_err(protocol::Error::from));
+                    try!(string.parse().map_err(|_| Error::from(protocol::Error::ProtocolViolation)))
+                }
+                _ => return Err(Error::from(protocol::Error::ProtocolViolation)),
+            };
+            Ok(value)
+        } else {
+            if self.escape_num() {
+                return Err(Error::from(protocol::Error::ProtocolViolation));
+            }
+            let chars = try!(self.read_json_numeric_chars(transport));
+            chars.parse().map_err(|_| Error::from(protocol::Error::ProtocolViolation))
+        }
+    }
+
+    fn read_json_base64<T: Transport>(&mut self, transport: &mut T) -> Result<Vec<u8>> {
+        let encoded = try!(self.read_json_string(transport, false));
+        json::base64_decode(&encoded).ok_or(Error::from(protocol::Error::ProtocolViolation))
+    }
+
+    fn read_json_object_begin<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        try!(self.read_context(transport));
+        try!(self.expect(transport, b'{'));
+        self.push_context(Context::Pair { first: true, colon: true });
+        Ok(())
+    }
+
+    fn read_json_object_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        try!(self.expect(transport, b'}'));
+        self.pop_context()
+    }
+
+    fn read_json_array_begin<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        try!(self.read_context(transport));
+        try!(self.expect(transport, b'['));
+        self.push_context(Context::List { first: true });
+        Ok(())
+    }
+
+    fn read_json_array_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        try!(self.expect(transport, b']'));
+        self.pop_context()
+    }
+
+    fn read_json_type<T: Transport>(&mut self, transport: &mut T) -> Result<Type> {
+        let name = try!(self.read_json_string(transport, false));
+        match type_from_name(&name) {
+            Some(type_) => Ok(type_),
+            None => Err(Error::from(protocol::Error::ProtocolViolation)),
+        }
+    }
+
+    fn read_json_size<T: Transport>(&mut self, transport: &mut T) -> Result<i32> {
+        let size = try!(self.read_json_integer(transport));
+        if size > ::std::i32::MAX as i64 {
+            return Err(Error::from(protocol::Error::ProtocolViolation));
+        }
+        try!(self.budget.container_len(size));
+        Ok(size as i32)
+    }
+}
+
+fn type_name(type_: Type) -> &'static [u8] {
+    match type_ {
+        Type::Bool => b"tf",
+        Type::Byte => b"i8",
+        Type::I16 => b"i16",
+        Type::I32 => b"i32",
+        Type::I64 => b"i64",
+        Type::Double => b"dbl",
+        Type::String => b"str",
+        Type::Struct => b"rec",
+        Type::Map => b"map",
+        Type::Set => b"set",
+        Type::List => b"lst",
+        Type::Stop | Type::Void => b"",
+    }
+}
+
+fn type_from_name(name: &[u8]) -> Option<Type> {
+    match name {
+        b"tf" => Some(Type::Bool),
+        b"i8" => Some(Type::Byte),
+        b"i16" => Some(Type::I16),
+        b"i32" => Some(Type::I32),
+        b"i64" => Some(Type::I64),
+        b"dbl" => Some(Type::Double),
+        b"str" => Some(Type::String),
+        b"rec" => Some(Type::Struct),
+        b"map" => Some(Type::Map),
+        b"set" => Some(Type::Set),
+        b"lst" => Some(Type::List),
+        _ => None,
+    }
+}
+
+fn narrow<N: Copy>(value: i64, min: i64, max: i64, cast: fn(i64) -> N) -> Result<N> {
+    if value < min || value > max {
+        Err(Error::from(protocol::Error::ProtocolViolation))
+    } else {
+        Ok(cast(value))
+    }
+}
+
+impl Protocol for JsonProtocol {
+    fn write_message_begin<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        name: &str,
+        message_type: MessageType,
+        sequence_id: i32
+    ) -> Result<()> {
+        try!(self.write_json_array_begin(transport));
+        try!(self.write_json_integer(transport, JSON_PROTOCOL_VERSION));
+        try!(self.write_json_string(transport, name.as_bytes()));
+        try!(self.write_json_integer(transport, message_type as i64));
+        self.write_json_integer(transport, sequence_id as i64)
+    }
+
+    fn write_message_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.write_json_array_end(transport)
+    }
+
+    fn write_struct_begin<T: Transport>(&mut self, transport: &mut T, _name: &str) -> Result<()> {
+        self.write_json_object_begin(transport)
+    }
+
+    fn write_struct_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.write_json_object_end(transport)
+    }
+
+    fn write_field_begin<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        _name: &str,
+        field_type: Type,
+        field_id: i16
+    ) -> Result<()> {
+        try!(self.write_json_integer(transport, field_id as i64));
+        try!(self.write_json_object_begin(transport));
+        self.write_json_string(transport, type_name(field_type))
+    }
+
+    fn write_field_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.write_json_object_end(transport)
+    }
+
+    fn write_field_stop<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_map_begin<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        key_type: Type,
+        value_type: Type,
+        size: usize
+    ) -> Result<()> {
+        try!(self.write_json_array_begin(transport));
+        try!(self.write_json_string(transport, type_name(key_type)));
+        try!(self.write_json_string(transport, type_name(value_type)));
+        try!(self.write_json_integer(transport, size as i64));
+        self.write_json_object_begin(transport)
+    }
+
+    fn write_map_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        try!(self.write_json_object_end(transport));
+        self.write_json_array_end(transport)
+    }
+
+    fn write_list_begin<T: Transport>(&mut self, transport: &mut T, elem_type: Type, size: usize) -> Result<()> {
+        try!(self.write_json_array_begin(transport));
+        try!(self.write_json_string(transport, type_name(elem_type)));
+        self.write_json_integer(transport, size as i64)
+    }
+
+    fn write_list_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.write_json_array_end(transport)
+    }
+
+    fn write_set_begin<T: Transport>(&mut self, transport: &mut T, elem_type: Type, size: usize) -> Result<()> {
+        self.write_list_begin(transport, elem_type, size)
+    }
+
+    fn write_set_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.write_json_array_end(transport)
+    }
+
+    fn write_bool<T: Transport>(&mut self, transport: &mut T, value: bool) -> Result<()> {
+        self.write_json_integer(transport, value as i64)
+    }
+
+    fn write_byte<T: Transport>(&mut self, transport: &mut T, value: i8) -> Result<()> {
+        self.write_json_integer(transport, value as i64)
+    }
+
+    fn write_i16<T: Transport>(&mut self, transport: &mut T, value: i16) -> Result<()> {
+        self.write_json_integer(transport, value as i64)
+    }
+
+    fn write_i32<T: Transport>(&mut self, transport: &mut T, value: i32) -> Result<()> {
+        self.write_json_integer(transport, value as i64)
+    }
+
+    fn write_i64<T: Transport>(&mut self, transport: &mut T, value: i64) -> Result<()> {
+        self.write_json_integer(transport, value)
+    }
+
+    fn write_double<T: Transport>(&mut self, transport: &mut T, value: f64) -> Result<()> {
+        self.write_json_double(transport, value)
+    }
+
+    fn write_str<T: Transport>(&mut self, transport: &mut T, value: &str) -> Result<()> {
+        self.write_json_string(transport, value.as_bytes())
+    }
+
+    fn write_string<T: Transport>(&mut self, transport: &mut T, value: &String) -> Result<()> {
+        self.write_json_string(transport, value.as_bytes())
+    }
+
+    fn write_binary<T: Transport>(&mut self, transport: &mut T, value: &[u8]) -> Result<()> {
+        self.write_json_base64(transport, value)
+    }
+
+    fn read_message_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(String, MessageType, i32)> {
+        // Drop whatever nesting an earlier message that failed to decode left.
+        self.budget.begin_message();
+        self.contexts.truncate(1);
+        self.lookahead = None;
+        try!(self.read_json_array_begin(transport));
+        if try!(self.read_json_integer(transport)) != JSON_PROTOCOL_VERSION {
+            return Err(Error::from(protocol::Error::BadVersion));
+        }
+        let name = try!(self.read_string(transport));
+        let raw_type = try!(self.read_json_integer(transport));
+        let message_type = match MessageType::from_num(raw_type as u64) {
+            Some(t) => t,
+            None => return Err(Error::from(protocol::Error::ProtocolViolation)),
+        };
+        let sequence_id = try!(self.read_i32(transport));
+        Ok((name, message_type, sequence_id))
+    }
+
+    fn read_message_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.budget.end_message();
+        self.read_json_array_end(transport)
+    }
+
+    fn read_struct_begin<T: Transport>(&mut self, transport: &mut T) -> Result<String> {
+        try!(self.budget.enter());
+        try!(self.read_json_object_begin(transport));
+        Ok(String::new())
+    }
+
+    fn read_struct_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.budget.leave();
+        self.read_json_object_end(transport)
+    }
+
+    fn read_field_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(String, Type, i16)> {
+        if try!(self.peek(transport)) == b'}' {
+            return Ok((String::new(), Type::Stop, 0));
+        }
+        let field_id = try!(self.read_i16(transport));
+        try!(self.read_json_object_begin(transport));
+        let field_type = try!(self.read_json_type(transport));
+        Ok((String::new(), field_type, field_id))
+    }
+
+    fn read_field_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.read_json_object_end(transport)
+    }
+
+    fn read_map_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(Type, Type, i32)> {
+        try!(self.budget.enter());
+        try!(self.read_json_array_begin(transport));
+        let key_type = try!(self.read_json_type(transport));
+        let value_type = try!(self.read_json_type(transport));
+        let size = try!(self.read_json_size(transport));
+        try!(self.read_json_object_begin(transport));
+        Ok((key_type, value_type, size))
+    }
+
+    fn read_map_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.budget.leave();
+        try!(self.read_json_object_end(transport));
+        self.read_json_array_end(transport)
+    }
+
+    fn read_list_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(Type, i32)> {
+        try!(self.budget.enter());
+        try!(self.read_json_array_begin(transport));
+        let elem_type = try!(self.read_json_type(transport));
+        let size = try!(self.read_json_size(transport));
+        Ok((elem_type, size))
+    }
+
+    fn read_list_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.budget.leave();
+        self.read_json_array_end(transport)
+    }
+
+    fn read_set_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(Type, i32)> {
+        self.read_list_begin(transport)
+    }
+
+    fn read_set_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.read_list_end(transport)
+    }
+
+    fn read_bool<T: Transport>(&mut self, transport: &mut T) -> Result<bool> {
+        Ok(try!(self.read_json_integer(transport)) != 0)
+    }
+
+    fn read_byte<T: Transport>(&mut self, transport: &mut T) -> Result<i8> {
+        let value = try!(self.read_json_integer(transport));
+        narrow(value, ::std::i8::MIN as i64, ::std::i8::MAX as i64, |v| v as i8)
+    }
+
+    fn read_i16<T: Transport>(&mut self, transport: &mut T) -> Result<i16> {
+        let value = try!(self.read_json_integer(transport));
+        narrow(value, ::std::i16::MIN as i64, ::std::i16::MAX as i64, |v| v as i16)
+    }
+
+    fn read_i32<T: Transport>(&mut self, transport: &mut T) -> Result<i32> {
+        let value = try!(self.read_json_integer(transport));
+        narrow(value, ::std::i32::MIN as i64, ::std::i32::MAX as i64, |v| v as i32)
+    }
+
+    fn read_i64<T: Transport>(&mut self, transport: &mut T) -> Result<i64> {
+        self.read_json_integer(transport)
+    }
+
+    fn read_double<T: Transport>(&mut self, transport: &mut T) -> Result<f64> {
+        self.read_json_double(transport)
+    }
+
+    fn read_string<T: Transport>(&mut self, transport: &mut T) -> Result<String> {
+        let bytes = try!(self.read_json_string(transport, false));
+        Ok(try!(String::from_utf8(bytes).map_err(|e| protocol::Error::from(e.utf8_error()))))
+    }
+
+    fn read_binary<T: Transport>(&mut self, transport: &mut T) -> Result<Vec<u8>> {
+        self.read_json_base64(transport)
+    }
+
+    fn skip<T: Transport>(&mut self, transport: &mut T, type_: Type) -> Result<()> {
+        match type_ {
+            // Skipped strings may hold arbitrary text rather than base64.
+            Type::String => self.read_json_string(transport, false).map(|_| ()),
+            _ => protocol::helpers::skip(self, transport, type_),
+        }
+    }
+
+    fn limits(&self) -> Limits {
+        self.budget.limits
+    }
+}
+
+#[cfg(test)]
+pub mod test;
@@ -0,0 +1,178 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::io::Cursor;
+
+use super::JsonProtocol;
+
+use mock::MockTransport;
+use protocol::{self, Limit, Limits, Protocol, Type, MessageType};
+use transport::RwTransport;
+use Error;
+
+fn written<F>(f: F) -> String where F: FnOnce(&mut JsonProtocol, &mut RwTransport<Cursor<Vec<u8>>>) {
+    let mut transport = RwTransport(Cursor::new(Vec::new()));
+    let mut protocol = JsonProtocol::new();
+    f(&mut protocol, &mut transport);
+    String::from_utf8((transport.0).into_inner()).unwrap()
+}
+
+fn reader(json: &str) -> MockTransport {
+    MockTransport::new(json.as_bytes().to_vec())
+}
+
+#[test]
+fn write_message() {
+    let json = written(|p, t| {
+        p.write_message_begin(t, "add", MessageType::Call, 7).unwrap();
+        p.write_struct_begin(t, "Args").unwrap();
+        p.write_field_begin(t, "num1", Type::I32, 1).unwrap();
+        p.write_i32(t, 15).unwrap();
+        p.write_field_end(t).unwrap();
+        p.write_field_begin(t, "flag", Type::Bool, 2).unwrap();
+        p.write_bool(t, true).unwrap();
+        p.write_field_end(t).unwrap();
+        p.write_field_stop(t).unwrap();
+        p.write_struct_end(t).unwrap();
+        p.write_message_end(t).unwrap();
+    });
+    assert_eq!(json, r#"[1,"add",1,7,{"1":{"i32":15},"2":{"tf":1}}]"#);
+}
+
+#[test]
+fn write_containers() {
+    let json = written(|p, t| {
+        p.write_map_begin(t, Type::I32, Type::String, 2).unwrap();
+        p.write_i32(t, 1).unwrap();
+        p.write_str(t, "one").unwrap();
+        p.write_i32(t, 2).unwrap();
+        p.write_str(t, "two").unwrap();
+        p.write_map_end(t).unwrap();
+        p.write_list_begin(t, Type::Double, 2).unwrap();
+        p.write_double(t, 1.5).unwrap();
+        p.write_double(t, ::std::f64::NEG_INFINITY).unwrap();
+        p.write_list_end(t).unwrap();
+    });
+    assert_eq!(json, r#"["i32","str",2,{"1":"one","2":"two"}]["dbl",2,1.5,"-Infinity"]"#);
+}
+
+#[test]
+fn write_escaped_string_and_binary() {
+    let json = written(|p, t| {
+        p.write_str(t, "a\"b\\c\n\u{1}").unwrap();
+        p.write_binary(t, b"hello").unwrap();
+    });
+    assert_eq!(json, r#""a\"b\\c\n\u0001""aGVsbG8""#);
+}
+
+#[test]
+fn read_message() {
+    let transport = &mut reader(r#"[1,"add",1,7,{"1":{"i32":15},"2":{"tf":1}}]"#);
+    let mut protocol = JsonProtocol::new();
+    assert_eq!(
+        protocol.read_message_begin(transport).unwrap(),
+        ("add".to_string(), MessageType::Call, 7)
+    );
+    protocol.read_struct_begin(transport).unwrap();
+    assert_eq!(protocol.read_field_begin(transport).unwrap(), ("".to_string(), Type::I32, 1));
+    assert_eq!(protocol.read_i32(transport).unwrap(), 15);
+    protocol.read_field_end(transport).unwrap();
+    assert_eq!(protocol.read_field_begin(transport).unwrap(), ("".to_string(), Type::Bool, 2));
+    assert_eq!(protocol.read_bool(transport).unwrap(), true);
+    protocol.read_field_end(transport).unwrap();
+    assert_eq!(protocol.read_field_begin(transport).unwrap().1, Type::Stop);
+    protocol.read_struct_end(transport).unwrap();
+    protocol.read_message_end(transport).unwrap();
+}
+
+#[test]
+fn read_containers() {
+    let transport = &mut reader(r#"["i32","str",2,{"1":"one","2":"two"}]["dbl",3,1.5,"NaN",-2e3]"#);
+    let mut protocol = JsonProtocol::new();
+    assert_eq!(protocol.read_map_begin(transport).unwrap(), (Type::I32, Type::String, 2));
+    assert_eq!(protocol.read_i32(transport).unwrap(), 1);
+    assert_eq!(&protocol.read_string(transport).unwrap(), "one");
+    assert_eq!(protocol.read_i32(transport).unwrap(), 2);
+    assert_eq!(&protocol.read_string(transport).unwrap(), "two");
+    protocol.read_map_end(transport).unwrap();
+    assert_eq!(protocol.read_list_begin(transport).unwrap(), (Type::Double, 3));
+    assert_eq!(protocol.read_double(transport).unwrap(), 1.5);
+    assert!(protocol.read_double(transport).unwrap().is_nan());
+    assert_eq!(protocol.read_double(transport).unwrap(), -2000.0);
+    protocol.read_list_end(transport).unwrap();
+}
+
+#[test]
+fn read_escaped_string_and_binary() {
+    let transport = &mut reader(r#""a\"b\/cé😀""aGVsbG8=""#);
+    let mut protocol = JsonProtocol::new();
+    assert_eq!(&protocol.read_string(transport).unwrap(), "a\"b/c\u{e9}\u{1f600}");
+    assert_eq!(&protocol.read_binary(transport).unwrap(), b"hello");
+}
+
+#[test]
+fn skip_unknown_field() {
+    let transport = &mut reader(r#"{"3":{"str":"not base64!"},"4":{"lst":["rec",1,{"1":{"i8":-1}}]}}"#);
+    let mut protocol = JsonProtocol::new();
+    protocol.skip(transport, Type::Struct).unwrap();
+}
+
+#[test]
+fn read_message_begin_bad_version() {
+    let transport = &mut reader(r#"[2,"add",1,7]"#);
+    let mut protocol = JsonProtocol::new();
+    match protocol.read_message_begin(transport).unwrap_err() {
+        Error::ProtocolError(e) => assert_eq!(e, protocol::Error::BadVersion),
+        e => panic!("Expected a protocol error, got {:?}", e)
+    }
+}
+
+#[test]
+fn read_missing_separator() {
+    let transport = &mut reader(r#"["i32" 1]"#);
+    let mut protocol = JsonProtocol::new();
+    match protocol.read_list_begin(transport).unwrap_err() {
+        Error::ProtocolError(e) => assert_eq!(e, protocol::Error::ProtocolViolation),
+        e => panic!("Expected a protocol error, got {:?}", e)
+    }
+}
+
+#[test]
+fn read_string_too_long() {
+    let transport = &mut MockTransport::new(b"\"abcdef\"".to_vec());
+    let mut limits = Limits::default();
+    limits.max_string_len = 4;
+    let mut protocol = JsonProtocol::new().with_limits(limits);
+    match protocol.read_string(transport).unwrap_err() {
+        Error::ProtocolError(e) => assert_eq!(e, protocol::Error::LimitExceeded(Limit::StringLength)),
+        e => panic!("Expected a protocol error, got {:?}", e)
+    }
+}
+
+#[test]
+fn read_list_too_large() {
+    let transport = &mut MockTransport::new(b"[\"i32\",1000000,".to_vec());
+    let mut limits = Limits::default();
+    limits.max_container_len = 10;
+    let mut protocol = JsonProtocol::new().with_limits(limits);
+    match protocol.read_list_begin(transport).unwrap_err() {
+        Error::ProtocolError(e) => assert_eq!(e, protocol::Error::LimitExceeded(Limit::ContainerSize)),
+        e => panic!("Expected a protocol error, got {:?}", e)
+    }
+}
@@ -0,0 +1,163 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+//! Bounds on the resources spent decoding untrusted input.
+
+use protocol::Error;
+use Result;
+
+/// The limit that was exceeded, reported by `Error::LimitExceeded`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Limit {
+    StringLength,
+    ContainerSize,
+    Depth,
+    MessageSize,
+}
+
+/// Limits enforced when decoding.
+///
+/// The defaults allow messages of up to 100 MiB and 64 levels of nested
+/// structs and containers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// The longest string or binary, in bytes.
+    pub max_string_len: usize,
+    /// The largest number of elements in a list, set or map.
+    pub max_container_len: usize,
+    /// How deeply structs and containers may be nested.
+    pub max_depth: usize,
+    /// The most bytes read for a single message or top-level value.
+    pub max_message_size: usize,
+}
+
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 100 * 1024 * 1024;
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+pub const DEFAULT_LIMITS: Limits = Limits {
+    max_string_len: DEFAULT_MAX_MESSAGE_SIZE,
+    max_container_len: DEFAULT_MAX_MESSAGE_SIZE,
+    max_depth: DEFAULT_MAX_DEPTH,
+    max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+};
+
+impl Default for Limits {
+    fn default() -> Limits {
+        DEFAULT_LIMITS
+    }
+}
+
+impl Limits {
+    /// No limits at all, for trusted input only.
+    pub fn unlimited() -> Limits {
+        Limits {
+            max_string_len: usize::max_value(),
+            max_container_len: usize::max_value(),
+            max_depth: usize::max_value(),
+            max_message_size: usize::max_value(),
+        }
+    }
+
+    /// Validate a string length read from the wire.
+    pub fn string_len(&self, len: i64) -> Result<usize> {
+        check(len, self.max_string_len, Limit::StringLength)
+    }
+
+    /// Validate a container size read from the wire.
+    pub fn container_len(&self, len: i64) -> Result<usize> {
+        check(len, self.max_container_len, Limit::ContainerSize)
+    }
+}
+
+fn check(len: i64, max: usize, limit: Limit) -> Result<usize> {
+    if len < 0 {
+        Err(::Error::from(Error::ProtocolViolation))
+    } else if len as u64 > max as u64 {
+        Err(::Error::from(Error::LimitExceeded(limit)))
+    } else {
+        Ok(len as usize)
+    }
+}
+
+/// Tracks how much of its `Limits` the value being decoded has used up.
+///
+/// Protocols call `begin_message` when reading a message header, `consume`
+/// for every byte they read and `enter`/`leave` around structs and
+/// containers. Outside of a message, every top-level struct or container
+/// gets a fresh allowance.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Budget {
+    pub limits: Limits,
+    depth: usize,
+    consumed: usize,
+    in_message: bool,
+}
+
+impl Budget {
+    pub const fn new(limits: Limits) -> Budget {
+        Budget { limits: limits, depth: 0, consumed: 0, in_message: false }
+    }
+
+    /// Start over for a new message, also forgetting the depth reached by
+    /// one that failed to decode.
+    pub fn begin_message(&mut self) {
+        self.depth = 0;
+        self.consumed = 0;
+        self.in_message = true;
+    }
+
+    pub fn end_message(&mut self) {
+        self.in_message = false;
+    }
+
+    pub fn consume(&mut self, bytes: usize) -> Result<()> {
+        self.consumed = self.consumed.saturating_add(bytes);
+        if self.consumed > self.limits.max_message_size {
+            Err(::Error::from(Error::LimitExceeded(Limit::MessageSize)))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Validate a string length and account for the bytes it will take.
+    pub fn string_len(&mut self, len: i64) -> Result<usize> {
+        let len = try!(self.limits.string_len(len));
+        try!(self.consume(len));
+        Ok(len)
+    }
+
+    pub fn container_len(&self, len: i64) -> Result<usize> {
+        self.limits.container_len(len)
+    }
+
+    pub fn enter(&mut self) -> Result<()> {
+        if self.depth == 0 && !self.in_message {
+            self.consumed = 0;
+        }
+        if self.depth >= self.limits.max_depth {
+            return Err(::Error::from(Error::LimitExceeded(Limit::Depth)));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    pub fn leave(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+}
@@ -16,13 +16,25 @@
  * specific language governing permissions and limitations
  * under the License.
  */
-use std::{str, fmt};
+use std::{io, str, fmt};
 use std::error::Error as StdError;
 
 use transport::Transport;
+use transport::slice::SliceTransport;
 use Result;
 
 pub mod binary_protocol;
+pub mod compact_protocol;
+pub mod json_protocol;
+pub mod simple_json_protocol;
+pub mod debug_protocol;
+pub mod multiplexed_protocol;
+pub mod header_protocol;
+pub mod limits;
+
+mod json;
+
+pub use self::limits::{Limit, Limits};
 
 #[derive(Debug, PartialEq)]
 pub enum Error {
@@ -32,6 +44,26 @@ pub enum Error {
     ProtocolViolation,
     /// Received string cannot be converted to a UTF8 string
     InvalidUtf8(str::Utf8Error),
+    /// The operation is not supported by this protocol, e.g. reading from a write-only protocol
+    NotImplemented,
+    /// The input exceeds one of the configured decoding `Limits`
+    LimitExceeded(Limit),
+    /// A required field, given as struct and field name, was missing
+    MissingRequiredField(&'static str, &'static str),
+    /// A client received a message which doesn't answer its last call,
+    /// usually because the connection got out of sync
+    UnexpectedReply(ReplyMismatch),
+}
+
+/// How a received message differs from the reply a client waits for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplyMismatch {
+    /// The sequence id, as (expected, received)
+    SequenceId(i32, i32),
+    /// The method name, as (expected, received)
+    MethodName(String, String),
+    /// The message was neither a `Reply` nor an `Exception`
+    MessageType(MessageType),
 }
 
 impl StdError for Error {
@@ -59,6 +91,14 @@ impl From<str::Utf8Error> for Error {
     }
 }
 
+/// For transports, which can only fail with an `io::Error`. Converting it
+/// back into a crate `Error` recovers the `ProtocolError`.
+impl From<Error> for io::Error {
+    fn from(e: Error) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
 pub trait ProtocolFactory {
     type Protocol: Protocol;
 
@@ -73,7 +113,7 @@ impl<F, P: Protocol> ProtocolFactory for F where F: Fn() -> P {
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub enum Type {
     Stop = 0x00,
     Void = 0x01,
@@ -159,6 +199,7 @@ pub enum MessageType {
     Call = 0x01,
     Reply = 0x02,
     Exception = 0x03,
+    Oneway = 0x04,
 }
 
 impl fmt::Display for MessageType {
@@ -166,7 +207,8 @@ impl fmt::Display for MessageType {
         f.write_str(match *self {
             MessageType::Call => "Call",
             MessageType::Reply => "Reply",
-            MessageType::Exception => "Exception"
+            MessageType::Exception => "Exception",
+            MessageType::Oneway => "Oneway"
         })
     }
 }
@@ -179,6 +221,7 @@ impl ::std::str::FromStr for MessageType {
             "Call" => MessageType::Call,
             "Reply" => MessageType::Reply,
             "Exception" => MessageType::Exception,
+            "Oneway" => MessageType::Oneway,
             _ => return Err(())
         })
     }
@@ -190,6 +233,7 @@ impl MessageType {
             0x01 => Some(MessageType::Call),
             0x02 => Some(MessageType::Reply),
             0x03 => Some(MessageType::Exception),
+            0x04 => Some(MessageType::Oneway),
             _ => None,
         }
     }
@@ -211,6 +255,16 @@ pub trait Decode: ThriftTyped + Default {
     where P: Protocol, T: Transport;
 }
 
+/// Decoding from an in-memory buffer, allowing the result to borrow from it.
+///
+/// `&'a str` and `&'a [u8]` are decoded without copying, for protocols which
+/// support `Protocol::read_borrowed_binary`. All other types are decoded as
+/// usual. Use `helpers::decode_borrowed` as the entry point.
+pub trait DecodeBorrowed<'a>: ThriftTyped + Default {
+    fn decode_borrowed<P>(&mut self, &mut P, &mut SliceTransport<'a>) -> Result<()>
+    where P: Protocol;
+}
+
 pub trait Protocol {
     fn write_message_begin<T: Transport>(
         &mut self,
@@ -287,6 +341,17 @@ pub trait Protocol {
     fn read_binary<T: Transport>(&mut self, transport: &mut T) -> Result<Vec<u8>>;
 
     fn skip<T: Transport>(&mut self, transport: &mut T, type_: Type) -> Result<()>;
+
+    /// The limits this protocol enforces when decoding.
+    fn limits(&self) -> Limits { Limits::default() }
+
+    /// Read a binary as a slice of the input buffer, without copying.
+    ///
+    /// Protocols whose binaries aren't stored verbatim, like JSON, keep the
+    /// default, which fails with `Error::NotImplemented`.
+    fn read_borrowed_binary<'a>(&mut self, _transport: &mut SliceTransport<'a>) -> Result<&'a [u8]> {
+        Err(::Error::from(Error::NotImplemented))
+    }
 }
 
 impl<'a, T: ?Sized> ThriftTyped for &'a T where T: ThriftTyped {
@@ -477,6 +542,14 @@ impl<'a, P: ?Sized> Protocol for &'a mut P where P: Protocol {
     fn skip<T: Transport>(&mut self, transport: &mut T, type_: Type) -> Result<()> {
         <P as Protocol>::skip(self, transport, type_)
     }
+
+    fn limits(&self) -> Limits {
+        <P as Protocol>::limits(self)
+    }
+
+    fn read_borrowed_binary<'b>(&mut self, transport: &mut SliceTransport<'b>) -> Result<&'b [u8]> {
+        <P as Protocol>::read_borrowed_binary(self, transport)
+    }
 }
 
 pub trait FromNum: Sized {
@@ -484,14 +557,24 @@ pub trait FromNum: Sized {
 }
 
 pub mod helpers {
-    use protocol::{ThriftTyped, Protocol, Type, MessageType, FromNum, Decode, Encode, Error};
+    use protocol::{ThriftTyped, Protocol, Type, MessageType, FromNum, Decode, DecodeBorrowed, Encode, Error,
+                   ReplyMismatch};
     use transport::Transport;
+    use transport::slice::SliceTransport;
     use Result;
 
     pub fn typ<T: ThriftTyped + Default>() -> Type {
         T::default().typ()
     }
 
+    /// Decode a `D` from `input`, possibly borrowing from it.
+    pub fn decode_borrowed<'a, D, P>(protocol: &mut P, input: &'a [u8]) -> Result<D>
+    where D: DecodeBorrowed<'a>, P: Protocol {
+        let mut value = D::default();
+        try!(value.decode_borrowed(protocol, &mut SliceTransport::new(input)));
+        Ok(value)
+    }
+
     pub fn read_enum<F, T, P>(iprot: &mut P, transport: &mut T) -> Result<F>
     where F: FromNum, T: Transport, P: Protocol {
         let i = try!(iprot.read_i32(transport));
@@ -501,6 +584,62 @@ pub mod helpers {
         }
     }
 
+    /// Skips over a value of the given type, reading and discarding it.
+    ///
+    /// Protocols whose framing is entirely described by the `read_*_begin` methods
+    /// can implement `Protocol::skip` by delegating here. Nested values are skipped
+    /// through `Protocol::skip` again, so a protocol may still special-case some types.
+    pub fn skip<P, T>(protocol: &mut P, transport: &mut T, type_: Type) -> Result<()>
+    where P: Protocol, T: Transport {
+        match type_ {
+            Type::Bool => { try!(protocol.read_bool(transport)); }
+            Type::Byte => { try!(protocol.read_byte(transport)); }
+            Type::I16 => { try!(protocol.read_i16(transport)); }
+            Type::I32 => { try!(protocol.read_i32(transport)); }
+            Type::I64 => { try!(protocol.read_i64(transport)); }
+            Type::Double => { try!(protocol.read_double(transport)); }
+            Type::String => { try!(protocol.read_binary(transport)); }
+            Type::Struct => {
+                try!(protocol.read_struct_begin(transport));
+                loop {
+                    let (_, field_type, _) = try!(protocol.read_field_begin(transport));
+                    if field_type == Type::Stop {
+                        break;
+                    }
+                    try!(protocol.skip(transport, field_type));
+                    try!(protocol.read_field_end(transport));
+                }
+                try!(protocol.read_struct_end(transport));
+            }
+            Type::Map => {
+                let (key_type, value_type, size) = try!(protocol.read_map_begin(transport));
+                for _ in 0..size {
+                    try!(protocol.skip(transport, key_type));
+                    try!(protocol.skip(transport, value_type));
+                }
+                try!(protocol.read_map_end(transport));
+            }
+            Type::Set => {
+                let (elem_type, size) = try!(protocol.read_set_begin(transport));
+                for _ in 0..size {
+                    try!(protocol.skip(transport, elem_type));
+                }
+                try!(protocol.read_set_end(transport));
+            }
+            Type::List => {
+                let (elem_type, size) = try!(protocol.read_list_begin(transport));
+                for _ in 0..size {
+                    try!(protocol.skip(transport, elem_type));
+                }
+                try!(protocol.read_list_end(transport));
+            }
+            Type::Void => { }
+            Type::Stop => { }
+        };
+
+        Ok(())
+    }
+
     pub fn send<W, T, P>(protocol: &mut P, transport: &mut T,
                          name: &str, _type: MessageType,
                          args: &W, cseqid: i32) -> Result<()>
@@ -512,6 +651,18 @@ pub mod helpers {
         Ok(())
     }
 
+    /// What a client returns when a reply carries neither a result nor an
+    /// exception: nothing for void methods, a `MissingResult` error otherwise.
+    pub fn missing_result<R: ThriftTyped + Default>(op: &str) -> Result<R> {
+        if typ::<R>() == Type::Void {
+            Ok(R::default())
+        } else {
+            Err(::Error::from(::ApplicationException::new(
+                ::exception::ApplicationExceptionKind::MissingResult,
+                format!("{} failed: unknown result", op))))
+        }
+    }
+
     pub fn receive<R, T, P>(protocol: &mut P, transport: &mut T,
                             op: &str, result: &mut R) -> Result<()>
     where R: Decode, T: Transport, P: Protocol {
@@ -519,23 +670,45 @@ pub mod helpers {
         receive_body(protocol, transport, op, result, &name, ty, id)
     }
 
+    /// Receives the reply to the call `op` with sequence id `seqid`.
+    ///
+    /// Messages answering anything else are skipped and reported as
+    /// `Error::UnexpectedReply`.
+    pub fn receive_reply<R, T, P>(protocol: &mut P, transport: &mut T,
+                                  op: &str, seqid: i32, result: &mut R) -> Result<()>
+    where R: Decode, T: Transport, P: Protocol {
+        let (name, ty, id) = try!(protocol.read_message_begin(transport));
+        let mismatch = if id != seqid {
+            Some(ReplyMismatch::SequenceId(seqid, id))
+        } else if name != op {
+            Some(ReplyMismatch::MethodName(op.to_string(), name.clone()))
+        } else if ty != MessageType::Reply && ty != MessageType::Exception {
+            Some(ReplyMismatch::MessageType(ty))
+        } else {
+            None
+        };
+
+        match mismatch {
+            Some(mismatch) => {
+                try!(protocol.skip(transport, Type::Struct));
+                try!(protocol.read_message_end(transport));
+                Err(::Error::from(Error::UnexpectedReply(mismatch)))
+            }
+            None => receive_body(protocol, transport, op, result, &name, ty, id),
+        }
+    }
+
     pub fn receive_body<R, T, P>(protocol: &mut P, transport: &mut T, op: &str,
                                  result: &mut R, name: &str, ty: MessageType,
                                  id: i32) -> Result<()>
     where R: Decode, T: Transport, P: Protocol {
         match (name, ty, id) {
             (_, MessageType::Exception, _) => {
-                println!("got exception");
-                // TODO
-                //let x = ApplicationException;
-                //x.read(&mut protocol)
-                //protocol.read_message_end();
-                //transport.read_end();
-                //throw x
-                Err(::Error::UserException)
+                let mut error = ::ApplicationException::default();
+                try!(error.decode(protocol, transport));
+                try!(protocol.read_message_end(transport));
+                Err(::Error::ApplicationException(error))
             }
-            // TODO: Make sure the client doesn't receive Call messages and that the server
-            // doesn't receive Reply messages
             (fname, _, _) => {
                 if &fname[..] == op {
                     try!(result.decode(protocol, transport));
@@ -543,13 +716,28 @@ pub mod helpers {
                     Ok(())
                  }
                 else {
-                    // FIXME: shall we err in this case?
                     try!(protocol.skip(transport, Type::Struct));
                     try!(protocol.read_message_end(transport));
-                    Err(::Error::from(Error::ProtocolViolation))
+                    let mismatch = ReplyMismatch::MethodName(op.to_string(), fname.to_string());
+                    Err(::Error::from(Error::UnexpectedReply(mismatch)))
                 }
             }
         }
     }
+
+    /// Receives the arguments of a call whose header has been read, as
+    /// `ty`. Processors only take calls: other messages are skipped and
+    /// reported as `Error::ProtocolViolation`.
+    pub fn receive_args<A, T, P>(protocol: &mut P, transport: &mut T,
+                                 args: &mut A, ty: MessageType) -> Result<()>
+    where A: Decode, T: Transport, P: Protocol {
+        if ty != MessageType::Call && ty != MessageType::Oneway {
+            try!(protocol.skip(transport, Type::Struct));
+            try!(protocol.read_message_end(transport));
+            return Err(::Error::from(Error::ProtocolViolation));
+        }
+        try!(args.decode(protocol, transport));
+        protocol.read_message_end(transport)
+    }
 }
 
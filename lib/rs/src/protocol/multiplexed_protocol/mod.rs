@@ -0,0 +1,261 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use protocol::{Limits, MessageType, Protocol, Type};
+use transport::Transport;
+use transport::slice::SliceTransport;
+use Result;
+
+/// The separator between the service name and the method name.
+pub static SEPARATOR: &'static str = ":";
+
+/// A protocol wrapper for calling one service on a multiplexed server.
+///
+/// Outgoing calls are named `Service:method`, as expected by
+/// `processor::MultiplexedProcessor` and `TMultiplexedProcessor` in other
+/// languages. Everything else, including replies, is passed through to the
+/// wrapped protocol unchanged.
+#[derive(Clone, Debug)]
+pub struct MultiplexedProtocol<P> {
+    service_name: String,
+    inner: P,
+}
+
+impl<P: Protocol> MultiplexedProtocol<P> {
+    pub fn new<S: Into<String>>(service_name: S, inner: P) -> MultiplexedProtocol<P> {
+        MultiplexedProtocol { service_name: service_name.into(), inner: inner }
+    }
+
+    pub fn service_name(&self) -> &str {
+        &self.service_name
+    }
+
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P: Protocol> Protocol for MultiplexedProtocol<P> {
+    fn write_message_begin<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        name: &str,
+        message_type: MessageType,
+        sequence_id: i32
+    ) -> Result<()> {
+        match message_type {
+            MessageType::Call | MessageType::Oneway => {
+                let name = format!("{}{}{}", self.service_name, SEPARATOR, name);
+                self.inner.write_message_begin(transport, &name, message_type, sequence_id)
+            }
+            _ => self.inner.write_message_begin(transport, name, message_type, sequence_id),
+        }
+    }
+
+    fn write_message_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.inner.write_message_end(transport)
+    }
+
+    fn write_struct_begin<T: Transport>(&mut self, transport: &mut T, name: &str) -> Result<()> {
+        self.inner.write_struct_begin(transport, name)
+    }
+
+    fn write_struct_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.inner.write_struct_end(transport)
+    }
+
+    fn write_field_begin<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        name: &str,
+        field_type: Type,
+        field_id: i16
+    ) -> Result<()> {
+        self.inner.write_field_begin(transport, name, field_type, field_id)
+    }
+
+    fn write_field_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.inner.write_field_end(transport)
+    }
+
+    fn write_field_stop<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.inner.write_field_stop(transport)
+    }
+
+    fn write_map_begin<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        key_type: Type,
+        value_type: Type,
+        size: usize
+    ) -> Result<()> {
+        self.inner.write_map_begin(transport, key_type, value_type, size)
+    }
+
+    fn write_map_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.inner.write_map_end(transport)
+    }
+
+    fn write_list_begin<T: Transport>(&mut self, transport: &mut T, elem_type: Type, size: usize) -> Result<()> {
+        self.inner.write_list_begin(transport, elem_type, size)
+    }
+
+    fn write_list_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.inner.write_list_end(transport)
+    }
+
+    fn write_set_begin<T: Transport>(&mut self, transport: &mut T, elem_type: Type, size: usize) -> Result<()> {
+        self.inner.write_set_begin(transport, elem_type, size)
+    }
+
+    fn write_set_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.inner.write_set_end(transport)
+    }
+
+    fn write_bool<T: Transport>(&mut self, transport: &mut T, value: bool) -> Result<()> {
+        self.inner.write_bool(transport, value)
+    }
+
+    fn write_byte<T: Transport>(&mut self, transport: &mut T, value: i8) -> Result<()> {
+        self.inner.write_byte(transport, value)
+    }
+
+    fn write_i16<T: Transport>(&mut self, transport: &mut T, value: i16) -> Result<()> {
+        self.inner.write_i16(transport, value)
+    }
+
+    fn write_i32<T: Transport>(&mut self, transport: &mut T, value: i32) -> Result<()> {
+        self.inner.write_i32(transport, value)
+    }
+
+    fn write_i64<T: Transport>(&mut self, transport: &mut T, value: i64) -> Result<()> {
+        self.inner.write_i64(transport, value)
+    }
+
+    fn write_double<T: Transport>(&mut self, transport: &mut T, value: f64) -> Result<()> {
+        self.inner.write_double(transport, value)
+    }
+
+    fn write_str<T: Transport>(&mut self, transport: &mut T, value: &str) -> Result<()> {
+        self.inner.write_str(transport, value)
+    }
+
+    fn write_string<T: Transport>(&mut self, transport: &mut T, value: &String) -> Result<()> {
+        self.inner.write_string(transport, value)
+    }
+
+    fn write_binary<T: Transport>(&mut self, transport: &mut T, value: &[u8]) -> Result<()> {
+        self.inner.write_binary(transport, value)
+    }
+
+    fn read_message_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(String, MessageType, i32)> {
+        self.inner.read_message_begin(transport)
+    }
+
+    fn read_message_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.inner.read_message_end(transport)
+    }
+
+    fn read_struct_begin<T: Transport>(&mut self, transport: &mut T) -> Result<String> {
+        self.inner.read_struct_begin(transport)
+    }
+
+    fn read_struct_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.inner.read_struct_end(transport)
+    }
+
+    fn read_field_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(String, Type, i16)> {
+        self.inner.read_field_begin(transport)
+    }
+
+    fn read_field_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.inner.read_field_end(transport)
+    }
+
+    fn read_map_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(Type, Type, i32)> {
+        self.inner.read_map_begin(transport)
+    }
+
+    fn read_map_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.inner.read_map_end(transport)
+    }
+
+    fn read_list_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(Type, i32)> {
+        self.inner.read_list_begin(transport)
+    }
+
+    fn read_list_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.inner.read_list_end(transport)
+    }
+
+    fn read_set_begin<T: Transport>(&mut self, transport: &mut T) -> Result<(Type, i32)> {
+        self.inner.read_set_begin(transport)
+    }
+
+    fn read_set_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.inner.read_set_end(transport)
+    }
+
+    fn read_bool<T: Transport>(&mut self, transport: &mut T) -> Result<bool> {
+        self.inner.read_bool(transport)
+    }
+
+    fn read_byte<T: Transport>(&mut self, transport: &mut T) -> Result<i8> {
+        self.inner.read_byte(transport)
+    }
+
+    fn read_i16<T: Transport>(&mut self, transport: &mut T) -> Result<i16> {
+        self.inner.read_i16(transport)
+    }
+
+    fn read_i32<T: Transport>(&mut self, transport: &mut T) -> Result<i32> {
+        self.inner.read_i32(transport)
+    }
+
+    fn read_i64<T: Transport>(&mut self, transport: &mut T) -> Result<i64> {
+        self.inner.read_i64(transport)
+    }
+
+    fn read_double<T: Transport>(&mut self, transport: &mut T) -> Result<f64> {
+        self.inner.read_double(transport)
+    }
+
+    fn read_string<T: Transport>(&mut self, transport: &mut T) -> Result<String> {
+        self.inner.read_string(transport)
+    }
+
+    fn read_binary<T: Transport>(&mut self, transport: &mut T) -> Result<Vec<u8>> {
+        self.inner.read_binary(transport)
+    }
+
+    fn skip<T: Transport>(&mut self, transport: &mut T, type_: Type) -> Result<()> {
+        self.inner.skip(transport, type_)
+    }
+
+    fn limits(&self) -> Limits {
+        self.inner.limits()
+    }
+
+    fn read_borrowed_binary<'a>(&mut self, transport: &mut SliceTransport<'a>) -> Result<&'a [u8]> {
+        self.inner.read_borrowed_binary(transport)
+    }
+}
+
+#[cfg(test)]
+pub mod test;
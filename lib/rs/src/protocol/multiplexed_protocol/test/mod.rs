@@ -0,0 +1,50 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use super::MultiplexedProtocol;
+
+use mock::*;
+use protocol::{MessageType, Protocol};
+
+#[test]
+fn prefixes_calls() {
+    let mut protocol = MultiplexedProtocol::new("Calculator", MockProtocol::new());
+    protocol.write_message_begin(&mut MockTransport::new(vec![]), "add", MessageType::Call, 7).unwrap();
+    assert_eq!(
+        protocol.into_inner().log(),
+        &[Message(Begin((String::from("Calculator:add"), MessageType::Call, 7)))]
+    );
+}
+
+#[test]
+fn passes_replies_through() {
+    let mut protocol = MultiplexedProtocol::new("Calculator", MockProtocol::new());
+    let transport = &mut MockTransport::new(vec![]);
+    protocol.write_message_begin(transport, "add", MessageType::Reply, 7).unwrap();
+    protocol.write_i32(transport, 5).unwrap();
+    protocol.write_message_end(transport).unwrap();
+    assert_eq!(
+        protocol.into_inner().log(),
+        &[
+            Message(Begin((String::from("add"), MessageType::Reply, 7))),
+            Prim(I32(5)),
+            Message(End),
+        ]
+    );
+}
@@ -0,0 +1,339 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::io::Cursor;
+
+use protocol::{self, Encode, MessageType, Protocol, Type};
+use protocol::json::{self, Context};
+use transport::{RwTransport, Transport};
+use {Result, Error};
+
+use podio::WritePodExt;
+
+/// A write-only protocol producing plain, human-readable JSON.
+///
+/// Structs are written as objects keyed by field name, maps as objects, lists
+/// and sets as arrays, enums as their numeric values and binaries as base64
+/// strings. Field ids and types are not part of the output, so it cannot be
+/// read back; all read methods fail with `Error::NotImplemented`. Use
+/// `JsonProtocol` when the data has to round-trip.
+#[derive(Clone, Debug)]
+pub struct SimpleJsonProtocol {
+    contexts: Vec<Context>,
+}
+
+impl Default for SimpleJsonProtocol {
+    fn default() -> SimpleJsonProtocol {
+        SimpleJsonProtocol { contexts: vec![Context::Base] }
+    }
+}
+
+/// Renders `value` as simple JSON, e.g. for logging.
+pub fn to_string<E: Encode>(value: &E) -> Result<String> {
+    let mut transport = RwTransport(Cursor::new(Vec::new()));
+    try!(value.encode(&mut SimpleJsonProtocol::new(), &mut transport));
+    let bytes = transport.0.into_inner();
+    Ok(try!(String::from_utf8(bytes).map_err(|e| protocol::Error::from(e.utf8_error()))))
+}
+
+impl SimpleJsonProtocol {
+    pub fn new() -> SimpleJsonProtocol {
+        SimpleJsonProtocol::default()
+    }
+
+    fn context(&mut self) -> &mut Context {
+        self.contexts.last_mut().expect("JSON context stack is never empty")
+    }
+
+    fn pop_context(&mut self) -> Result<()> {
+        if self.contexts.len() > 1 {
+            self.contexts.pop();
+            Ok(())
+        } else {
+            Err(Error::from(protocol::Error::ProtocolViolation))
+        }
+    }
+
+    fn write_raw<T: Transport>(&mut self, transport: &mut T, bytes: &[u8]) -> Result<()> {
+        Ok(try!(transport.write_all(bytes)))
+    }
+
+    /// Writes the separator due before the next value and returns whether that
+    /// value is an object key.
+    fn write_context<T: Transport>(&mut self, transport: &mut T) -> Result<bool> {
+        if let Some(separator) = self.context().next() {
+            try!(transport.write_u8(separator));
+        }
+        Ok(self.context().escape_num())
+    }
+
+    fn write_unquoted<T: Transport>(&mut self, transport: &mut T, value: &str) -> Result<()> {
+        let formatted = if try!(self.write_context(transport)) {
+            format!("\"{}\"", value)
+        } else {
+            value.to_string()
+        };
+        self.write_raw(transport, formatted.as_bytes())
+    }
+
+    fn write_quoted<T: Transport>(&mut self, transport: &mut T, value: &[u8]) -> Result<()> {
+        try!(self.write_context(transport));
+        let mut escaped = Vec::with_capacity(value.len() + 2);
+        json::escape_string(value, &mut escaped);
+        self.write_raw(transport, &escaped)
+    }
+
+    fn write_open<T: Transport>(&mut self, transport: &mut T, open: &[u8], context: Context) -> Result<()> {
+        if try!(self.write_context(transport)) {
+            // JSON object keys have to be strings.
+            return Err(Error::from(protocol::Error::NotImplemented));
+        }
+        try!(self.write_raw(transport, open));
+        self.contexts.push(context);
+        Ok(())
+    }
+
+    fn write_object_begin<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.write_open(transport, b"{", Context::Pair { first: true, colon: true })
+    }
+
+    fn write_object_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        try!(self.pop_context());
+        self.write_raw(transport, b"}")
+    }
+
+    fn write_array_begin<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.write_open(transport, b"[", Context::List { first: true })
+    }
+
+    fn write_array_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        try!(self.pop_context());
+        self.write_raw(transport, b"]")
+    }
+}
+
+fn not_implemented<R>() -> Result<R> {
+    Err(Error::from(protocol::Error::NotImplemented))
+}
+
+impl Protocol for SimpleJsonProtocol {
+    fn write_message_begin<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        name: &str,
+        message_type: MessageType,
+        sequence_id: i32
+    ) -> Result<()> {
+        try!(self.write_array_begin(transport));
+        try!(self.write_quoted(transport, name.as_bytes()));
+        try!(self.write_unquoted(transport, &(message_type as i32).to_string()));
+        self.write_unquoted(transport, &sequence_id.to_string())
+    }
+
+    fn write_message_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.write_array_end(transport)
+    }
+
+    fn write_struct_begin<T: Transport>(&mut self, transport: &mut T, _name: &str) -> Result<()> {
+        self.write_object_begin(transport)
+    }
+
+    fn write_struct_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.write_object_end(transport)
+    }
+
+    fn write_field_begin<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        name: &str,
+        _field_type: Type,
+        _field_id: i16
+    ) -> Result<()> {
+        self.write_quoted(transport, name.as_bytes())
+    }
+
+    fn write_field_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_field_stop<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_map_begin<T: Transport>(
+        &mut self,
+        transport: &mut T,
+        _key_type: Type,
+        _value_type: Type,
+        _size: usize
+    ) -> Result<()> {
+        self.write_object_begin(transport)
+    }
+
+    fn write_map_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.write_object_end(transport)
+    }
+
+    fn write_list_begin<T: Transport>(&mut self, transport: &mut T, _elem_type: Type, _size: usize) -> Result<()> {
+        self.write_array_begin(transport)
+    }
+
+    fn write_list_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.write_array_end(transport)
+    }
+
+    fn write_set_begin<T: Transport>(&mut self, transport: &mut T, _elem_type: Type, _size: usize) -> Result<()> {
+        self.write_array_begin(transport)
+    }
+
+    fn write_set_end<T: Transport>(&mut self, transport: &mut T) -> Result<()> {
+        self.write_array_end(transport)
+    }
+
+    fn write_bool<T: Transport>(&mut self, transport: &mut T, value: bool) -> Result<()> {
+        self.write_unquoted(transport, if value { "true" } else { "false" })
+    }
+
+    fn write_byte<T: Transport>(&mut self, transport: &mut T, value: i8) -> Result<()> {
+        self.write_unquoted(transport, &value.to_string())
+    }
+
+    fn write_i16<T: Transport>(&mut self, transport: &mut T, value: i16) -> Result<()> {
+        self.write_unquoted(transport, &value.to_string())
+    }
+
+    fn write_i32<T: Transport>(&mut self, transport: &mut T, value: i32) -> Result<()> {
+        self.write_unquoted(transport, &value.to_string())
+    }
+
+    fn write_i64<T: Transport>(&mut self, transport: &mut T, value: i64) -> Result<()> {
+        self.write_unquoted(transport, &value.to_string())
+    }
+
+    fn write_double<T: Transport>(&mut self, transport: &mut T, value: f64) -> Result<()> {
+        if value.is_nan() {
+            self.write_quoted(transport, b"NaN")
+        } else if value.is_infinite() {
+            self.write_quoted(transport, if value > 0.0 { b"Infinity" } else { b"-Infinity" })
+        } else {
+            self.write_unquoted(transport, &format!("{:?}", value))
+        }
+    }
+
+    fn write_str<T: Transport>(&mut self, transport: &mut T, value: &str) -> Result<()> {
+        self.write_quoted(transport, value.as_bytes())
+    }
+
+    fn write_string<T: Transport>(&mut self, transport: &mut T, value: &String) -> Result<()> {
+        self.write_quoted(transport, value.as_bytes())
+    }
+
+    fn write_binary<T: Transport>(&mut self, transport: &mut T, value: &[u8]) -> Result<()> {
+        let mut encoded = Vec::with_capacity(value.len() * 4 / 3 + 4);
+        json::base64_encode(value, &mut encoded);
+        self.write_quoted(transport, &encoded)
+    }
+
+    fn read_message_begin<T: Transport>(&mut self, _transport: &mut T) -> Result<(String, MessageType, i32)> {
+        not_implemented()
+    }
+
+    fn read_message_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        not_implemented()
+    }
+
+    fn read_struct_begin<T: Transport>(&mut self, _transport: &mut T) -> Result<String> {
+        not_implemented()
+    }
+
+    fn read_struct_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        not_implemented()
+    }
+
+    fn read_field_begin<T: Transport>(&mut self, _transport: &mut T) -> Result<(String, Type, i16)> {
+        not_implemented()
+    }
+
+    fn read_field_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        not_implemented()
+    }
+
+    fn read_map_begin<T: Transport>(&mut self, _transport: &mut T) -> Result<(Type, Type, i32)> {
+        not_implemented()
+    }
+
+    fn read_map_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        not_implemented()
+    }
+
+    fn read_list_begin<T: Transport>(&mut self, _transport: &mut T) -> Result<(Type, i32)> {
+        not_implemented()
+    }
+
+    fn read_list_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        not_implemented()
+    }
+
+    fn read_set_begin<T: Transport>(&mut self, _transport: &mut T) -> Result<(Type, i32)> {
+        not_implemented()
+    }
+
+    fn read_set_end<T: Transport>(&mut self, _transport: &mut T) -> Result<()> {
+        not_implemented()
+    }
+
+    fn read_bool<T: Transport>(&mut self, _transport: &mut T) -> Result<bool> {
+        not_implemented()
+    }
+
+    fn read_byte<T: Transport>(&mut self, _transport: &mut T) -> Result<i8> {
+        not_implemented()
+    }
+
+    fn read_i16<T: Transport>(&mut self, _transport: &mut T) -> Result<i16> {
+        not_implemented()
+    }
+
+    fn read_i32<T: Transport>(&mut self, _transport: &mut T) -> Result<i32> {
+        not_implemented()
+    }
+
+    fn read_i64<T: Transport>(&mut self, _transport: &mut T) -> Result<i64> {
+        not_implemented()
+    }
+
+    fn read_double<T: Transport>(&mut self, _transport: &mut T) -> Result<f64> {
+        not_implemented()
+    }
+
+    fn read_string<T: Transport>(&mut self, _transport: &mut T) -> Result<String> {
+        not_implemented()
+    }
+
+    fn read_binary<T: Transport>(&mut self, _transport: &mut T) -> Result<Vec<u8>> {
+        not_implemented()
+    }
+
+    fn skip<T: Transport>(&mut self, _transport: &mut T, _type_: Type) -> Result<()> {
+        not_implemented()
+    }
+}
+
+#[cfg(test)]
+pub mod test;
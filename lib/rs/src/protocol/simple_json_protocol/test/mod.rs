@@ -0,0 +1,91 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::io::Cursor;
+
+use super::SimpleJsonProtocol;
+
+use mock::MockTransport;
+use protocol::{self, Protocol, Type, MessageType};
+use transport::RwTransport;
+use Error;
+
+fn written<F>(f: F) -> String where F: FnOnce(&mut SimpleJsonProtocol, &mut RwTransport<Cursor<Vec<u8>>>) {
+    let mut transport = RwTransport(Cursor::new(Vec::new()));
+    let mut protocol = SimpleJsonProtocol::new();
+    f(&mut protocol, &mut transport);
+    String::from_utf8((transport.0).into_inner()).unwrap()
+}
+
+#[test]
+fn write_message() {
+    let json = written(|p, t| {
+        p.write_message_begin(t, "add", MessageType::Call, 7).unwrap();
+        p.write_struct_begin(t, "Args").unwrap();
+        p.write_field_begin(t, "num1", Type::I32, 1).unwrap();
+        p.write_i32(t, 15).unwrap();
+        p.write_field_end(t).unwrap();
+        p.write_field_begin(t, "flag", Type::Bool, 2).unwrap();
+        p.write_bool(t, true).unwrap();
+        p.write_field_end(t).unwrap();
+        p.write_field_stop(t).unwrap();
+        p.write_struct_end(t).unwrap();
+        p.write_message_end(t).unwrap();
+    });
+    assert_eq!(json, r#"["add",1,7,{"num1":15,"flag":true}]"#);
+}
+
+#[test]
+fn write_containers() {
+    let json = written(|p, t| {
+        p.write_map_begin(t, Type::I32, Type::List, 2).unwrap();
+        p.write_i32(t, 1).unwrap();
+        p.write_list_begin(t, Type::Double, 2).unwrap();
+        p.write_double(t, 1.5).unwrap();
+        p.write_double(t, ::std::f64::NAN).unwrap();
+        p.write_list_end(t).unwrap();
+        p.write_i32(t, 2).unwrap();
+        p.write_set_begin(t, Type::String, 1).unwrap();
+        p.write_binary(t, b"hello").unwrap();
+        p.write_set_end(t).unwrap();
+        p.write_map_end(t).unwrap();
+    });
+    assert_eq!(json, r#"{"1":[1.5,"NaN"],"2":["aGVsbG8"]}"#);
+}
+
+#[test]
+fn write_container_as_map_key() {
+    let mut transport = RwTransport(Cursor::new(Vec::new()));
+    let mut protocol = SimpleJsonProtocol::new();
+    protocol.write_map_begin(&mut transport, Type::List, Type::I32, 1).unwrap();
+    match protocol.write_list_begin(&mut transport, Type::I32, 0).unwrap_err() {
+        Error::ProtocolError(e) => assert_eq!(e, protocol::Error::NotImplemented),
+        e => panic!("Expected a protocol error, got {:?}", e)
+    }
+}
+
+#[test]
+fn read_is_not_implemented() {
+    let transport = &mut MockTransport::new(b"{}".to_vec());
+    let mut protocol = SimpleJsonProtocol::new();
+    match protocol.read_struct_begin(transport).unwrap_err() {
+        Error::ProtocolError(e) => assert_eq!(e, protocol::Error::NotImplemented),
+        e => panic!("Expected a protocol error, got {:?}", e)
+    }
+}
@@ -19,6 +19,17 @@
 
 pub mod simple_server;
 pub mod threaded;
+pub mod pipelined;
+pub mod pooled;
+#[cfg(feature = "nonblocking")]
+pub mod nonblocking;
+
+mod pool;
+mod request;
 
 pub use self::simple_server::SimpleServer;
 pub use self::threaded::ThreadedServer;
+pub use self::pipelined::PipelinedServer;
+pub use self::pooled::{PooledServer, ServerHandle};
+#[cfg(feature = "nonblocking")]
+pub use self::nonblocking::NonblockingServer;
@@ -0,0 +1,287 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::cmp;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net;
+use std::sync::{Arc, mpsc};
+
+use mio::{Events, Interest, Poll, Registry, Token, Waker};
+use mio::net::{TcpListener, TcpStream};
+
+use processor::Processor;
+use protocol::{Protocol, ProtocolFactory};
+use server::pool::ThreadPool;
+use server::request;
+use transport::memory::MemoryTransport;
+
+const LISTENER: Token = Token(0);
+const WAKER: Token = Token(1);
+
+/// What a worker hands back to the event loop: the reply to send, empty
+/// for oneway calls, or `None` if the connection should be closed.
+type Done = (Token, Option<Vec<u8>>);
+
+/// Hands the reply back once the job is over, even if it panicked.
+struct DoneGuard {
+    token: Token,
+    reply: Option<Vec<u8>>,
+    done: mpsc::Sender<Done>,
+    waker: Arc<Waker>,
+}
+
+impl DoneGuard {
+    fn finish(mut self, reply: Option<Vec<u8>>) {
+        self.reply = reply;
+    }
+}
+
+impl Drop for DoneGuard {
+    fn drop(&mut self) {
+        let _ = self.done.send((self.token, self.reply.take()));
+        let _ = self.waker.wake();
+    }
+}
+
+/// A server running all its connections on one thread, like the
+/// TNonblockingServer of other Thrift implementations.
+///
+/// Clients have to use the framed transport (`transport::framed`). The event
+/// loop reads whatever arrives on any connection and hands every complete
+/// frame to a pool of workers running the processor. Each connection has at
+/// most one request processed at a time, so its replies stay in order, and
+/// idle connections don't tie up any thread.
+pub struct NonblockingServer<P, PF> {
+    inner: Arc<NonblockingServerInner<P, PF>>,
+    max_frame_size: usize,
+}
+
+struct NonblockingServerInner<P, PF> {
+    processor: P,
+    protocol_factory: PF,
+}
+
+struct Connection {
+    stream: TcpStream,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    processing: bool,
+}
+
+impl<P, PF> NonblockingServer<P, PF>
+where P: Processor<PF::Protocol, MemoryTransport> + Send + Sync + 'static,
+      PF: ProtocolFactory + Send + Sync + 'static {
+
+    /// Frames may be as large as the protocols' `max_message_size`.
+    pub fn new(processor: P, factory: PF) -> Self {
+        let max_frame_size = factory.new_protocol().limits().max_message_size;
+        NonblockingServer {
+            inner: Arc::new(NonblockingServerInner {
+                processor: processor,
+                protocol_factory: factory,
+            }),
+            max_frame_size: max_frame_size,
+        }
+    }
+
+    /// Serves the connections accepted by `listener` forever, processing
+    /// requests on `workers` threads. Only returns if polling fails.
+    pub fn serve(self, listener: net::TcpListener, workers: usize) -> io::Result<()> {
+        try!(listener.set_nonblocking(true));
+        let mut listener = TcpListener::from_std(listener);
+
+        let mut poll = try!(Poll::new());
+        try!(poll.registry().register(&mut listener, LISTENER, Interest::READABLE));
+        let waker = Arc::new(try!(Waker::new(poll.registry(), WAKER)));
+        let (done_tx, done_rx) = mpsc::channel::<Done>();
+        let pool = ThreadPool::new(workers);
+
+        let mut connections = HashMap::new();
+        let mut next_token = WAKER.0 + 1;
+        let mut events = Events::with_capacity(1024);
+
+        loop {
+            if let Err(e) = poll.poll(&mut events, None) {
+                if e.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(e);
+            }
+
+            for event in events.iter() {
+                match event.token() {
+                    LISTENER => loop {
+                        match listener.accept() {
+                            Ok((mut stream, _)) => {
+                                let token = Token(next_token);
+                                next_token += 1;
+                                let interest = Interest::READABLE | Interest::WRITABLE;
+                                if poll.registry().register(&mut stream, token, interest).is_ok() {
+                                    connections.insert(token, Connection::new(stream));
+                                }
+                            }
+                            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                            Err(e) => {
+                                warn!("failed to accept connection: {}", e);
+                                break;
+                            }
+                        }
+                    },
+                    WAKER => {
+                        while let Ok((token, reply)) = done_rx.try_recv() {
+                            let open = match (connections.get_mut(&token), reply) {
+                                (Some(connection), Some(reply)) => {
+                                    connection.processing = false;
+                                    connection.queue(&reply);
+                                    self.run(connection, token, &pool, &done_tx, &waker)
+                                }
+                                (Some(_), None) => false,
+                                (None, _) => continue,
+                            };
+                            if !open {
+                                close(poll.registry(), &mut connections, token);
+                            }
+                        }
+                    }
+                    token => {
+                        let open = match connections.get_mut(&token) {
+                            Some(connection) => self.run(connection, token, &pool, &done_tx, &waker),
+                            None => continue,
+                        };
+                        if !open {
+                            close(poll.registry(), &mut connections, token);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Moves `connection` along as far as it goes without blocking, and
+    /// tells whether it is still open.
+    fn run(&self, connection: &mut Connection, token: Token,
+           pool: &ThreadPool, done: &mpsc::Sender<Done>, waker: &Arc<Waker>) -> bool {
+        // Reading stops once a whole frame of the largest size could be
+        // buffered, until the request being processed is done.
+        match connection.read(self.max_frame_size.saturating_add(4)) {
+            Ok(true) => {}
+            Ok(false) | Err(_) => return false,
+        }
+        if connection.write().is_err() {
+            return false;
+        }
+
+        if connection.processing {
+            return true;
+        }
+        let request = match connection.next_frame(self.max_frame_size) {
+            Ok(Some(request)) => request,
+            Ok(None) => return true,
+            Err(_) => return false,
+        };
+
+        connection.processing = true;
+        let inner = self.inner.clone();
+        let guard = DoneGuard { token: token, reply: None, done: done.clone(), waker: waker.clone() };
+        pool.execute(move || {
+            // Failed requests are answered with an exception, but without a
+            // header there is nobody to answer.
+            guard.finish(match request::read_header(&inner.protocol_factory, &request) {
+                Ok(header) => Some(request::process(&inner.processor, &inner.protocol_factory, request, header)),
+                Err(e) => {
+                    warn!("closing connection after a request without a valid message header: {}", e);
+                    None
+                }
+            });
+        });
+        true
+    }
+}
+
+fn close(registry: &Registry, connections: &mut HashMap<Token, Connection>, token: Token) {
+    if let Some(mut connection) = connections.remove(&token) {
+        let _ = registry.deregister(&mut connection.stream);
+    }
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> Connection {
+        Connection { stream: stream, input: Vec::new(), output: Vec::new(), processing: false }
+    }
+
+    /// Reads what is available until `limit` bytes are buffered, and tells
+    /// whether the peer is still there.
+    fn read(&mut self, limit: usize) -> io::Result<bool> {
+        let mut buf = [0; 4096];
+        while self.input.len() < limit {
+            let len = cmp::min(buf.len(), limit - self.input.len());
+            match self.stream.read(&mut buf[..len]) {
+                Ok(0) => return Ok(false),
+                Ok(n) => self.input.extend_from_slice(&buf[..n]),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(true),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Writes as much of the queued output as the socket takes.
+    fn write(&mut self) -> io::Result<()> {
+        while !self.output.is_empty() {
+            match self.stream.write(&self.output) {
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::WriteZero, "failed to write reply")),
+                Ok(n) => { self.output.drain(..n); }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Queues `reply` as a frame, unless there is nothing to send.
+    fn queue(&mut self, reply: &[u8]) {
+        if !reply.is_empty() {
+            let len = reply.len() as u32;
+            self.output.extend_from_slice(&[(len >> 24) as u8, (len >> 16) as u8, (len >> 8) as u8, len as u8]);
+            self.output.extend_from_slice(reply);
+        }
+    }
+
+    /// Takes the first frame out of the input, if it has arrived completely.
+    fn next_frame(&mut self, max_frame_size: usize) -> io::Result<Option<Vec<u8>>> {
+        if self.input.len() < 4 {
+            return Ok(None);
+        }
+        let len = ((self.input[0] as i32) << 24) | ((self.input[1] as i32) << 16)
+                | ((self.input[2] as i32) << 8) | (self.input[3] as i32);
+        if len < 0 || len as usize > max_frame_size {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too large"));
+        }
+        let end = 4 + len as usize;
+        if self.input.len() < end {
+            return Ok(None);
+        }
+        let frame = self.input[4..end].to_vec();
+        self.input.drain(..end);
+        Ok(Some(frame))
+    }
+}
@@ -0,0 +1,180 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use protocol::{Protocol, ProtocolFactory};
+use processor::Processor;
+use server::pool::{Semaphore, ThreadPool};
+use server::request;
+use transport::{Split, Transport};
+use transport::framed::FramedTransport;
+use transport::memory::MemoryTransport;
+use transport::server::TransportServer;
+
+type AcceptErrorHandler = Box<Fn(&io::Error) + Send + Sync>;
+
+/// A server processing several requests of one connection at once.
+///
+/// Clients have to use the framed transport (`transport::framed`). Every
+/// connection gets a thread reading its requests, which are then processed
+/// by a pool of workers shared by all connections. Replies are written as
+/// soon as they are ready, so a pipelining client isn't held up by a slow
+/// call made before its others; it tells the replies apart by their
+/// sequence ids, e.g. with a `client::Channel`.
+///
+/// By default, up to 64 connections are served at once, each with up to 16
+/// requests waiting for or being processed. Further requests are only read
+/// once earlier ones are done.
+pub struct PipelinedServer<P, PF, TS> {
+    inner: Arc<PipelinedServerInner<P, PF>>,
+    transport_server: TS,
+    max_connections: usize,
+    on_accept_error: AcceptErrorHandler,
+}
+
+struct PipelinedServerInner<P, PF> {
+    processor: P,
+    protocol_factory: PF,
+    max_frame_size: usize,
+    max_in_flight: usize,
+}
+
+impl<P, PF, TS> PipelinedServer<P, PF, TS>
+where P: Processor<PF::Protocol, MemoryTransport> + Send + Sync + 'static,
+      PF: ProtocolFactory + Send + Sync + 'static,
+      TS: TransportServer,
+      TS::Transport: Split {
+
+    /// Frames may be as large as the protocols' `max_message_size`.
+    pub fn new(processor: P, factory: PF, server: TS) -> Self {
+        let max_frame_size = factory.new_protocol().limits().max_message_size;
+        PipelinedServer {
+            inner: Arc::new(PipelinedServerInner {
+                processor: processor,
+                protocol_factory: factory,
+                max_frame_size: max_frame_size,
+                max_in_flight: 16,
+            }),
+            transport_server: server,
+            max_connections: 64,
+            on_accept_error: Box::new(|e| warn!("failed to accept connection: {}", e)),
+        }
+    }
+
+    pub fn with_max_connections(mut self, max_connections: usize) -> Self {
+        assert!(max_connections != 0, "Can't serve 0 connections.");
+        self.max_connections = max_connections;
+        self
+    }
+
+    /// How many requests of one connection may wait for or be processed at
+    /// once.
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        assert!(max_in_flight != 0, "Can't process 0 requests at once.");
+        Arc::get_mut(&mut self.inner).unwrap().max_in_flight = max_in_flight;
+        self
+    }
+
+    /// Called for every failed `accept`, instead of logging it.
+    pub fn on_accept_error<F>(mut self, handler: F) -> Self
+    where F: Fn(&io::Error) + Send + Sync + 'static {
+        self.on_accept_error = Box::new(handler);
+        self
+    }
+
+    /// Serves connections forever, processing requests on `workers` threads.
+    pub fn serve(self, workers: usize) {
+        let pool = Arc::new(ThreadPool::new(workers));
+        let connections = Semaphore::new(self.max_connections);
+
+        loop {
+            let permit = Semaphore::acquire(&connections);
+            let transport = match self.transport_server.accept() {
+                Ok(transport) => transport,
+                Err(e) => {
+                    (self.on_accept_error)(&e);
+                    // Don't spin while e.g. out of file descriptors.
+                    thread::sleep(Duration::from_millis(10));
+                    continue;
+                }
+            };
+            let (reader, writer) = match transport.split() {
+                Ok(halves) => halves,
+                Err(_) => continue,
+            };
+
+            let shared = self.inner.clone();
+            let pool = pool.clone();
+            thread::spawn(move || {
+                shared.serve_connection(reader, writer, &pool);
+                drop(permit);
+            });
+        }
+    }
+}
+
+impl<P, PF> PipelinedServerInner<P, PF>
+where P: Processor<PF::Protocol, MemoryTransport> + Send + Sync + 'static,
+      PF: ProtocolFactory + Send + Sync + 'static {
+
+    /// Reads the requests of a connection until it fails, or sends a frame
+    /// which isn't a message at all. Requests read until then are still
+    /// answered before the connection is closed.
+    fn serve_connection<R, W>(self: Arc<Self>, reader: R, writer: W, pool: &ThreadPool)
+    where R: Transport, W: Transport + Send + 'static {
+        let mut reader = FramedTransport::new(reader).with_max_frame_size(self.max_frame_size);
+        let writer = Arc::new(Mutex::new(FramedTransport::new(writer)));
+        let in_flight = Semaphore::new(self.max_in_flight);
+
+        loop {
+            let permit = Semaphore::acquire(&in_flight);
+            let request = match reader.read_frame() {
+                Ok(request) => request,
+                Err(_) => break,
+            };
+            let header = match request::read_header(&self.protocol_factory, &request) {
+                Ok(header) => header,
+                Err(e) => {
+                    warn!("closing connection after a request without a valid message header: {}", e);
+                    break;
+                }
+            };
+
+            let shared = self.clone();
+            let writer = writer.clone();
+            pool.execute(move || {
+                let reply = request::process(&shared.processor, &shared.protocol_factory, request, header);
+                if !reply.is_empty() {
+                    let mut writer = writer.lock().unwrap();
+                    let _ = writer.write_all(&reply).and_then(|_| writer.flush());
+                }
+                drop(permit);
+            });
+        }
+
+        // Wait for the requests still in flight, whose replies keep the
+        // connection open until they are written. The permit taken for the
+        // frame that wasn't read has been given back on `break`.
+        let _all: Vec<_> = (0..self.max_in_flight).map(|_| Semaphore::acquire(&in_flight)).collect();
+    }
+}
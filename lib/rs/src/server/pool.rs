@@ -0,0 +1,101 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, mpsc};
+use std::thread;
+
+type Job = Box<FnOnce() + Send>;
+
+/// A fixed number of threads running jobs in the order they were queued.
+///
+/// Dropping the pool lets the queued jobs finish and waits for the threads.
+pub struct ThreadPool {
+    jobs: Option<mpsc::Sender<Job>>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl ThreadPool {
+    pub fn new(threads: usize) -> ThreadPool {
+        assert!(threads != 0, "Can't run jobs on 0 threads.");
+
+        let (tx, rx) = mpsc::channel::<Job>();
+        let rx = Arc::new(Mutex::new(rx));
+        let threads = (0..threads).map(|_| {
+            let rx = rx.clone();
+            thread::spawn(move || loop {
+                let job = match rx.lock().unwrap().recv() {
+                    Ok(job) => job,
+                    Err(_) => return,
+                };
+                // A panicking job mustn't take the thread down with it.
+                let _ = panic::catch_unwind(AssertUnwindSafe(job));
+            })
+        }).collect();
+
+        ThreadPool { jobs: Some(tx), threads: threads }
+    }
+
+    pub fn execute<F: FnOnce() + Send + 'static>(&self, job: F) {
+        // The threads only stop once the sender is dropped.
+        let _ = self.jobs.as_ref().unwrap().send(Box::new(job));
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        drop(self.jobs.take());
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Hands out a fixed number of permits, e.g. to bound how many
+/// connections or requests are being served at once.
+pub struct Semaphore {
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+/// A permit of a `Semaphore`, given back when dropped.
+pub struct Permit(Arc<Semaphore>);
+
+impl Semaphore {
+    pub fn new(permits: usize) -> Arc<Semaphore> {
+        Arc::new(Semaphore { available: Mutex::new(permits), released: Condvar::new() })
+    }
+
+    /// Waits until a permit is available.
+    pub fn acquire(semaphore: &Arc<Semaphore>) -> Permit {
+        let mut available = semaphore.available.lock().unwrap();
+        while *available == 0 {
+            available = semaphore.released.wait(available).unwrap();
+        }
+        *available -= 1;
+        Permit(semaphore.clone())
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        *self.0.available.lock().unwrap() += 1;
+        self.0.released.notify_one();
+    }
+}
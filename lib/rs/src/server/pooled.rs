@@ -0,0 +1,263 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use processor::Processor;
+use protocol::ProtocolFactory;
+use server::pool::ThreadPool;
+use transport::server::{Connection, TransportServer};
+
+type AcceptErrorHandler = Box<Fn(&io::Error) + Send + Sync>;
+
+/// A server running each connection on one of a fixed number of workers,
+/// which can be shut down.
+///
+/// At most `max_connections` connections are open at once, beyond which
+/// new ones wait in the listen backlog. Connections without a free worker
+/// wait for one, so by default there are as many as there are workers.
+pub struct PooledServer<P, PF> {
+    processor: P,
+    protocol_factory: PF,
+    workers: usize,
+    max_connections: usize,
+    drain_timeout: Duration,
+    on_accept_error: AcceptErrorHandler,
+}
+
+/// Controls a running `PooledServer`.
+pub struct ServerHandle {
+    shared: Arc<Shared>,
+    /// Makes the acceptor return from `accept`.
+    wake: Box<Fn() -> io::Result<()> + Send + Sync>,
+    acceptor: thread::JoinHandle<()>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    /// Notified when a connection closes or the server is shut down.
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    shutdown: bool,
+    /// The open connections, to close idle ones on shutdown and those still
+    /// busy when draining times out.
+    connections: HashMap<usize, Entry>,
+}
+
+struct Entry {
+    close: Box<Fn() + Send>,
+    /// Whether a request is being processed.
+    busy: bool,
+}
+
+impl<P, PF> PooledServer<P, PF>
+where PF: ProtocolFactory + Send + Sync + 'static {
+
+    /// Runs on 4 workers, draining for up to 30 seconds on shutdown.
+    pub fn new(processor: P, factory: PF) -> Self {
+        PooledServer {
+            processor: processor,
+            protocol_factory: factory,
+            workers: 4,
+            max_connections: 4,
+            drain_timeout: Duration::from_secs(30),
+            on_accept_error: Box::new(|e| warn!("failed to accept connection: {}", e)),
+        }
+    }
+
+    /// Also sets `max_connections` to `workers`.
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        assert!(workers != 0, "Can't serve on 0 workers.");
+        self.workers = workers;
+        self.max_connections = workers;
+        self
+    }
+
+    pub fn with_max_connections(mut self, max_connections: usize) -> Self {
+        assert!(max_connections != 0, "Can't serve 0 connections.");
+        self.max_connections = max_connections;
+        self
+    }
+
+    /// How long a shutdown waits for requests being processed, before it
+    /// closes their connections. `join` still waits for their handlers to
+    /// return.
+    pub fn with_drain_timeout(mut self, drain_timeout: Duration) -> Self {
+        self.drain_timeout = drain_timeout;
+        self
+    }
+
+    /// Called for every failed `accept`, instead of logging it.
+    pub fn on_accept_error<F>(mut self, handler: F) -> Self
+    where F: Fn(&io::Error) + Send + Sync + 'static {
+        self.on_accept_error = Box::new(handler);
+        self
+    }
+
+    /// Starts serving the connections accepted by `server` in the background.
+    pub fn serve<TS>(self, server: TS) -> ServerHandle
+    where P: Processor<PF::Protocol, TS::Transport> + Send + Sync + 'static,
+          TS: TransportServer + Send + Sync + 'static,
+          TS::Transport: Connection + Send + 'static {
+        let shared = Arc::new(Shared { state: Mutex::new(State::default()), changed: Condvar::new() });
+        let server = Arc::new(server);
+
+        let acceptor = {
+            let (shared, server) = (shared.clone(), server.clone());
+            thread::spawn(move || self.accept(&*server, shared))
+        };
+
+        ServerHandle { shared: shared, wake: Box::new(move || server.wake()), acceptor: acceptor }
+    }
+
+    fn accept<TS>(self, server: &TS, shared: Arc<Shared>)
+    where P: Processor<PF::Protocol, TS::Transport> + Send + Sync + 'static,
+          TS: TransportServer,
+          TS::Transport: Connection + Send + 'static {
+        let pool = ThreadPool::new(self.workers);
+        let on_accept_error = self.on_accept_error;
+        let drain_timeout = self.drain_timeout;
+        let max_connections = self.max_connections;
+        let handler = Arc::new((self.processor, self.protocol_factory));
+        let mut next_id = 0;
+
+        loop {
+            {
+                let mut state = shared.state.lock().unwrap();
+                while !state.shutdown && state.connections.len() >= max_connections {
+                    state = shared.changed.wait(state).unwrap();
+                }
+                if state.shutdown {
+                    break;
+                }
+            }
+
+            let result = server.accept();
+            // After a shutdown, this may be the connection waking us up.
+            if shared.state.lock().unwrap().shutdown {
+                break;
+            }
+            let transport = match result {
+                Ok(transport) => transport,
+                Err(e) => {
+                    on_accept_error(&e);
+                    // Don't spin while e.g. out of file descriptors.
+                    thread::sleep(Duration::from_millis(10));
+                    continue;
+                }
+            };
+            let close = match transport.closer() {
+                Ok(close) => close,
+                Err(e) => {
+                    on_accept_error(&e);
+                    continue;
+                }
+            };
+            shared.state.lock().unwrap().connections.insert(next_id, Entry { close: close, busy: false });
+
+            let (handler, shared, id) = (handler.clone(), shared.clone(), next_id);
+            next_id += 1;
+            pool.execute(move || {
+                serve_connection(&handler.0, &handler.1, transport, id, &shared);
+                shared.state.lock().unwrap().connections.remove(&id);
+                shared.changed.notify_all();
+            });
+        }
+
+        // Idle connections were closed by `shutdown`, busy ones close once
+        // their current request is done.
+        let deadline = Instant::now() + drain_timeout;
+        let mut state = shared.state.lock().unwrap();
+        while !state.connections.is_empty() {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            state = shared.changed.wait_timeout(state, deadline - now).unwrap().0;
+        }
+        for entry in state.connections.values() {
+            (entry.close)();
+        }
+        drop(state);
+        drop(pool);
+    }
+}
+
+fn serve_connection<P, PF, T>(processor: &P, factory: &PF, mut transport: T, id: usize, shared: &Shared)
+where P: Processor<PF::Protocol, T>, PF: ProtocolFactory, T: Connection {
+    let mut protocol = factory.new_protocol();
+    loop {
+        // Idle until the next request starts arriving.
+        match transport.wait_readable() {
+            Ok(true) => {}
+            Ok(false) | Err(_) => return,
+        }
+        if !set_busy(shared, id, true) {
+            return;
+        }
+        let processed = processor.process(&mut protocol, &mut transport);
+        if !set_busy(shared, id, false) || processed.is_err() {
+            return;
+        }
+    }
+}
+
+/// Marks connection `id` as busy or idle, and tells whether it should go
+/// on, which it shouldn't once the server was shut down.
+fn set_busy(shared: &Shared, id: usize, busy: bool) -> bool {
+    let mut state = shared.state.lock().unwrap();
+    if state.shutdown {
+        return false;
+    }
+    if let Some(entry) = state.connections.get_mut(&id) {
+        entry.busy = busy;
+    }
+    true
+}
+
+impl ServerHandle {
+    /// Stops accepting connections, closes the idle ones and lets the busy
+    /// ones drain. Returns right away, use `join` to wait for the server to
+    /// stop.
+    pub fn shutdown(&self) {
+        {
+            let mut state = self.shared.state.lock().unwrap();
+            state.shutdown = true;
+            for entry in state.connections.values().filter(|entry| !entry.busy) {
+                (entry.close)();
+            }
+        }
+        self.shared.changed.notify_all();
+        if let Err(e) = (self.wake)() {
+            warn!("failed to wake up the acceptor, it stops after the next connection: {}", e);
+        }
+    }
+
+    /// Waits for the server to stop after a `shutdown`.
+    pub fn join(self) {
+        let _ = self.acceptor.join();
+    }
+}
@@ -0,0 +1,61 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+
+//! Handling of a whole request read from a frame, shared by the servers
+//! reading framed transports themselves.
+
+use exception::{ApplicationException, ApplicationExceptionKind};
+use protocol::{MessageType, Protocol, ProtocolFactory};
+use processor::{self, Processor};
+use transport::memory::MemoryTransport;
+use transport::slice::SliceTransport;
+use {Error, Result};
+
+/// Reads the message header of `request`, with a protocol of its own since
+/// protocols may keep state between calls.
+pub fn read_header<PF: ProtocolFactory>(factory: &PF, request: &[u8]) -> Result<(String, MessageType, i32)> {
+    factory.new_protocol().read_message_begin(&mut SliceTransport::new(request))
+}
+
+/// Processes `request`, whose header is `header`, and returns the reply.
+/// Failures are answered with an `ApplicationException`.
+pub fn process<P, PF>(processor: &P, factory: &PF, request: Vec<u8>,
+                      (name, ty, id): (String, MessageType, i32)) -> Vec<u8>
+where P: Processor<PF::Protocol, MemoryTransport>, PF: ProtocolFactory {
+    let mut transport = MemoryTransport::new(request);
+    let mut protocol = factory.new_protocol();
+    let e = match processor.process(&mut protocol, &mut transport) {
+        Ok(()) => return transport.into_written(),
+        Err(e) => e,
+    };
+
+    warn!("failed to process request {}: {}", name, e);
+    let kind = match e {
+        Error::ProtocolError(_) => ApplicationExceptionKind::ProtocolError,
+        _ => ApplicationExceptionKind::InternalError,
+    };
+    let error = ApplicationException::new(kind, format!("failed to process {}: {}", name, e));
+    let mut transport = MemoryTransport::default();
+    let mut protocol = factory.new_protocol();
+    match processor::reply_exception(&mut protocol, &mut transport, &name, ty, id, &error) {
+        Ok(()) => transport.into_written(),
+        Err(_) => Vec::new(),
+    }
+}
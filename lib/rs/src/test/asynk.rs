@@ -0,0 +1,141 @@
+#![allow(dead_code)]
+
+use std::sync::{Arc, Mutex};
+
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::{Builder, Runtime};
+
+use asynk::{self, AsyncProcessor, AsyncServer, BoxFuture};
+use compiletest::Exception;
+use exception::{ApplicationException, ApplicationExceptionKind};
+use protocol::{helpers, Decode, MessageType, Protocol};
+use protocol::binary_protocol::BinaryProtocol;
+use transport::memory::MemoryTransport;
+use Error;
+
+service! {
+    trait_name = Calculator,
+    processor_name = CalculatorProcessor,
+    client_name = CalculatorClient,
+    async_trait_name = CalculatorAsync,
+    async_processor_name = CalculatorAsyncProcessor,
+    async_client_name = CalculatorAsyncClient,
+    service_methods = [
+        CalculatorAddArgs -> CalculatorAddResult = this.add(a: i32 => 1, b: i32 => 2,) -> i32 => CalculatorAddError = [] (i32),
+        CalculatorDivideArgs -> CalculatorDivideResult = this.divide(a: i32 => 1, b: i32 => 2,) -> i32 => CalculatorDivideError = [Zero(zero: Exception => 1),] (Result<i32, CalculatorDivideError>),
+        CalculatorLogArgs -> CalculatorLogResult = this.log(message: String => 1,) -> () => CalculatorLogError = [] (()) oneway,
+        CalculatorLogsArgs -> CalculatorLogsResult = this.logs() -> Vec<String> => CalculatorLogsError = [] (Vec<String>),
+        CalculatorCrashArgs -> CalculatorCrashResult = this.crash() -> i32 => CalculatorCrashError = [] (i32),
+    ],
+    parent_methods = [],
+    bounds = [S: Calculator,],
+    fields = [this: S,]
+}
+
+#[derive(Clone, Default)]
+struct Handler(Arc<Mutex<Vec<String>>>);
+
+impl CalculatorAsync for Handler {
+    fn add(&self, a: i32, b: i32) -> BoxFuture<'static, i32> {
+        asynk::ready(a + b)
+    }
+
+    fn divide(&self, a: i32, b: i32) -> BoxFuture<'static, Result<i32, CalculatorDivideError>> {
+        if b == 0 {
+            let zero = Exception { name: "zero".to_string(), message: "division by zero".to_string() };
+            asynk::ready(Err(CalculatorDivideError::Zero(zero)))
+        } else {
+            asynk::ready(Ok(a / b))
+        }
+    }
+
+    fn log(&self, message: String) -> BoxFuture<'static, ()> {
+        self.0.lock().unwrap().push(message);
+        asynk::ready(())
+    }
+
+    fn logs(&self) -> BoxFuture<'static, Vec<String>> {
+        asynk::ready(self.0.lock().unwrap().clone())
+    }
+
+    fn crash(&self) -> BoxFuture<'static, i32> {
+        panic!("crashed")
+    }
+}
+
+fn runtime() -> Runtime {
+    Builder::new_current_thread().enable_io().build().unwrap()
+}
+
+fn connect(rt: &Runtime) -> CalculatorAsyncClient<BinaryProtocol, TcpStream> {
+    let listener = rt.block_on(TcpListener::bind("127.0.0.1:0")).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = AsyncServer::new(CalculatorAsyncProcessor::new(Handler::default()), BinaryProtocol::default);
+    rt.spawn(server.serve(listener));
+
+    let stream = rt.block_on(TcpStream::connect(addr)).unwrap();
+    CalculatorAsyncClient::new(BinaryProtocol::default(), stream)
+}
+
+#[test]
+fn test_calls_over_loopback() {
+    let rt = runtime();
+    let mut client = connect(&rt);
+
+    assert_eq!(rt.block_on(client.add(1, 2)).unwrap(), 3);
+    assert_eq!(rt.block_on(client.divide(7, 2)).unwrap().unwrap(), 3);
+    match rt.block_on(client.divide(1, 0)).unwrap() {
+        Err(CalculatorDivideError::Zero(e)) => assert_eq!(e.message, "division by zero"),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_oneway_over_loopback() {
+    let rt = runtime();
+    let mut client = connect(&rt);
+
+    rt.block_on(client.log("one".to_string())).unwrap();
+    rt.block_on(client.log("two".to_string())).unwrap();
+    assert_eq!(rt.block_on(client.logs()).unwrap(), vec!["one".to_string(), "two".to_string()]);
+}
+
+#[test]
+fn test_frames_larger_than_a_read_over_loopback() {
+    let rt = runtime();
+    let mut client = connect(&rt);
+
+    let long = "x".repeat(100 * 1024);
+    rt.block_on(client.log(long.clone())).unwrap();
+    assert_eq!(rt.block_on(client.logs()).unwrap(), vec![long]);
+}
+
+#[test]
+fn test_handler_panic_over_loopback() {
+    let rt = runtime();
+    let mut client = connect(&rt);
+
+    match rt.block_on(client.crash()) {
+        Err(Error::ApplicationException(e)) => assert_eq!(e.kind, ApplicationExceptionKind::InternalError),
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(rt.block_on(client.add(2, 2)).unwrap(), 4);
+}
+
+#[test]
+fn test_unknown_method() {
+    let mut request = MemoryTransport::default();
+    helpers::send(&mut BinaryProtocol::default(), &mut request, "missing", MessageType::Call,
+                  &CalculatorLogsArgs::default(), 5).unwrap();
+
+    let processor = CalculatorAsyncProcessor::new(Handler::default());
+    let reply = runtime().block_on(processor.process(BinaryProtocol::default(), request.into_written()));
+
+    let mut protocol = BinaryProtocol::default();
+    let mut reply = MemoryTransport::new(reply.unwrap().unwrap());
+    assert_eq!(protocol.read_message_begin(&mut reply).unwrap(),
+               ("missing".to_string(), MessageType::Exception, 5));
+    let mut exception = ApplicationException::default();
+    exception.decode(&mut protocol, &mut reply).unwrap();
+    assert_eq!(exception.kind, ApplicationExceptionKind::UnknownMethod);
+}
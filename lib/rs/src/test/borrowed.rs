@@ -0,0 +1,88 @@
+use mock::MockTransport;
+
+use protocol::{self, helpers, Encode, Protocol};
+use protocol::binary_protocol::BinaryProtocol;
+use protocol::compact_protocol::CompactProtocol;
+use protocol::json_protocol::JsonProtocol;
+use Error;
+
+use test::generated::*;
+
+strukt! {
+    name = Borrowed<'a>,
+    fields = {
+        name: &'a str => 1,
+        data: &'a [u8] => 2,
+        tags: Vec<&'a str> => 3,
+        count: i32 => 4,
+        simple: Simple => 5,
+    }
+}
+
+fn sample<'a>() -> Borrowed<'a> {
+    Borrowed {
+        name: "borrowed",
+        data: b"\x00\x01\x02",
+        tags: vec!["a", "bc"],
+        count: 7,
+        simple: Simple { key: "owned".to_string() },
+    }
+}
+
+fn encode_with<P: Protocol, E: Encode>(mut protocol: P, value: &E) -> Vec<u8> {
+    let mut buf = MockTransport::new(vec![]);
+    value.encode(&mut protocol, &mut buf).unwrap();
+    buf.written().to_vec()
+}
+
+fn points_into(inner: &[u8], outer: &[u8]) -> bool {
+    let start = outer.as_ptr() as usize;
+    let ptr = inner.as_ptr() as usize;
+    ptr >= start && ptr + inner.len() <= start + outer.len()
+}
+
+fn check_roundtrip<P: Protocol + Clone>(protocol: P) {
+    let bytes = encode_with(protocol.clone(), &sample());
+    let decoded: Borrowed = helpers::decode_borrowed(&mut protocol.clone(), &bytes).unwrap();
+
+    assert_eq!(decoded, sample());
+    assert!(points_into(decoded.name.as_bytes(), &bytes));
+    assert!(points_into(decoded.data, &bytes));
+    assert!(decoded.tags.iter().all(|t| points_into(t.as_bytes(), &bytes)));
+}
+
+#[test]
+fn test_binary_roundtrip_borrows() {
+    check_roundtrip(BinaryProtocol::default());
+}
+
+#[test]
+fn test_compact_roundtrip_borrows() {
+    check_roundtrip(CompactProtocol::new());
+}
+
+#[test]
+fn test_owned_struct_decodes_from_slice() {
+    let simple = Simple { key: "hello".to_string() };
+    let bytes = encode_with(BinaryProtocol::default(), &simple);
+    let decoded: Simple = helpers::decode_borrowed(&mut BinaryProtocol::default(), &bytes).unwrap();
+    assert_eq!(decoded, simple);
+}
+
+#[test]
+fn test_invalid_utf8_rejected() {
+    let bytes = encode_with(BinaryProtocol::default(), &(&b"\xff\xfe"[..]));
+    match helpers::decode_borrowed::<&str, _>(&mut BinaryProtocol::default(), &bytes) {
+        Err(Error::ProtocolError(protocol::Error::InvalidUtf8(_))) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_json_cannot_borrow() {
+    let bytes = encode_with(JsonProtocol::new(), &sample());
+    match helpers::decode_borrowed::<Borrowed, _>(&mut JsonProtocol::new(), &bytes) {
+        Err(Error::ProtocolError(protocol::Error::NotImplemented)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
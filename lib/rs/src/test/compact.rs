@@ -0,0 +1,87 @@
+use std::collections::{BTreeSet, BTreeMap};
+use std::io::Cursor;
+
+use test::generated::*;
+
+use protocol::{Encode, Decode, MessageType};
+use protocol::compact_protocol::CompactProtocol;
+use protocol::helpers;
+use transport::RwTransport;
+
+fn roundtrip<T: Encode + Decode>(instance: &T) -> T {
+    let mut transport = RwTransport(Cursor::new(Vec::new()));
+    instance.encode(&mut CompactProtocol::new(), &mut transport).unwrap();
+    transport.0.set_position(0);
+
+    let mut decoded = T::default();
+    decoded.decode(&mut CompactProtocol::new(), &mut transport).unwrap();
+    decoded
+}
+
+fn decode<T: Decode>(bytes: Vec<u8>) -> T {
+    let mut instance = T::default();
+    instance.decode(&mut CompactProtocol::new(), &mut RwTransport(Cursor::new(bytes))).unwrap();
+    instance
+}
+
+#[test]
+fn test_compact_struct_roundtrip() {
+    let mut five = BTreeSet::new();
+    five.insert(Operation::Add);
+    five.insert(Operation::Clear);
+
+    let instance = Many {
+        one: -17,
+        two: String::from("Some String"),
+        three: vec![Simple { key: String::from("A String") }],
+        five: five,
+        six: Some(Simple { key: String::from("Another") }),
+    };
+
+    assert_eq!(roundtrip(&instance), instance);
+}
+
+#[test]
+fn test_compact_bool_fields_roundtrip() {
+    let mut names = BTreeMap::new();
+    names.insert(String::from("yes"), true);
+    names.insert(String::from("no"), false);
+
+    let instance = Flags {
+        first: true,
+        second: false,
+        far: -300,
+        names: names,
+        empty: BTreeMap::new(),
+        bits: vec![true, false, true],
+    };
+
+    assert_eq!(roundtrip(&instance), instance);
+}
+
+#[test]
+fn test_compact_send_receive() {
+    let args = Nested { nested: vec![vec![vec![Simple { key: String::from("key") }]]] };
+
+    let mut transport = RwTransport(Cursor::new(Vec::new()));
+    helpers::send(&mut CompactProtocol::new(), &mut transport,
+                  "op", MessageType::Reply, &args, 7).unwrap();
+    transport.0.set_position(0);
+
+    let mut result = Nested::default();
+    helpers::receive(&mut CompactProtocol::new(), &mut transport, "op", &mut result).unwrap();
+    assert_eq!(result, args);
+}
+
+#[test]
+fn test_compact_empty_containers() {
+    // An empty map carries no key and value types.
+    let map: BTreeMap<i32, String> = decode(vec![0x00]);
+    assert!(map.is_empty());
+
+    // An empty list still carries its element type, which must match.
+    let list: Vec<i32> = decode(vec![0x05]);
+    assert!(list.is_empty());
+    let mut list: Vec<i32> = Vec::new();
+    assert!(list.decode(&mut CompactProtocol::new(), &mut RwTransport(Cursor::new(vec![0x08]))).is_err());
+}
@@ -0,0 +1,194 @@
+#![allow(dead_code)]
+
+use std::io::{self, Read};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use client::Channel;
+use exception::{ApplicationException, ApplicationExceptionKind};
+use protocol::{helpers, Decode, MessageType, Protocol};
+use protocol::binary_protocol::BinaryProtocol;
+use protocol::compact_protocol::CompactProtocol;
+use protocol::json_protocol::JsonProtocol;
+use Processor;
+use transport::server::TcpWriter;
+use Error;
+
+service! {
+    trait_name = Echo,
+    processor_name = EchoProcessor,
+    client_name = EchoClient,
+    concurrent_client_name = EchoConcurrentClient,
+    service_methods = [
+        EchoEchoArgs -> EchoEchoResult = this.echo(value: i32 => 1,) -> i32 => EchoEchoError = [] (i32),
+        EchoForgetArgs -> EchoForgetResult = this.forget(value: i32 => 1,) -> () => EchoForgetError = [] (()) oneway,
+    ],
+    parent_methods = [],
+    bounds = [S: Echo,],
+    fields = [this: S,]
+}
+
+service! {
+    trait_name = Lists,
+    processor_name = ListsProcessor,
+    client_name = ListsClient,
+    concurrent_client_name = ListsConcurrentClient,
+    service_methods = [
+        ListsRangeArgs -> ListsRangeResult = this.range(len: i32 => 1,) -> Vec<i32> => ListsRangeError = [] (Vec<i32>),
+    ],
+    parent_methods = [],
+    bounds = [S: Lists,],
+    fields = [this: S,]
+}
+
+struct Range;
+
+impl Lists for Range {
+    fn range(&self, len: i32) -> Vec<i32> {
+        (0..len).collect()
+    }
+}
+
+/// Connects a concurrent client to a server running `serve` on the
+/// accepted connection.
+fn connect<F>(serve: F) -> (EchoConcurrentClient<BinaryProtocol, TcpWriter>, thread::JoinHandle<()>)
+where F: FnOnce(TcpStream) + Send + 'static {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || serve(listener.accept().unwrap().0));
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let channel = Channel::split(BinaryProtocol::default(), stream).unwrap();
+    (EchoConcurrentClient::new(channel), server)
+}
+
+fn read_call<A: Decode>(stream: &mut TcpStream) -> (String, MessageType, i32, A) {
+    let mut protocol = BinaryProtocol::default();
+    let (name, ty, id) = protocol.read_message_begin(stream).unwrap();
+    let mut args = A::default();
+    args.decode(&mut protocol, stream).unwrap();
+    protocol.read_message_end(stream).unwrap();
+    (name, ty, id, args)
+}
+
+fn reply_echo(stream: &mut TcpStream, id: i32, value: i32) {
+    let mut result = EchoEchoResult::default();
+    result.success = Some(value);
+    helpers::send(&mut BinaryProtocol::default(), stream, "echo", MessageType::Reply, &result, id).unwrap();
+}
+
+#[test]
+fn test_replies_out_of_order() {
+    let (client, server) = connect(|mut stream| {
+        // Wait until both calls are in flight, then answer the last one first.
+        let (_, _, first, a) = read_call::<EchoEchoArgs>(&mut stream);
+        let (_, _, second, b) = read_call::<EchoEchoArgs>(&mut stream);
+        assert!(first != second);
+        reply_echo(&mut stream, second, b.value.unwrap());
+        reply_echo(&mut stream, first, a.value.unwrap());
+    });
+
+    let client = Arc::new(client);
+    let callers: Vec<_> = (0..2).map(|i| {
+        let client = client.clone();
+        thread::spawn(move || assert_eq!(client.echo(i).unwrap(), i))
+    }).collect();
+    for caller in callers {
+        caller.join().unwrap();
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn test_oneway_and_exception() {
+    let (client, server) = connect(|mut stream| {
+        let (name, ty, _, args) = read_call::<EchoForgetArgs>(&mut stream);
+        assert_eq!((&name[..], ty, args.value), ("forget", MessageType::Oneway, Some(3)));
+
+        let (_, _, id, _) = read_call::<EchoEchoArgs>(&mut stream);
+        let error = ApplicationException::new(ApplicationExceptionKind::InternalError, "oops");
+        helpers::send(&mut BinaryProtocol::default(), &mut stream, "echo", MessageType::Exception, &error, id).unwrap();
+    });
+
+    client.forget(3).unwrap();
+    match client.echo(4) {
+        Err(Error::ApplicationException(e)) => assert_eq!(e.message, "oops"),
+        other => panic!("unexpected {:?}", other),
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn test_closed_connection_fails_calls() {
+    let (client, server) = connect(|mut stream| {
+        read_call::<EchoEchoArgs>(&mut stream);
+    });
+
+    match client.echo(1) {
+        Err(Error::TransportError(ref e)) if e.kind() == io::ErrorKind::ConnectionAborted => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    server.join().unwrap();
+    assert!(client.echo(2).is_err());
+}
+
+#[test]
+fn test_close_fails_pending_calls() {
+    let (received, wait_received) = mpsc::channel();
+    let (client, server) = connect(move |mut stream| {
+        read_call::<EchoEchoArgs>(&mut stream);
+        received.send(()).unwrap();
+        // Closing the channel shuts the connection down.
+        assert_eq!(stream.read(&mut [0]).unwrap(), 0);
+    });
+
+    let client = Arc::new(client);
+    let caller = {
+        let client = client.clone();
+        thread::spawn(move || client.echo(1))
+    };
+    wait_received.recv().unwrap();
+    client.channel.close();
+
+    match caller.join().unwrap() {
+        Err(Error::TransportError(ref e)) if e.kind() == io::ErrorKind::ConnectionAborted => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(client.echo(2).is_err());
+    assert!(client.forget(3).is_err());
+    server.join().unwrap();
+}
+
+/// Calls `range` over `protocol`, which the server uses as well.
+fn check_range<P, F>(protocol: F)
+where P: Protocol + Clone + Send + 'static, F: Fn() -> P + Send + 'static {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server_protocol = protocol();
+    let server = thread::spawn(move || {
+        let mut stream = listener.accept().unwrap().0;
+        let processor = ListsProcessor::new(Range);
+        for _ in 0..2 {
+            processor.process(&mut server_protocol.clone(), &mut stream).unwrap();
+        }
+    });
+
+    let channel = Channel::split(protocol(), TcpStream::connect(addr).unwrap()).unwrap();
+    let client = ListsConcurrentClient::new(channel);
+    assert_eq!(client.range(0).unwrap(), vec![]);
+    assert_eq!(client.range(3).unwrap(), vec![0, 1, 2]);
+    server.join().unwrap();
+}
+
+#[test]
+fn test_compact_replies() {
+    // Empty compact lists still carry their element type.
+    check_range(CompactProtocol::new);
+}
+
+#[test]
+fn test_json_replies() {
+    // The body is decoded in the JSON context left by the message header.
+    check_range(JsonProtocol::new);
+}
@@ -0,0 +1,39 @@
+use std::collections::BTreeSet;
+
+use test::generated::*;
+
+use debug;
+
+#[test]
+fn test_pretty_struct() {
+    let mut five = BTreeSet::new();
+    five.insert(Operation::Clear);
+
+    let instance = Many {
+        one: 17,
+        two: String::from("Some String"),
+        three: vec![Simple { key: String::from("A String") }],
+        five: five,
+        six: None,
+    };
+
+    assert_eq!(debug::pretty(&instance), "\
+Many {
+  3: one = 17,
+  4: two = \"Some String\",
+  9: three = [
+    Simple {
+      16: key = \"A String\",
+    },
+  ],
+  11: five = {
+    3,
+  },
+}");
+}
+
+#[test]
+fn test_pretty_primitive() {
+    assert_eq!(debug::pretty(&-5i64), "-5");
+    assert_eq!(debug::pretty(&String::from("x")), "\"x\"");
+}
@@ -0,0 +1,101 @@
+use std::collections::BTreeMap;
+
+use compiletest::{self, ChildServiceClient, DeeplyNested, Exception, ServiceWithExceptionClient};
+use descriptor::{Described, Requiredness, ThriftEnum, ThriftService, ThriftStruct, TypeDescriptor};
+use mock::*;
+use protocol::{ThriftTyped, Type};
+
+use test::generated::*;
+
+#[test]
+fn test_struct_descriptor() {
+    let desc = Many::descriptor();
+    assert_eq!(desc.name, "Many");
+    assert_eq!(desc.fields.iter().map(|f| f.id).collect::<Vec<_>>(), vec![3, 4, 9, 11, 14]);
+
+    let three = desc.field(9).unwrap();
+    assert_eq!(three.name, "three");
+    assert_eq!(three.typ, TypeDescriptor::List(&TypeDescriptor::Struct(Simple::descriptor)));
+    assert_eq!(three.requiredness, Requiredness::Default);
+    assert!(!three.required);
+
+    let six = desc.field_by_name("six").unwrap();
+    assert_eq!(six.id, 14);
+    assert_eq!(six.typ.typ(), Type::Struct);
+    assert_eq!(six.requiredness, Requiredness::Optional);
+
+    match desc.field(11).unwrap().typ {
+        TypeDescriptor::Set(&TypeDescriptor::Enum(f)) => assert_eq!(f().name, "Operation"),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    assert!(desc.field(5).is_none());
+}
+
+#[test]
+fn test_descriptor_types_match_wire_types() {
+    let flags = Flags::default();
+    for field in Flags::descriptor().fields {
+        let typ = match field.id {
+            1 => flags.first.typ(),
+            100 => flags.far.typ(),
+            101 => flags.names.typ(),
+            _ => continue,
+        };
+        assert_eq!(field.typ.typ(), typ);
+    }
+    assert_eq!(<BTreeMap<String, bool> as Described>::DESCRIPTOR,
+               TypeDescriptor::Map(&TypeDescriptor::String, &TypeDescriptor::Bool));
+    assert!(<Vec<u8> as Described>::DESCRIPTOR != <String as Described>::DESCRIPTOR);
+}
+
+#[test]
+fn test_recursive_and_empty_descriptors() {
+    match Recursive::descriptor().field(0).unwrap().typ {
+        TypeDescriptor::List(&TypeDescriptor::Struct(f)) => assert_eq!(f().name, "Recursive"),
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(Empty::descriptor().name, "Empty");
+    assert!(Empty::descriptor().fields.is_empty());
+}
+
+#[test]
+fn test_enum_descriptor() {
+    let desc = Operation::descriptor();
+    assert_eq!(desc.name, "Operation");
+    assert_eq!(desc.values, &[("Add", 1), ("Sub", 2), ("Clear", 3)]);
+    assert_eq!(desc.name_of(3), Some("Clear"));
+    assert_eq!(desc.value_of("Sub"), Some(2));
+    assert_eq!(desc.name_of(4), None);
+}
+
+#[test]
+fn test_service_descriptor() {
+    let desc = ChildServiceClient::<MockProtocol, MockTransport>::descriptor();
+    assert_eq!(desc.name, "ChildService");
+    assert_eq!(desc.methods.iter().map(|m| m.name).collect::<Vec<_>>(), vec!["operation", "get_struct"]);
+
+    let operation = desc.method("operation").unwrap();
+    assert_eq!(operation.args.iter().map(|a| (a.name, a.id)).collect::<Vec<_>>(),
+               vec![("one", 2), ("another", 3)]);
+    assert_eq!(operation.args[0].typ, TypeDescriptor::String);
+    assert_eq!(operation.result, TypeDescriptor::Enum(compiletest::Operation::descriptor));
+    assert!(operation.exceptions.is_empty());
+    assert!(!operation.oneway);
+
+    let get_struct = desc.method("get_struct").unwrap();
+    assert_eq!(get_struct.result, TypeDescriptor::Struct(DeeplyNested::descriptor));
+    assert!(desc.method("missing").is_none());
+}
+
+#[test]
+fn test_service_descriptor_exceptions() {
+    let desc = ServiceWithExceptionClient::<MockProtocol, MockTransport>::descriptor();
+    let operation = desc.method("operation").unwrap();
+    assert!(operation.args.is_empty());
+    assert_eq!(operation.result, TypeDescriptor::I32);
+    assert_eq!(operation.exceptions.len(), 1);
+    assert_eq!((operation.exceptions[0].name, operation.exceptions[0].id), ("bad", 1));
+    assert_eq!(operation.exceptions[0].typ, TypeDescriptor::Struct(Exception::descriptor));
+    assert_eq!(operation.exceptions[0].requiredness, Requiredness::Optional);
+}
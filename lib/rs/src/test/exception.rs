@@ -0,0 +1,155 @@
+use std::io::Cursor;
+
+use compiletest::*;
+use exception::{ApplicationException, ApplicationExceptionKind};
+use mock::MockTransport;
+use protocol::{self, helpers, Decode, Encode, MessageType, Protocol};
+use protocol::binary_protocol::BinaryProtocol;
+use transport::RwTransport;
+use {Error, Processor};
+
+struct Counter;
+
+impl NotifyService for Counter {
+    fn notify(&self, _message: String) {
+        panic!("cannot notify");
+    }
+
+    fn count(&self) -> i32 {
+        panic!("cannot count");
+    }
+}
+
+fn message<E: Encode>(name: &str, ty: MessageType, body: &E, id: i32) -> Vec<u8> {
+    let mut transport = RwTransport(Cursor::new(Vec::new()));
+    helpers::send(&mut BinaryProtocol::default(), &mut transport, name, ty, body, id).unwrap();
+    transport.0.into_inner()
+}
+
+fn process(request: Vec<u8>) -> Vec<u8> {
+    let mut transport = MockTransport::new(request);
+    NotifyServiceProcessor::new(Counter).process(&mut BinaryProtocol::default(), &mut transport).unwrap();
+    transport.written().to_vec()
+}
+
+fn read_exception(reply: Vec<u8>, name: &str, id: i32) -> ApplicationException {
+    let mut protocol = BinaryProtocol::default();
+    let mut reply = MockTransport::new(reply);
+    assert_eq!(protocol.read_message_begin(&mut reply).unwrap(),
+               (name.to_string(), MessageType::Exception, id));
+    let mut exception = ApplicationException::default();
+    exception.decode(&mut protocol, &mut reply).unwrap();
+    exception
+}
+
+#[test]
+fn test_unknown_method_answers_exception() {
+    let args = NotifyServiceCountArgs::default();
+    let reply = process(message("missing", MessageType::Call, &args, 7));
+
+    let exception = read_exception(reply, "missing", 7);
+    assert_eq!(exception.kind, ApplicationExceptionKind::UnknownMethod);
+    assert_eq!(exception.message, "unknown method missing");
+}
+
+#[test]
+fn test_unknown_oneway_method_is_skipped() {
+    let args = NotifyServiceNotifyArgs::default();
+    assert!(process(message("missing", MessageType::Oneway, &args, 0)).is_empty());
+}
+
+#[test]
+fn test_processor_rejects_replies() {
+    let result = NotifyServiceCountResult::default();
+    let mut transport = MockTransport::new(message("count", MessageType::Reply, &result, 3));
+    match NotifyServiceProcessor::new(Counter).process(&mut BinaryProtocol::default(), &mut transport) {
+        Err(Error::ProtocolError(protocol::Error::ProtocolViolation)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(transport.written().is_empty());
+}
+
+#[test]
+fn test_handler_panic_answers_internal_error() {
+    let args = NotifyServiceCountArgs::default();
+    let reply = process(message("count", MessageType::Call, &args, 2));
+
+    let exception = read_exception(reply, "count", 2);
+    assert_eq!(exception.kind, ApplicationExceptionKind::InternalError);
+    assert_eq!(exception.message, "internal error processing count: cannot count");
+}
+
+#[test]
+fn test_oneway_handler_panic_is_not_answered() {
+    let mut args = NotifyServiceNotifyArgs::default();
+    args.message = Some("hello".to_string());
+    assert!(process(message("notify", MessageType::Oneway, &args, 0)).is_empty());
+}
+
+#[test]
+fn test_client_receives_application_exception() {
+    let exception = ApplicationException::new(ApplicationExceptionKind::UnknownMethod, "unknown method count");
+    let reply = message("count", MessageType::Exception, &exception, 1);
+    let mut client = NotifyServiceClient::new(BinaryProtocol::default(), MockTransport::new(reply));
+
+    match client.count() {
+        Err(Error::ApplicationException(e)) => assert_eq!(e, exception),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+// An `ApplicationException` as sent by a peer which knows more kinds.
+strukt! {
+    name = NewerException,
+    fields = {
+        message: String => 1,
+        kind: i32 => 2,
+    }
+}
+
+#[test]
+fn test_client_receives_exception_of_unknown_kind() {
+    let exception = NewerException { message: "from the future".to_string(), kind: 42 };
+    let reply = message("count", MessageType::Exception, &exception, 1);
+    let mut client = NotifyServiceClient::new(BinaryProtocol::default(), MockTransport::new(reply));
+
+    match client.count() {
+        Err(Error::ApplicationException(e)) => {
+            assert_eq!(e.kind, ApplicationExceptionKind::Unknown);
+            assert_eq!(e.message, "from the future");
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_client_missing_result() {
+    let result = NotifyServiceCountResult::default();
+    let reply = message("count", MessageType::Reply, &result, 1);
+    let mut client = NotifyServiceClient::new(BinaryProtocol::default(), MockTransport::new(reply));
+
+    match client.count() {
+        Err(Error::ApplicationException(e)) => {
+            assert_eq!(e.kind, ApplicationExceptionKind::MissingResult);
+            assert_eq!(e.message, "count failed: unknown result");
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_client_void_method_with_exceptions() {
+    let result = ServiceWithExceptionCheckResult::default();
+    let reply = message("check", MessageType::Reply, &result, 1);
+    let mut client = ServiceWithExceptionClient::new(BinaryProtocol::default(), MockTransport::new(reply));
+    assert!(client.check().unwrap().is_ok());
+
+    let mut result = ServiceWithExceptionCheckResult::default();
+    result.bad = Some(Exception { name: "bad".to_string(), message: "worse".to_string() });
+    let reply = message("check", MessageType::Reply, &result, 1);
+    let mut client = ServiceWithExceptionClient::new(BinaryProtocol::default(), MockTransport::new(reply));
+    match client.check().unwrap() {
+        Err(ServiceWithExceptionCheckError::Bad(e)) => assert_eq!(e.message, "worse"),
+        other => panic!("unexpected {:?}", other),
+    }
+}
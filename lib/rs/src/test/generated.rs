@@ -1,4 +1,4 @@
-use std::collections::{BTreeSet};
+use std::collections::{BTreeSet, BTreeMap};
 
 strukt! {
     name = Simple,
@@ -55,3 +55,31 @@ enom! {
     default = Sub
 }
 
+
+strukt! {
+    name = Flags,
+    fields = {
+        first: bool => 1,
+        second: bool => 2,
+        far: i16 => 100,
+        names: BTreeMap<String, bool> => 101,
+        empty: BTreeMap<String, i64> => 102,
+        bits: Vec<bool> => 3,
+    }
+}
+
+strukt! {
+    name = WithDefaults,
+    fields = {
+        num: i32 => 1,
+        op: Operation => 2,
+        comment: Option<String> => 4,
+        plain: i64 => 5,
+    },
+    defaults = {
+        num: 7,
+        op: Operation::Clear,
+        comment: Some(String::from("x")),
+    },
+    required = [num,]
+}
@@ -0,0 +1,53 @@
+use std::io::Cursor;
+
+use compiletest::*;
+use mock::MockTransport;
+use processor::Processor;
+use protocol::{helpers, MessageType};
+use protocol::header_protocol::HeaderProtocol;
+use transport::Transport;
+use transport::header::{self, HeaderTransport, ProtocolId};
+
+struct Shared;
+
+impl SharedService for Shared {
+    fn get_struct(&self, key: i32) -> DeeplyNested {
+        let request_id = header::request_header("request-id").unwrap_or_default();
+        assert!(header::set_reply_header("echo", request_id));
+
+        let mut nested = DeeplyNested::default();
+        nested.nested.insert(vec![vec![vec![vec![key]]]]);
+        nested
+    }
+}
+
+#[test]
+fn test_header_round_trip() {
+    let mut args = SharedServiceGetStructArgs::default();
+    args.key = Some(3);
+
+    let mut client = HeaderTransport::with_protocol(Cursor::new(Vec::new()), ProtocolId::Compact);
+    client.header().unwrap().write_headers.insert(String::from("request-id"), String::from("abc"));
+    helpers::send(&mut HeaderProtocol::new(), &mut client, "get_struct", MessageType::Call, &args, 11).unwrap();
+
+    let request = client.into_inner().into_inner();
+    let mut server = HeaderTransport::new(MockTransport::new(request));
+    SharedServiceProcessor::new(Shared).process(&mut HeaderProtocol::new(), &mut server).unwrap();
+
+    let reply = server.get_ref().written().to_vec();
+    let mut client = HeaderTransport::new(Cursor::new(reply));
+    let mut result = SharedServiceGetStructResult::default();
+    helpers::receive(&mut HeaderProtocol::new(), &mut client, "get_struct", &mut result).unwrap();
+
+    assert_eq!(result.success.unwrap().nested.into_iter().next(), Some(vec![vec![vec![vec![3]]]]));
+    let header = client.header().unwrap();
+    assert_eq!(header.protocol_id, ProtocolId::Compact);
+    assert_eq!(header.sequence_id, 11);
+    assert_eq!(header.read_headers.get("echo").map(|v| &v[..]), Some("abc"));
+}
+
+#[test]
+fn test_reply_headers_outside_request() {
+    assert!(!header::set_reply_header("k", "v"));
+    assert_eq!(header::request_header("k"), None);
+}
@@ -0,0 +1,68 @@
+use std::collections::{BTreeSet, BTreeMap};
+use std::io::Cursor;
+
+use test::generated::*;
+
+use protocol::{Encode, Decode, MessageType};
+use protocol::json_protocol::JsonProtocol;
+use protocol::helpers;
+use transport::RwTransport;
+
+fn roundtrip<T: Encode + Decode>(instance: &T) -> T {
+    let mut transport = RwTransport(Cursor::new(Vec::new()));
+    instance.encode(&mut JsonProtocol::new(), &mut transport).unwrap();
+    transport.0.set_position(0);
+
+    let mut decoded = T::default();
+    decoded.decode(&mut JsonProtocol::new(), &mut transport).unwrap();
+    decoded
+}
+
+#[test]
+fn test_json_struct_roundtrip() {
+    let mut five = BTreeSet::new();
+    five.insert(Operation::Add);
+
+    let instance = Many {
+        one: -17,
+        two: String::from("Some \"quoted\" String"),
+        three: vec![Simple { key: String::from("A String") }],
+        five: five,
+        six: Some(Simple { key: String::from("Another") }),
+    };
+
+    assert_eq!(roundtrip(&instance), instance);
+}
+
+#[test]
+fn test_json_map_roundtrip() {
+    let mut names = BTreeMap::new();
+    names.insert(String::from("yes"), true);
+    names.insert(String::from("no"), false);
+
+    let instance = Flags {
+        first: true,
+        second: false,
+        far: -300,
+        names: names,
+        empty: BTreeMap::new(),
+        bits: vec![true, false],
+    };
+
+    assert_eq!(roundtrip(&instance), instance);
+}
+
+#[test]
+fn test_json_send_receive() {
+    let args = Simple { key: String::from("key") };
+
+    let mut transport = RwTransport(Cursor::new(Vec::new()));
+    helpers::send(&mut JsonProtocol::new(), &mut transport,
+                  "op", MessageType::Reply, &args, 7).unwrap();
+    assert_eq!(&transport.0.get_ref()[..], &br#"[1,"op",2,7,{"16":{"str":"key"}}]"#[..]);
+    transport.0.set_position(0);
+
+    let mut result = Simple::default();
+    helpers::receive(&mut JsonProtocol::new(), &mut transport, "op", &mut result).unwrap();
+    assert_eq!(result, args);
+}
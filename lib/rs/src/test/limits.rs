@@ -0,0 +1,88 @@
+use std::io::Cursor;
+
+use mock::*;
+use protocol::{self, Decode, Encode, Limit, Limits, MessageType, Protocol, Type};
+use protocol::binary_protocol::BinaryProtocol;
+use protocol::compact_protocol::CompactProtocol;
+use protocol::json_protocol::JsonProtocol;
+use transport::RwTransport;
+use Error;
+
+use test::generated::*;
+
+#[test]
+fn test_decode_checks_container_len() {
+    // The mock protocol replays what was written without checking sizes itself.
+    let mut protocol = MockProtocol::new();
+    let mut transport = MockTransport::new(vec![]);
+    protocol.write_list_begin(&mut transport, Type::I32, 0x7fff_ffff).unwrap();
+
+    let mut decoded = Vec::<i32>::new();
+    match decoded.decode(&mut protocol, &mut transport) {
+        Err(Error::ProtocolError(protocol::Error::LimitExceeded(Limit::ContainerSize))) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_decode_respects_protocol_limits() {
+    // A `Many` whose `three` field claims to hold 0x7fffffff elements.
+    let bytes = vec![0x0f, 0x00, 0x09, 0x0c, 0x7f, 0xff, 0xff, 0xff];
+    let mut limits = Limits::default();
+    limits.max_container_len = 100;
+    let mut protocol = BinaryProtocol::default().with_limits(limits);
+    let mut many = Many::default();
+    match many.decode(&mut protocol, &mut MockTransport::new(bytes)) {
+        Err(Error::ProtocolError(protocol::Error::LimitExceeded(Limit::ContainerSize))) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_decode_recursive_too_deep() {
+    // `Recursive` nests itself through a list in field 0.
+    let mut bytes = Vec::new();
+    for _ in 0..100 {
+        bytes.extend_from_slice(&[0x0f, 0x00, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x01]);
+    }
+    let mut recursive = Recursive::default();
+    match recursive.decode(&mut BinaryProtocol::default(), &mut MockTransport::new(bytes)) {
+        Err(Error::ProtocolError(protocol::Error::LimitExceeded(Limit::Depth))) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+/// Encodes `value` as a message, then checks that after failing to decode a
+/// truncated copy of it, the same protocol still decodes the whole message.
+fn check_reuse_after_failure<P: Protocol>(mut protocol: P, value: &Many) {
+    let mut transport = RwTransport(Cursor::new(Vec::new()));
+    protocol.write_message_begin(&mut transport, "m", MessageType::Call, 1).unwrap();
+    value.encode(&mut protocol, &mut transport).unwrap();
+    protocol.write_message_end(&mut transport).unwrap();
+    let bytes = transport.0.into_inner();
+
+    // Cut the message off inside the innermost struct.
+    let mut truncated = MockTransport::new(bytes[..bytes.len() - 6].to_vec());
+    protocol.read_message_begin(&mut truncated).unwrap();
+    assert!(Many::default().decode(&mut protocol, &mut truncated).is_err());
+
+    let mut whole = MockTransport::new(bytes);
+    assert_eq!(protocol.read_message_begin(&mut whole).unwrap(), ("m".to_string(), MessageType::Call, 1));
+    let mut decoded = Many::default();
+    decoded.decode(&mut protocol, &mut whole).unwrap();
+    protocol.read_message_end(&mut whole).unwrap();
+    assert_eq!(&decoded, value);
+}
+
+#[test]
+fn test_reuse_protocol_after_failed_message() {
+    let mut many = Many::default();
+    many.three = vec![Simple { key: String::from("a key") }];
+    // `Many`, the list and `Simple` take up all of the allowed depth.
+    let mut limits = Limits::default();
+    limits.max_depth = 3;
+
+    check_reuse_after_failure(BinaryProtocol::default().with_limits(limits), &many);
+    check_reuse_after_failure(CompactProtocol::new().with_limits(limits), &many);
+    check_reuse_after_failure(JsonProtocol::new().with_limits(limits), &many);
+}
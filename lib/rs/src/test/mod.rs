@@ -5,6 +5,29 @@ mod prim;
 mod strukt;
 mod enom;
 mod generated;
+mod compact;
+mod json;
+mod simple_json;
+mod debug;
+mod multiplexed;
+mod header;
+mod limits;
+mod borrowed;
+mod value;
+mod descriptor;
+mod unknown;
+mod required;
+mod unyon;
+mod oneway;
+mod exception;
+mod seqid;
+mod concurrent;
+mod pipelined;
+mod pooled;
+#[cfg(feature = "async")]
+mod asynk;
+#[cfg(feature = "nonblocking")]
+mod nonblocking;
 
 pub fn encode<T: Encode>(x: &T) -> MockProtocol {
     let mut protocol = MockProtocol::new();
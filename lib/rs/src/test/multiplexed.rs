@@ -0,0 +1,107 @@
+use std::io::Cursor;
+
+use compiletest::*;
+use exception::ApplicationExceptionKind;
+use mock::MockTransport;
+use processor::{MultiplexedProcessor, Processor};
+use protocol::{self, helpers, MessageType};
+use protocol::binary_protocol::BinaryProtocol;
+use protocol::multiplexed_protocol::MultiplexedProtocol;
+use transport::RwTransport;
+use Error;
+
+struct Shared;
+
+impl SharedService for Shared {
+    fn get_struct(&self, key: i32) -> DeeplyNested {
+        let mut nested = DeeplyNested::default();
+        nested.nested.insert(vec![vec![vec![vec![key]]]]);
+        nested
+    }
+}
+
+struct Child;
+
+impl ChildService for Child {
+    fn operation(&self, _one: String, another: i32) -> Operation {
+        if another > 0 { Operation::Mul } else { Operation::Div }
+    }
+}
+
+fn processor() -> MultiplexedProcessor<BinaryProtocol, MockTransport> {
+    let mut processor = MultiplexedProcessor::new();
+    processor.register("SharedService", SharedServiceProcessor::new(Shared));
+    processor.register("ChildService", ChildServiceProcessor::new(Shared, Child));
+    processor
+}
+
+fn call<W: protocol::Encode>(service: &str, method: &str, args: &W) -> MockTransport {
+    let mut protocol = MultiplexedProtocol::new(service, BinaryProtocol::default());
+    let mut request = RwTransport(Cursor::new(Vec::new()));
+    helpers::send(&mut protocol, &mut request, method, MessageType::Call, args, 1).unwrap();
+    MockTransport::new((request.0).into_inner())
+}
+
+#[test]
+fn test_multiplexed_dispatch() {
+    let processor = processor();
+
+    let mut args = ChildServiceOperationArgs::default();
+    args.one = Some(String::from("one"));
+    args.another = Some(2);
+    let mut transport = call("ChildService", "operation", &args);
+    processor.process(&mut BinaryProtocol::default(), &mut transport).unwrap();
+
+    let mut result = ChildServiceOperationResult::default();
+    helpers::receive(&mut BinaryProtocol::default(), &mut MockTransport::new(transport.written().to_vec()),
+                     "operation", &mut result).unwrap();
+    assert_eq!(result.success, Some(Operation::Mul));
+
+    let mut args = SharedServiceGetStructArgs::default();
+    args.key = Some(5);
+    let mut transport = call("SharedService", "get_struct", &args);
+    processor.process(&mut BinaryProtocol::default(), &mut transport).unwrap();
+
+    let mut result = SharedServiceGetStructResult::default();
+    helpers::receive(&mut BinaryProtocol::default(), &mut MockTransport::new(transport.written().to_vec()),
+                     "get_struct", &mut result).unwrap();
+    assert_eq!(result.success, Some(Shared.get_struct(5)));
+}
+
+#[test]
+fn test_multiplexed_unknown_service() {
+    let mut args = SharedServiceGetStructArgs::default();
+    args.key = Some(5);
+    let mut transport = call("Unknown", "get_struct", &args);
+    processor().process(&mut BinaryProtocol::default(), &mut transport).unwrap();
+
+    let mut result = SharedServiceGetStructResult::default();
+    match helpers::receive(&mut BinaryProtocol::default(), &mut MockTransport::new(transport.written().to_vec()),
+                           "Unknown:get_struct", &mut result) {
+        Err(Error::ApplicationException(ref e)) if e.kind == ApplicationExceptionKind::UnknownMethod => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_multiplexed_default_service() {
+    let mut args = SharedServiceGetStructArgs::default();
+    args.key = Some(5);
+    let mut transport = MockTransport::new(vec![]);
+    helpers::send(&mut BinaryProtocol::default(), &mut transport, "get_struct", MessageType::Call, &args, 1).unwrap();
+    let mut transport = MockTransport::new(transport.written().to_vec());
+
+    let mut processor = processor();
+    let mut unrouted = transport.clone();
+    processor.process(&mut BinaryProtocol::default(), &mut unrouted).unwrap();
+    let mut result = SharedServiceGetStructResult::default();
+    match helpers::receive(&mut BinaryProtocol::default(), &mut MockTransport::new(unrouted.written().to_vec()),
+                           "get_struct", &mut result) {
+        Err(Error::ApplicationException(ref e)) if e.kind == ApplicationExceptionKind::UnknownMethod => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    processor.register_default(SharedServiceProcessor::new(Shared));
+    processor.process(&mut BinaryProtocol::default(), &mut transport).unwrap();
+    assert!(!transport.written().is_empty());
+}
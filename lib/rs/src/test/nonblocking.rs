@@ -0,0 +1,165 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+use exception::{ApplicationException, ApplicationExceptionKind};
+use processor::Processor;
+use protocol::{Decode, Limits, MessageType, Protocol};
+use protocol::binary_protocol::BinaryProtocol;
+use server::NonblockingServer;
+use transport::framed::FramedTransport;
+use transport::memory::MemoryTransport;
+use super::concurrent::{Echo, EchoClient, EchoProcessor};
+use Result;
+
+/// Records oneway calls, and holds calls to `echo(0)` until `release` has
+/// a message or is dropped.
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<i32>>>, Option<Arc<Mutex<mpsc::Receiver<()>>>>);
+
+impl Echo for Recorder {
+    fn echo(&self, value: i32) -> i32 {
+        if let (0, Some(release)) = (value, self.1.as_ref()) {
+            let _ = release.lock().unwrap().recv();
+        }
+        value
+    }
+
+    fn forget(&self, value: i32) {
+        self.0.lock().unwrap().push(value);
+    }
+}
+
+fn serve(recorder: Recorder) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let server = NonblockingServer::new(EchoProcessor::new(recorder), BinaryProtocol::default);
+    thread::spawn(move || server.serve(listener, 2));
+    addr
+}
+
+fn client(addr: &str) -> EchoClient<BinaryProtocol, FramedTransport<TcpStream>> {
+    EchoClient::new(BinaryProtocol::default(), FramedTransport::new(TcpStream::connect(addr).unwrap()))
+}
+
+#[test]
+fn test_more_connections_than_workers() {
+    let addr = serve(Recorder::default());
+
+    let mut clients: Vec<_> = (0..50).map(|_| client(&addr)).collect();
+    for (i, client) in clients.iter_mut().enumerate().rev() {
+        assert_eq!(client.echo(i as i32).unwrap(), i as i32);
+    }
+    for (i, client) in clients.iter_mut().enumerate() {
+        assert_eq!(client.echo(i as i32 * 2).unwrap(), i as i32 * 2);
+    }
+}
+
+#[test]
+fn test_oneway_calls_keep_order() {
+    let recorder = Recorder::default();
+    let addr = serve(recorder.clone());
+
+    let mut client = client(&addr);
+    for i in 0..10 {
+        client.forget(i).unwrap();
+    }
+    // Requests of one connection are processed one after the other.
+    assert_eq!(client.echo(0).unwrap(), 0);
+    assert_eq!(*recorder.0.lock().unwrap(), (0..10).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_failed_request_gets_exception() {
+    let mut client = client(&serve(Recorder::default()));
+
+    // The arguments hold a string field with a negative length.
+    client.protocol.write_message_begin(&mut client.transport, "echo", MessageType::Call, 7).unwrap();
+    client.transport.write_all(&[0x0b, 0x00, 0x01, 0xff, 0xff, 0xff, 0xff]).unwrap();
+    client.transport.flush().unwrap();
+
+    let (name, ty, id) = client.protocol.read_message_begin(&mut client.transport).unwrap();
+    assert_eq!((&name[..], ty, id), ("echo", MessageType::Exception, 7));
+    let mut error = ApplicationException::default();
+    error.decode(&mut client.protocol, &mut client.transport).unwrap();
+    client.protocol.read_message_end(&mut client.transport).unwrap();
+    assert_eq!(error.kind, ApplicationExceptionKind::ProtocolError);
+
+    assert_eq!(client.echo(3).unwrap(), 3);
+}
+
+#[test]
+fn test_oversized_frame_closes_connection() {
+    let addr = serve(Recorder::default());
+
+    let mut stream = TcpStream::connect(&addr[..]).unwrap();
+    stream.write_all(&[0x7f, 0xff, 0xff, 0xff]).unwrap();
+    assert_eq!(stream.read(&mut [0; 4]).unwrap_or(0), 0);
+
+    assert_eq!(client(&addr).echo(1).unwrap(), 1);
+}
+
+#[test]
+fn test_input_is_bounded_while_processing() {
+    let (release, release_rx) = mpsc::channel();
+    let recorder = Recorder(Arc::default(), Some(Arc::new(Mutex::new(release_rx))));
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let mut limits = Limits::default();
+    limits.max_message_size = 1024;
+    let factory = move || BinaryProtocol::default().with_limits(limits);
+    let server = NonblockingServer::new(EchoProcessor::new(recorder), factory);
+    thread::spawn(move || server.serve(listener, 2));
+
+    let mut client = client(&addr);
+    client.transport.get_mut().set_nonblocking(true).unwrap();
+    {
+        // Sends `echo(0)`, which is held until released.
+        let (protocol, transport) = (&mut client.protocol, &mut client.transport);
+        let mut args = super::concurrent::EchoEchoArgs::default();
+        args.value = Some(0);
+        ::protocol::helpers::send(protocol, transport, "echo", ::protocol::MessageType::Call, &args, 1).unwrap();
+    }
+
+    // The server stops reading, so the socket buffers fill up for good.
+    let chunk = vec![0; 64 * 1024];
+    let (mut sent, mut stalled) = (0, 0);
+    while sent < 64 * 1024 * 1024 && stalled < 20 {
+        match client.transport.get_mut().write(&chunk) {
+            Ok(n) => {
+                sent += n;
+                stalled = 0;
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                stalled += 1;
+                thread::sleep(Duration::from_millis(10));
+            }
+            Err(e) => panic!("{}", e),
+        }
+    }
+    assert!(sent < 64 * 1024 * 1024, "server buffered {} bytes", sent);
+    drop(release);
+}
+
+/// Panics outside the handler, where generated processors don't catch it.
+struct Panicking;
+
+impl Processor<BinaryProtocol, MemoryTransport> for Panicking {
+    fn process(&self, _: &mut BinaryProtocol, _: &mut MemoryTransport) -> Result<()> {
+        panic!("processor failed")
+    }
+}
+
+#[test]
+fn test_panicking_job_closes_connection() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let server = NonblockingServer::new(Panicking, BinaryProtocol::default);
+    thread::spawn(move || server.serve(listener, 1));
+
+    for _ in 0..2 {
+        assert!(client(&addr).echo(1).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::io::Cursor;
+
+use compiletest::*;
+use descriptor::ThriftService;
+use mock::MockTransport;
+use protocol::{helpers, MessageType, Protocol};
+use protocol::binary_protocol::BinaryProtocol;
+use transport::RwTransport;
+use Processor;
+
+#[derive(Clone, Default)]
+struct Notified(Rc<RefCell<Vec<String>>>);
+
+impl NotifyService for Notified {
+    fn notify(&self, message: String) {
+        self.0.borrow_mut().push(message);
+    }
+
+    fn count(&self) -> i32 {
+        self.0.borrow().len() as i32
+    }
+}
+
+#[test]
+fn test_oneway_client_does_not_wait() {
+    // Nothing to read: the call would fail if it waited for a reply.
+    let mut client = NotifyServiceClient::new(BinaryProtocol::default(), MockTransport::new(vec![]));
+    client.notify("hello".to_string()).unwrap();
+
+    let mut request = MockTransport::new(client.transport.written().to_vec());
+    let (name, ty, _) = BinaryProtocol::default().read_message_begin(&mut request).unwrap();
+    assert_eq!((&name[..], ty), ("notify", MessageType::Oneway));
+}
+
+#[test]
+fn test_oneway_processor_does_not_reply() {
+    let mut args = NotifyServiceNotifyArgs::default();
+    args.message = Some("hello".to_string());
+    let mut request = RwTransport(Cursor::new(Vec::new()));
+    helpers::send(&mut BinaryProtocol::default(), &mut request, "notify", MessageType::Oneway, &args, 0).unwrap();
+
+    let notified = Notified::default();
+    let processor = NotifyServiceProcessor::new(notified.clone());
+    let mut transport = MockTransport::new(request.0.into_inner());
+    processor.process(&mut BinaryProtocol::default(), &mut transport).unwrap();
+
+    assert!(transport.written().is_empty());
+    assert_eq!(*notified.0.borrow(), vec!["hello".to_string()]);
+}
+
+#[test]
+fn test_oneway_in_descriptor() {
+    let desc = NotifyServiceClient::<BinaryProtocol, MockTransport>::descriptor();
+    assert!(desc.method("notify").unwrap().oneway);
+    assert!(!desc.method("count").unwrap().oneway);
+}
@@ -0,0 +1,170 @@
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use client::Channel;
+use exception::{ApplicationException, ApplicationExceptionKind};
+use protocol::{helpers, Decode, MessageType, Protocol};
+use protocol::binary_protocol::BinaryProtocol;
+use protocol::json_protocol::JsonProtocol;
+use server::PipelinedServer;
+use transport::framed::FramedTransport;
+use transport::server::TcpWriter;
+use super::concurrent::{Echo, EchoClient, EchoConcurrentClient, EchoEchoArgs, EchoEchoResult, EchoProcessor};
+
+/// Echoes values, holding calls for 0 until they are released.
+struct Gate {
+    started: Mutex<mpsc::Sender<()>>,
+    release: Mutex<mpsc::Receiver<()>>,
+}
+
+impl Echo for Gate {
+    fn echo(&self, value: i32) -> i32 {
+        if value == 0 {
+            self.started.lock().unwrap().send(()).unwrap();
+            let _ = self.release.lock().unwrap().recv_timeout(Duration::from_secs(5));
+        }
+        value
+    }
+
+    fn forget(&self, _value: i32) {}
+}
+
+fn gate() -> (Gate, mpsc::Receiver<()>, mpsc::Sender<()>) {
+    let (started_tx, started) = mpsc::channel();
+    let (release, release_rx) = mpsc::channel();
+    (Gate { started: Mutex::new(started_tx), release: Mutex::new(release_rx) }, started, release)
+}
+
+fn connect(addr: SocketAddr) -> Arc<EchoConcurrentClient<BinaryProtocol, FramedTransport<TcpWriter>>> {
+    let transport = FramedTransport::new(TcpStream::connect(addr).unwrap());
+    Arc::new(EchoConcurrentClient::new(Channel::split(BinaryProtocol::default(), transport).unwrap()))
+}
+
+#[test]
+fn test_slow_call_does_not_block_connection() {
+    let (gate, started, release) = gate();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = PipelinedServer::new(EchoProcessor::new(gate),
+                                      BinaryProtocol::default, listener);
+    thread::spawn(move || server.serve(2));
+
+    let client = connect(addr);
+
+    let (done, finished) = mpsc::channel();
+    let slow = {
+        let (client, done) = (client.clone(), done.clone());
+        thread::spawn(move || done.send(client.echo(0).unwrap()).unwrap())
+    };
+    started.recv().unwrap();
+
+    assert_eq!(client.echo(1).unwrap(), 1);
+    done.send(1).unwrap();
+    release.send(()).unwrap();
+    slow.join().unwrap();
+
+    assert_eq!(finished.iter().take(2).collect::<Vec<_>>(), vec![1, 0]);
+}
+
+#[test]
+fn test_failed_request_gets_exception() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = PipelinedServer::new(EchoProcessor::new(gate().0), BinaryProtocol::default, listener);
+    thread::spawn(move || server.serve(2));
+
+    let transport = FramedTransport::new(TcpStream::connect(addr).unwrap());
+    let mut client = EchoClient::new(BinaryProtocol::default(), transport);
+
+    // The arguments hold a string field with a negative length.
+    client.protocol.write_message_begin(&mut client.transport, "echo", MessageType::Call, 7).unwrap();
+    client.transport.write_all(&[0x0b, 0x00, 0x01, 0xff, 0xff, 0xff, 0xff]).unwrap();
+    client.transport.flush().unwrap();
+
+    let (name, ty, id) = client.protocol.read_message_begin(&mut client.transport).unwrap();
+    assert_eq!((&name[..], ty, id), ("echo", MessageType::Exception, 7));
+    let mut error = ApplicationException::default();
+    error.decode(&mut client.protocol, &mut client.transport).unwrap();
+    client.protocol.read_message_end(&mut client.transport).unwrap();
+    assert_eq!(error.kind, ApplicationExceptionKind::ProtocolError);
+
+    assert_eq!(client.echo(3).unwrap(), 3);
+}
+
+#[test]
+fn test_max_in_flight() {
+    let (gate, started, release) = gate();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = PipelinedServer::new(EchoProcessor::new(gate), BinaryProtocol::default, listener)
+        .with_max_in_flight(1);
+    thread::spawn(move || server.serve(2));
+    let client = connect(addr);
+
+    let slow = {
+        let client = client.clone();
+        thread::spawn(move || client.echo(0).unwrap())
+    };
+    started.recv().unwrap();
+
+    let (done, finished) = mpsc::channel();
+    let fast = {
+        let client = client.clone();
+        thread::spawn(move || done.send(client.echo(1).unwrap()).unwrap())
+    };
+    // The fast call isn't read before the slow one is done.
+    assert!(finished.recv_timeout(Duration::from_millis(200)).is_err());
+
+    release.send(()).unwrap();
+    assert_eq!(slow.join().unwrap(), 0);
+    assert_eq!(finished.recv().unwrap(), 1);
+    fast.join().unwrap();
+}
+
+#[test]
+fn test_json_requests_on_one_connection() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = PipelinedServer::new(EchoProcessor::new(gate().0), JsonProtocol::new, listener);
+    thread::spawn(move || server.serve(2));
+
+    let transport = FramedTransport::new(TcpStream::connect(addr).unwrap());
+    let mut client = EchoClient::new(JsonProtocol::new(), transport);
+    assert_eq!(client.echo(1).unwrap(), 1);
+    assert_eq!(client.echo(2).unwrap(), 2);
+}
+
+#[test]
+fn test_bad_header_lets_requests_in_flight_finish() {
+    let (gate, started, release) = gate();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = PipelinedServer::new(EchoProcessor::new(gate), BinaryProtocol::default, listener);
+    thread::spawn(move || server.serve(2));
+
+    let mut transport = FramedTransport::new(TcpStream::connect(addr).unwrap());
+    let mut protocol = BinaryProtocol::default();
+    let mut args = EchoEchoArgs::default();
+    args.value = Some(0);
+    helpers::send(&mut protocol, &mut transport, "echo", MessageType::Call, &args, 5).unwrap();
+    started.recv().unwrap();
+
+    // A frame which isn't a message ends the connection, once the slow call
+    // has been answered.
+    transport.write_all(b"garbage").unwrap();
+    transport.flush().unwrap();
+    thread::sleep(Duration::from_millis(50));
+    release.send(()).unwrap();
+
+    assert_eq!(protocol.read_message_begin(&mut transport).unwrap(), ("echo".to_string(), MessageType::Reply, 5));
+    let mut result = EchoEchoResult::default();
+    result.decode(&mut protocol, &mut transport).unwrap();
+    assert_eq!(result.success, Some(0));
+    match transport.read(&mut [0]) {
+        Ok(0) | Err(_) => {}
+        other => panic!("connection still open: {:?}", other),
+    }
+}
@@ -0,0 +1,119 @@
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+use protocol::binary_protocol::BinaryProtocol;
+use server::{PooledServer, ServerHandle};
+use super::concurrent::{Echo, EchoClient, EchoProcessor};
+
+/// Echoes values, holding calls for 0 until they are released.
+struct Gate {
+    started: Mutex<mpsc::Sender<()>>,
+    release: Mutex<mpsc::Receiver<()>>,
+}
+
+impl Echo for Gate {
+    fn echo(&self, value: i32) -> i32 {
+        if value == 0 {
+            self.started.lock().unwrap().send(()).unwrap();
+            let _ = self.release.lock().unwrap().recv_timeout(Duration::from_secs(5));
+        }
+        value
+    }
+
+    fn forget(&self, _value: i32) {}
+}
+
+fn gate() -> (Gate, mpsc::Receiver<()>, mpsc::Sender<()>) {
+    let (started_tx, started) = mpsc::channel();
+    let (release, release_rx) = mpsc::channel();
+    (Gate { started: Mutex::new(started_tx), release: Mutex::new(release_rx) }, started, release)
+}
+
+fn serve(server: PooledServer<EchoProcessor<Gate>, fn() -> BinaryProtocol>) -> (ServerHandle, SocketAddr) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    (server.serve(listener), addr)
+}
+
+fn client(addr: SocketAddr) -> EchoClient<BinaryProtocol, TcpStream> {
+    EchoClient::new(BinaryProtocol::default(), TcpStream::connect(addr).unwrap())
+}
+
+#[test]
+fn test_shutdown_closes_idle_connections() {
+    let (handle, addr) = serve(PooledServer::new(EchoProcessor::new(gate().0),
+                                         BinaryProtocol::default as fn() -> BinaryProtocol));
+    let mut idle = client(addr);
+    assert_eq!(idle.echo(1).unwrap(), 1);
+
+    handle.shutdown();
+    handle.join();
+
+    assert!(idle.echo(2).is_err());
+}
+
+#[test]
+fn test_shutdown_drains_request_in_flight() {
+    let (gate, started, release) = gate();
+    let (handle, addr) = serve(PooledServer::new(EchoProcessor::new(gate),
+                                         BinaryProtocol::default as fn() -> BinaryProtocol));
+    let mut slow = client(addr);
+    let call = thread::spawn(move || slow.echo(0).unwrap());
+    started.recv().unwrap();
+
+    handle.shutdown();
+    release.send(()).unwrap();
+    handle.join();
+
+    assert_eq!(call.join().unwrap(), 0);
+}
+
+#[test]
+fn test_drain_timeout_closes_busy_connections() {
+    let (gate, started, release) = gate();
+    let (handle, addr) = serve(PooledServer::new(EchoProcessor::new(gate),
+                                         BinaryProtocol::default as fn() -> BinaryProtocol)
+                       .with_drain_timeout(Duration::from_millis(50)));
+    let mut slow = client(addr);
+    let call = thread::spawn(move || slow.echo(0).is_err());
+    started.recv().unwrap();
+
+    handle.shutdown();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(500));
+        drop(release);
+    });
+    handle.join();
+
+    assert!(call.join().unwrap());
+}
+
+#[test]
+fn test_max_connections() {
+    let (handle, addr) = serve(PooledServer::new(EchoProcessor::new(gate().0),
+                                         BinaryProtocol::default as fn() -> BinaryProtocol)
+                       .with_workers(1));
+    let mut first = client(addr);
+    assert_eq!(first.echo(1).unwrap(), 1);
+
+    let (done, finished) = mpsc::channel();
+    let mut second = client(addr);
+    thread::spawn(move || done.send(second.echo(2).unwrap()).unwrap());
+    assert!(finished.recv_timeout(Duration::from_millis(200)).is_err());
+
+    drop(first);
+    assert_eq!(finished.recv_timeout(Duration::from_secs(5)).unwrap(), 2);
+
+    handle.shutdown();
+    handle.join();
+}
+
+
+#[test]
+#[should_panic(expected = "Can't serve 0 connections.")]
+fn test_zero_max_connections() {
+    PooledServer::new(EchoProcessor::new(gate().0), BinaryProtocol::default as fn() -> BinaryProtocol)
+        .with_max_connections(0);
+}
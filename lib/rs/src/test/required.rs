@@ -0,0 +1,110 @@
+use std::io::Cursor;
+
+use compiletest::*;
+use descriptor::{Requiredness, ThriftStruct};
+use exception::{ApplicationException, ApplicationExceptionKind};
+use mock::MockTransport;
+use protocol::{self, helpers, Decode, Encode, MessageType, Protocol};
+use protocol::binary_protocol::BinaryProtocol;
+use test::generated::{Empty, WithDefaults};
+use transport::RwTransport;
+use {Error, Processor};
+
+strukt! {
+    name = Account,
+    fields = {
+        id: i64 => 1,
+        owner: String => 2,
+        note: Option<String> => 3,
+    },
+    required = [id, owner,]
+}
+
+strukt! {
+    name = PartialAccount,
+    fields = {
+        owner: String => 2,
+        note: Option<String> => 3,
+    }
+}
+
+fn roundtrip<E: Encode, D: Decode>(value: &E) -> ::Result<D> {
+    let mut transport = MockTransport::new(vec![]);
+    value.encode(&mut BinaryProtocol::default(), &mut transport).unwrap();
+    let mut decoded = D::default();
+    try!(decoded.decode(&mut BinaryProtocol::default(), &mut MockTransport::new(transport.written().to_vec())));
+    Ok(decoded)
+}
+
+#[test]
+fn test_required_fields_present() {
+    let account = Account { id: 1, owner: "me".to_string(), note: None };
+    assert_eq!(roundtrip::<_, Account>(&account).unwrap(), account);
+}
+
+#[test]
+fn test_missing_required_field() {
+    let partial = PartialAccount { owner: "me".to_string(), note: Some("n".to_string()) };
+    match roundtrip::<_, Account>(&partial) {
+        Err(Error::ProtocolError(protocol::Error::MissingRequiredField("Account", "id"))) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_missing_required_field_with_default() {
+    match roundtrip::<_, WithDefaults>(&Empty) {
+        Err(Error::ProtocolError(protocol::Error::MissingRequiredField("WithDefaults", "num"))) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_required_in_descriptor() {
+    let desc = Account::descriptor();
+    assert_eq!(desc.field(1).unwrap().requiredness, Requiredness::Required);
+    assert_eq!(desc.field(2).unwrap().requiredness, Requiredness::Required);
+    assert_eq!(desc.field(3).unwrap().requiredness, Requiredness::Optional);
+    assert_eq!(PartialAccount::descriptor().field(2).unwrap().requiredness, Requiredness::Default);
+
+    assert!(desc.field(1).unwrap().required);
+    assert!(desc.field(2).unwrap().required);
+    assert!(!desc.field(3).unwrap().required);
+    assert!(!PartialAccount::descriptor().field(2).unwrap().required);
+}
+
+struct Child;
+
+impl ChildService for Child {
+    fn operation(&self, _one: String, _another: i32) -> Operation {
+        panic!("must not be called without all arguments")
+    }
+}
+
+struct Shared;
+
+impl SharedService for Shared {
+    fn get_struct(&self, _key: i32) -> DeeplyNested {
+        DeeplyNested::default()
+    }
+}
+
+#[test]
+fn test_missing_argument_answers_exception() {
+    let mut args = ChildServiceOperationArgs::default();
+    args.one = Some("one".to_string());
+
+    let mut request = RwTransport(Cursor::new(Vec::new()));
+    helpers::send(&mut BinaryProtocol::default(), &mut request, "operation", MessageType::Call, &args, 3).unwrap();
+    let mut transport = MockTransport::new(request.0.into_inner());
+    ChildServiceProcessor::new(Shared, Child).process(&mut BinaryProtocol::default(), &mut transport).unwrap();
+
+    let mut protocol = BinaryProtocol::default();
+    let mut reply = MockTransport::new(transport.written().to_vec());
+    assert_eq!(protocol.read_message_begin(&mut reply).unwrap(),
+               ("operation".to_string(), MessageType::Exception, 3));
+    let mut exception = ApplicationException::default();
+    exception.decode(&mut protocol, &mut reply).unwrap();
+    assert_eq!(exception.kind, ApplicationExceptionKind::ProtocolError);
+    assert_eq!(exception.message, "missing argument another to operation");
+}
@@ -0,0 +1,78 @@
+use std::io::Cursor;
+
+use compiletest::*;
+use mock::MockTransport;
+use protocol::{self, helpers, Encode, MessageType, Protocol, ReplyMismatch};
+use protocol::binary_protocol::BinaryProtocol;
+use transport::RwTransport;
+use Error;
+
+fn reply(name: &str, ty: MessageType, count: i32, id: i32) -> Vec<u8> {
+    let mut result = NotifyServiceCountResult::default();
+    result.success = Some(count);
+    message(name, ty, &result, id)
+}
+
+fn message<E: Encode>(name: &str, ty: MessageType, body: &E, id: i32) -> Vec<u8> {
+    let mut transport = RwTransport(Cursor::new(Vec::new()));
+    helpers::send(&mut BinaryProtocol::default(), &mut transport, name, ty, body, id).unwrap();
+    transport.0.into_inner()
+}
+
+fn client(replies: Vec<Vec<u8>>) -> NotifyServiceClient<BinaryProtocol, MockTransport> {
+    let replies = replies.into_iter().flat_map(|r| r.into_iter()).collect();
+    NotifyServiceClient::new(BinaryProtocol::default(), MockTransport::new(replies))
+}
+
+fn mismatch(result: ::Result<i32>) -> ReplyMismatch {
+    match result {
+        Err(Error::ProtocolError(protocol::Error::UnexpectedReply(mismatch))) => mismatch,
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_sequence_ids_increment() {
+    let mut client = client(vec![reply("count", MessageType::Reply, 5, 1),
+                                 reply("count", MessageType::Reply, 6, 3)]);
+    assert_eq!(client.count().unwrap(), 5);
+    client.notify("hello".to_string()).unwrap();
+    assert_eq!(client.count().unwrap(), 6);
+
+    let mut protocol = BinaryProtocol::default();
+    let mut requests = MockTransport::new(client.transport.written().to_vec());
+    let mut ids = vec![];
+    for _ in 0..3 {
+        let (_, _, id) = protocol.read_message_begin(&mut requests).unwrap();
+        protocol.skip(&mut requests, protocol::Type::Struct).unwrap();
+        protocol.read_message_end(&mut requests).unwrap();
+        ids.push(id);
+    }
+    assert_eq!(ids, vec![1, 2, 3]);
+}
+
+#[test]
+fn test_wrong_sequence_id() {
+    let mut client = client(vec![reply("count", MessageType::Reply, 5, 4)]);
+    assert_eq!(mismatch(client.count()), ReplyMismatch::SequenceId(1, 4));
+}
+
+#[test]
+fn test_wrong_method_name() {
+    let mut client = client(vec![reply("other", MessageType::Reply, 5, 1)]);
+    assert_eq!(mismatch(client.count()), ReplyMismatch::MethodName("count".to_string(), "other".to_string()));
+}
+
+#[test]
+fn test_wrong_message_type() {
+    let mut client = client(vec![reply("count", MessageType::Call, 5, 1)]);
+    assert_eq!(mismatch(client.count()), ReplyMismatch::MessageType(MessageType::Call));
+}
+
+#[test]
+fn test_mismatched_reply_is_skipped() {
+    let mut client = client(vec![reply("count", MessageType::Reply, 5, 7),
+                                 reply("count", MessageType::Reply, 6, 2)]);
+    assert_eq!(mismatch(client.count()), ReplyMismatch::SequenceId(1, 7));
+    assert_eq!(client.count().unwrap(), 6);
+}
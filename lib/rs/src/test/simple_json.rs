@@ -0,0 +1,44 @@
+use std::collections::{BTreeSet, BTreeMap};
+
+use test::generated::*;
+
+use protocol::simple_json_protocol;
+
+#[test]
+fn test_simple_json_struct() {
+    let mut five = BTreeSet::new();
+    five.insert(Operation::Clear);
+
+    let instance = Many {
+        one: 17,
+        two: String::from("Some \"String\""),
+        three: vec![Simple { key: String::from("A String") }],
+        five: five,
+        six: None,
+    };
+
+    assert_eq!(
+        simple_json_protocol::to_string(&instance).unwrap(),
+        r#"{"one":17,"two":"Some \"String\"","three":[{"key":"A String"}],"five":[3]}"#
+    );
+}
+
+#[test]
+fn test_simple_json_map() {
+    let mut names = BTreeMap::new();
+    names.insert(String::from("yes"), true);
+
+    let instance = Flags {
+        first: true,
+        second: false,
+        far: 1,
+        names: names,
+        empty: BTreeMap::new(),
+        bits: vec![],
+    };
+
+    assert_eq!(
+        simple_json_protocol::to_string(&instance).unwrap(),
+        r#"{"first":true,"second":false,"far":1,"names":{"yes":true},"empty":{},"bits":[]}"#
+    );
+}
@@ -207,3 +207,24 @@ fn test_struct_with_optional_field_as_none() {
     assert_eq!(instance.this, second.this);
 }
 
+
+#[test]
+fn test_struct_with_defaults() {
+    let instance = WithDefaults::default();
+    assert_eq!(instance.num, 7);
+    assert_eq!(instance.op, Operation::Clear);
+    assert_eq!(instance.comment, Some(String::from("x")));
+    assert_eq!(instance.plain, 0);
+}
+
+#[test]
+fn test_decode_keeps_defaults_of_missing_fields() {
+    let mut sent = WithDefaults::default();
+    sent.comment = None;
+    sent.num = 1;
+    let decoded = decode::<WithDefaults>(&mut encode(&sent));
+    assert_eq!(decoded.num, 1);
+    assert_eq!(decoded.op, Operation::Clear);
+    // Unset optional fields are not sent, so the receiver sees the default.
+    assert_eq!(decoded.comment, Some(String::from("x")));
+}
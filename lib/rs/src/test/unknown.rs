@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+
+use mock::MockTransport;
+use protocol::{Decode, Encode, Protocol, Type};
+use protocol::binary_protocol::BinaryProtocol;
+use protocol::compact_protocol::CompactProtocol;
+use value::Value;
+
+use test::generated::*;
+
+strukt! {
+    name = RecordV1,
+    fields = {
+        id: i64 => 1,
+    },
+    unknown_fields = unknown
+}
+
+strukt! {
+    name = RecordV2,
+    fields = {
+        id: i64 => 1,
+        name: String => 2,
+        tags: BTreeMap<String, Vec<Simple>> => 3,
+    }
+}
+
+// Like `RecordV1`, but from an IDL where field 2 had another type.
+strukt! {
+    name = Retyped,
+    fields = {
+        id: i64 => 1,
+        name: Option<i32> => 2,
+    },
+    unknown_fields = unknown
+}
+
+strukt! {
+    name = Opaque,
+    fields = {
+    },
+    unknown_fields = unknown
+}
+
+fn encode_with<P: Protocol, E: Encode>(mut protocol: P, value: &E) -> Vec<u8> {
+    let mut transport = MockTransport::new(vec![]);
+    value.encode(&mut protocol, &mut transport).unwrap();
+    transport.written().to_vec()
+}
+
+fn decode_with<P: Protocol, D: Decode>(mut protocol: P, bytes: Vec<u8>) -> D {
+    let mut value = D::default();
+    value.decode(&mut protocol, &mut MockTransport::new(bytes)).unwrap();
+    value
+}
+
+fn v2() -> RecordV2 {
+    let mut tags = BTreeMap::new();
+    tags.insert("a".to_string(), vec![Simple { key: "b".to_string() }]);
+    RecordV2 { id: 7, name: "seven".to_string(), tags: tags }
+}
+
+#[test]
+fn test_unknown_fields_survive_roundtrip() {
+    let mut v1: RecordV1 = decode_with(BinaryProtocol::default(), encode_with(BinaryProtocol::default(), &v2()));
+    assert_eq!(v1.id, 7);
+    assert_eq!(v1.unknown.len(), 2);
+    assert_eq!(v1.unknown.get(2).unwrap(), Some(Value::Binary(b"seven".to_vec())));
+    assert_eq!(v1.unknown.get(4).unwrap(), None);
+    assert_eq!(v1.unknown.binary_bytes(2), Some((Type::String, &[0, 0, 0, 5, b's', b'e', b'v', b'e', b'n'][..])));
+
+    v1.id = 8;
+    let v2: RecordV2 = decode_with(BinaryProtocol::default(), encode_with(BinaryProtocol::default(), &v1));
+    assert_eq!(v2, RecordV2 { id: 8, ..self::v2() });
+}
+
+#[test]
+fn test_unknown_fields_across_protocols() {
+    let v1: RecordV1 = decode_with(CompactProtocol::new(), encode_with(CompactProtocol::new(), &v2()));
+    // Stored re-encoded with the binary protocol, whatever it was read with.
+    assert_eq!(v1.unknown.binary_bytes(2), Some((Type::String, &[0, 0, 0, 5, b's', b'e', b'v', b'e', b'n'][..])));
+    let v2: RecordV2 = decode_with(BinaryProtocol::default(), encode_with(BinaryProtocol::default(), &v1));
+    assert_eq!(v2, self::v2());
+}
+
+#[test]
+fn test_struct_without_known_fields() {
+    let opaque: Opaque = decode_with(BinaryProtocol::default(), encode_with(BinaryProtocol::default(), &v2()));
+    assert_eq!(opaque.unknown.ids().collect::<Vec<_>>(), vec![1, 2, 3]);
+    let v2: RecordV2 = decode_with(BinaryProtocol::default(), encode_with(BinaryProtocol::default(), &opaque));
+    assert_eq!(v2, self::v2());
+}
+
+#[test]
+fn test_known_id_with_wrong_type_is_kept() {
+    let mut bytes = encode_with(BinaryProtocol::default(), &Simple { key: "x".to_string() });
+    // Turn the string field 16 into field 1, which `RecordV1` knows as an i64.
+    assert_eq!(&bytes[..3], &[0x0b, 0x00, 0x10]);
+    bytes[2] = 1;
+
+    let v1: RecordV1 = decode_with(BinaryProtocol::default(), bytes);
+    assert_eq!(v1.id, 0);
+    assert_eq!(v1.unknown.get(1).unwrap(), Some(Value::Binary(b"x".to_vec())));
+    // `id` is always written, so the unknown field with its id is not.
+    assert_eq!(encode_with(BinaryProtocol::default(), &v1),
+               encode_with(BinaryProtocol::default(), &RecordV1::default()));
+}
+
+#[test]
+fn test_without_opt_in_unknown_fields_are_skipped() {
+    let simple: Simple = decode_with(BinaryProtocol::default(), encode_with(BinaryProtocol::default(), &v2()));
+    assert_eq!(encode_with(BinaryProtocol::default(), &simple), encode_with(BinaryProtocol::default(), &Simple::default()));
+}
+
+#[test]
+fn test_wrong_typed_known_field_survives_roundtrip() {
+    let mut retyped: Retyped = decode_with(BinaryProtocol::default(), encode_with(BinaryProtocol::default(), &v2()));
+    assert_eq!(retyped.name, None);
+    assert_eq!(retyped.unknown.get(2).unwrap(), Some(Value::Binary(b"seven".to_vec())));
+    let v2: RecordV2 = decode_with(BinaryProtocol::default(), encode_with(BinaryProtocol::default(), &retyped));
+    assert_eq!(v2, self::v2());
+
+    // Once the known field is set, it is written instead.
+    retyped.name = Some(3);
+    let again: Retyped = decode_with(BinaryProtocol::default(), encode_with(BinaryProtocol::default(), &retyped));
+    assert_eq!(again.name, Some(3));
+    assert_eq!(again.unknown.ids().collect::<Vec<_>>(), vec![3]);
+}
@@ -0,0 +1,118 @@
+use mock::MockTransport;
+use descriptor::ThriftStruct;
+use protocol::{self, Decode, Encode, Protocol, Type};
+use protocol::binary_protocol::BinaryProtocol;
+use value::Value;
+use Error;
+
+use test::generated::*;
+
+unyon! {
+    name = Shape,
+    fields = {
+        Radius(radius: i32 => 1),
+        Name(name: String => 2),
+        Nested(nested: Simple => 3),
+    }
+}
+
+unyon! {
+    name = Nothing,
+    fields = {
+    }
+}
+
+strukt! {
+    name = Drawing,
+    fields = {
+        shape: Shape => 1,
+        count: i32 => 2,
+    }
+}
+
+fn encode<E: Encode>(value: &E) -> ::Result<Vec<u8>> {
+    let mut transport = MockTransport::new(vec![]);
+    try!(value.encode(&mut BinaryProtocol::default(), &mut transport));
+    Ok(transport.written().to_vec())
+}
+
+fn decode<D: Decode>(bytes: Vec<u8>) -> ::Result<D> {
+    let mut value = D::default();
+    try!(value.decode(&mut BinaryProtocol::default(), &mut MockTransport::new(bytes)));
+    Ok(value)
+}
+
+#[test]
+fn test_union_roundtrip() {
+    for shape in vec![Shape::Radius(3), Shape::Name("x".to_string()),
+                      Shape::Nested(Simple { key: "k".to_string() })] {
+        assert_eq!(decode::<Shape>(encode(&shape).unwrap()).unwrap(), shape);
+    }
+}
+
+#[test]
+fn test_union_encodes_one_field() {
+    let bytes = encode(&Shape::Radius(3)).unwrap();
+    assert_eq!(bytes, vec![0x08, 0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x00]);
+}
+
+#[test]
+fn test_empty_union() {
+    match encode(&Shape::Empty) {
+        Err(Error::ProtocolError(protocol::Error::ProtocolViolation)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // As a field an empty union is left out, like an unset optional.
+    let drawing = Drawing { shape: Shape::Empty, count: 2 };
+    assert_eq!(decode::<Drawing>(encode(&drawing).unwrap()).unwrap(), drawing);
+    let drawing = Drawing { shape: Shape::Name("n".to_string()), count: 2 };
+    assert_eq!(decode::<Drawing>(encode(&drawing).unwrap()).unwrap(), drawing);
+}
+
+#[test]
+fn test_union_with_two_fields_is_rejected() {
+    let mut protocol = BinaryProtocol::default();
+    let mut transport = MockTransport::new(vec![]);
+    protocol.write_struct_begin(&mut transport, "Shape").unwrap();
+    protocol.write_field_begin(&mut transport, "radius", Type::I32, 1).unwrap();
+    protocol.write_i32(&mut transport, 3).unwrap();
+    protocol.write_field_begin(&mut transport, "name", Type::String, 2).unwrap();
+    protocol.write_str(&mut transport, "x").unwrap();
+    protocol.write_field_stop(&mut transport).unwrap();
+
+    match decode::<Shape>(transport.written().to_vec()) {
+        Err(Error::ProtocolError(protocol::Error::ProtocolViolation)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_union_unknown_field_is_empty() {
+    let bytes = encode(&Simple { key: "k".to_string() }).unwrap();
+    assert_eq!(decode::<Shape>(bytes).unwrap(), Shape::Empty);
+}
+
+#[test]
+fn test_union_dynamic_access() {
+    let mut shape = Shape::Radius(3);
+    assert_eq!(shape.get_field(1), Some(Value::I32(3)));
+    assert_eq!(shape.get_field(2), None);
+    shape.set_field_by_name("name", Value::Binary(b"n".to_vec())).unwrap();
+    assert_eq!(shape, Shape::Name("n".to_string()));
+    assert_eq!(Shape::descriptor().fields.len(), 3);
+
+    shape.clear_field(1).unwrap();
+    assert_eq!(shape, Shape::Name("n".to_string()));
+    shape.clear_field_by_name("name").unwrap();
+    assert_eq!(shape, Shape::Empty);
+    assert!(shape.clear_field(7).is_err());
+}
+
+#[test]
+fn test_union_without_fields() {
+    assert!(encode(&Nothing::Empty).is_err());
+    assert_eq!(decode::<Nothing>(encode(&Simple { key: "k".to_string() }).unwrap()).unwrap(), Nothing::Empty);
+    assert!(Nothing::descriptor().fields.is_empty());
+    assert!(Nothing::Empty.clone().set_field(1, Value::I32(1)).is_err());
+}
@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+
+use mock::MockTransport;
+use protocol::{self, Decode, Encode, Protocol, Type};
+use protocol::binary_protocol::BinaryProtocol;
+use protocol::compact_protocol::CompactProtocol;
+use descriptor::ThriftStruct;
+use value::{self, Value};
+use Error;
+
+use test::generated::*;
+
+fn encode_with<P: Protocol, E: Encode>(mut protocol: P, value: &E) -> Vec<u8> {
+    let mut transport = MockTransport::new(vec![]);
+    value.encode(&mut protocol, &mut transport).unwrap();
+    transport.written().to_vec()
+}
+
+fn decode_with<P: Protocol, D: Decode>(mut protocol: P, bytes: Vec<u8>) -> D {
+    let mut value = D::default();
+    value.decode(&mut protocol, &mut MockTransport::new(bytes)).unwrap();
+    value
+}
+
+fn many() -> Many {
+    Many {
+        one: 1,
+        two: "two".to_string(),
+        three: vec![Simple { key: "three".to_string() }],
+        five: vec![Operation::Add, Operation::Clear].into_iter().collect(),
+        six: None,
+    }
+}
+
+#[test]
+fn test_decode_struct() {
+    let value: Value = decode_with(BinaryProtocol::default(), encode_with(BinaryProtocol::default(), &many()));
+
+    let mut simple = BTreeMap::new();
+    simple.insert(16, Value::Binary(b"three".to_vec()));
+    let mut fields = BTreeMap::new();
+    fields.insert(3, Value::I32(1));
+    fields.insert(4, Value::Binary(b"two".to_vec()));
+    fields.insert(9, Value::List(Type::Struct, vec![Value::Struct(simple)]));
+    fields.insert(11, Value::Set(Type::I32, vec![Value::I32(1), Value::I32(3)]));
+    assert_eq!(value, Value::Struct(fields));
+}
+
+#[test]
+fn test_binary_roundtrip_is_identical() {
+    let bytes = encode_with(BinaryProtocol::default(), &many());
+    let value: Value = decode_with(BinaryProtocol::default(), bytes.clone());
+    assert_eq!(encode_with(BinaryProtocol::default(), &value), bytes);
+}
+
+#[test]
+fn test_compact_roundtrip_is_identical() {
+    let bytes = encode_with(CompactProtocol::new(), &many());
+    let value: Value = decode_with(CompactProtocol::new(), bytes.clone());
+    assert_eq!(encode_with(CompactProtocol::new(), &value), bytes);
+}
+
+#[test]
+fn test_roundtrip_through_typed() {
+    let mut flags = Flags::default();
+    flags.first = true;
+    flags.far = -3;
+    flags.names.insert("x".to_string(), true);
+    flags.bits = vec![true, false];
+
+    let value: Value = decode_with(CompactProtocol::new(), encode_with(CompactProtocol::new(), &flags));
+    let decoded: Flags = decode_with(CompactProtocol::new(), encode_with(CompactProtocol::new(), &value));
+    assert_eq!(decoded, flags);
+}
+
+#[test]
+fn test_decode_non_struct() {
+    let list = vec![1i64, 2, 3];
+    let mut value = Value::List(Type::Void, vec![]);
+    value.decode(&mut BinaryProtocol::default(),
+                 &mut MockTransport::new(encode_with(BinaryProtocol::default(), &list))).unwrap();
+    assert_eq!(value, Value::List(Type::I64, vec![Value::I64(1), Value::I64(2), Value::I64(3)]));
+}
+
+#[test]
+fn test_encode_checks_element_types() {
+    let value = Value::List(Type::I32, vec![Value::I32(1), Value::Bool(true)]);
+    let mut transport = MockTransport::new(vec![]);
+    match value.encode(&mut BinaryProtocol::default(), &mut transport) {
+        Err(Error::ProtocolError(protocol::Error::ProtocolViolation)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_get_field() {
+    let many = many();
+    assert_eq!(many.get_field(3), Some(Value::I32(1)));
+    assert_eq!(many.get_field_by_name("two"), Some(Value::Binary(b"two".to_vec())));
+    assert_eq!(many.get_field(11), Some(Value::Set(Type::I32, vec![Value::I32(1), Value::I32(3)])));
+    // Unset optional fields and unknown ids alike have no value.
+    assert_eq!(many.get_field(14), None);
+    assert_eq!(many.get_field(5), None);
+    assert_eq!(Empty::default().get_field(1), None);
+}
+
+#[test]
+fn test_set_field() {
+    let mut many = many();
+    many.set_field(3, Value::I32(42)).unwrap();
+    many.set_field_by_name("two", Value::Binary(b"changed".to_vec())).unwrap();
+    many.set_field(11, Value::Set(Type::I32, vec![Value::I32(2)])).unwrap();
+    many.set_field(14, value::to_value(&Simple { key: "six".to_string() }).unwrap()).unwrap();
+
+    assert_eq!(many.one, 42);
+    assert_eq!(many.two, "changed");
+    assert_eq!(many.five, vec![Operation::Sub].into_iter().collect());
+    assert_eq!(many.six, Some(Simple { key: "six".to_string() }));
+}
+
+#[test]
+fn test_set_and_clear_optional_field() {
+    let mut many = many();
+    many.set_field_by_name("six", value::to_value(&Simple { key: "six".to_string() }).unwrap()).unwrap();
+    assert_eq!(many.six, Some(Simple { key: "six".to_string() }));
+
+    many.clear_field(14).unwrap();
+    assert_eq!(many.six, None);
+    assert_eq!(many.get_field(14), None);
+    // Clearing an unset field is fine.
+    many.clear_field_by_name("six").unwrap();
+
+    many.set_field(14, value::to_value(&Simple { key: "again".to_string() }).unwrap()).unwrap();
+    assert_eq!(many.six, Some(Simple { key: "again".to_string() }));
+}
+
+#[test]
+fn test_clear_field_rejects_non_optional_fields() {
+    let mut many = many();
+    for &id in &[3, 4, 5] {
+        match many.clear_field(id) {
+            Err(Error::ProtocolError(protocol::Error::ProtocolViolation)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+    assert!(many.clear_field_by_name("missing").is_err());
+    assert!(Empty::default().clear_field(1).is_err());
+    assert_eq!(many, self::many());
+}
+
+#[test]
+fn test_set_field_rejects_bad_values() {
+    let mut many = many();
+    for &(id, ref value) in &[(3, Value::I64(1)),
+                              (11, Value::Set(Type::I32, vec![Value::I32(100)])),
+                              (5, Value::I32(1))] {
+        match many.set_field(id, value.clone()) {
+            Err(Error::ProtocolError(protocol::Error::ProtocolViolation)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+    assert!(many.set_field_by_name("missing", Value::I32(1)).is_err());
+    assert_eq!(many, self::many());
+}
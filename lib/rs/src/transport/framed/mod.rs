@@ -0,0 +1,135 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+//! The framed transport.
+//!
+//! Every message is sent as one frame, prefixed with its length as a
+//! big-endian i32. Servers relying on it, like
+//! `server::NonblockingServer`, can tell when a whole message has arrived
+//! without decoding it.
+
+use std::io::{self, Cursor, Read, Write};
+use std::mem;
+
+use podio::{ReadPodExt, WritePodExt, BigEndian};
+
+use protocol;
+use protocol::limits::{DEFAULT_MAX_MESSAGE_SIZE, Limit};
+use transport::{Split, Transport};
+
+pub struct FramedTransport<T> {
+    inner: T,
+    read: Cursor<Vec<u8>>,
+    write: Vec<u8>,
+    max_frame_size: usize,
+}
+
+impl<T: Read + Write> FramedTransport<T> {
+    /// Accepts frames of up to `limits::DEFAULT_MAX_MESSAGE_SIZE` bytes.
+    pub fn new(inner: T) -> FramedTransport<T> {
+        FramedTransport {
+            inner: inner,
+            read: Cursor::new(Vec::new()),
+            write: Vec::new(),
+            max_frame_size: DEFAULT_MAX_MESSAGE_SIZE,
+        }
+    }
+
+    /// The largest frame accepted, excluding the length prefix. Larger ones
+    /// fail with `LimitExceeded`.
+    pub fn with_max_frame_size(mut self, max_frame_size: usize) -> FramedTransport<T> {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Reads the next frame as a whole, dropping whatever is left of the
+    /// one being read.
+    pub fn read_frame(&mut self) -> io::Result<Vec<u8>> {
+        self.read = Cursor::new(Vec::new());
+        let size = try!(self.inner.read_i32::<BigEndian>());
+        if size < 0 {
+            return Err(io::Error::from(protocol::Error::ProtocolViolation));
+        }
+        if size as usize > self.max_frame_size {
+            return Err(io::Error::from(protocol::Error::LimitExceeded(Limit::MessageSize)));
+        }
+        // The buffer grows as data arrives, rather than trusting the size.
+        let mut frame = Vec::new();
+        try!((&mut self.inner).take(size as u64).read_to_end(&mut frame));
+        if frame.len() < size as usize {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "frame truncated"));
+        }
+        Ok(frame)
+    }
+}
+
+impl<T: Read + Write> Read for FramedTransport<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.read.position() as usize == self.read.get_ref().len() {
+            self.read = Cursor::new(try!(self.read_frame()));
+        }
+        self.read.read(buf)
+    }
+}
+
+impl<T: Read + Write> Write for FramedTransport<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    /// Sends everything written since the last flush as one frame.
+    fn flush(&mut self) -> io::Result<()> {
+        if self.write.len() > i32::max_value() as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame too large"));
+        }
+        let payload = mem::replace(&mut self.write, Vec::new());
+        let mut frame = Vec::with_capacity(4 + payload.len());
+        try!(frame.write_i32::<BigEndian>(payload.len() as i32));
+        frame.extend_from_slice(&payload);
+        try!(self.inner.write_all(&frame));
+        self.inner.flush()
+    }
+}
+
+impl<T: Read + Write> Transport for FramedTransport<T> {}
+
+impl<T: Split> Split for FramedTransport<T> {
+    type Reader = FramedTransport<T::Reader>;
+    type Writer = FramedTransport<T::Writer>;
+
+    /// Both ends keep the maximum frame size. Anything buffered is dropped.
+    fn split(self) -> io::Result<(Self::Reader, Self::Writer)> {
+        let max_frame_size = self.max_frame_size;
+        let (reader, writer) = try!(self.inner.split());
+        Ok((FramedTransport::new(reader).with_max_frame_size(max_frame_size),
+            FramedTransport::new(writer).with_max_frame_size(max_frame_size)))
+    }
+}
+
+#[cfg(test)]
+pub mod test;
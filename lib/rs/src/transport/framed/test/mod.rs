@@ -0,0 +1,74 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::io::{Cursor, ErrorKind, Read, Write};
+
+use super::FramedTransport;
+use protocol::{self, Limit};
+
+#[test]
+fn write_frame() {
+    let mut transport = FramedTransport::new(Cursor::new(Vec::new()));
+    transport.write_all(b"ab").unwrap();
+    transport.write_all(b"c").unwrap();
+    assert!(transport.get_ref().get_ref().is_empty());
+
+    transport.flush().unwrap();
+    assert_eq!(&transport.get_ref().get_ref()[..], &[0, 0, 0, 3, b'a', b'b', b'c'][..]);
+}
+
+#[test]
+fn read_frames() {
+    let input = vec![0, 0, 0, 2, b'a', b'b', 0, 0, 0, 1, b'c'];
+    let mut transport = FramedTransport::new(Cursor::new(input));
+
+    let mut buf = [0; 3];
+    assert_eq!(transport.read(&mut buf).unwrap(), 2);
+    assert_eq!(&buf[..2], b"ab");
+    assert_eq!(transport.read(&mut buf).unwrap(), 1);
+    assert_eq!(buf[0], b'c');
+    assert!(transport.read(&mut buf).is_err());
+}
+
+#[test]
+fn reject_large_frame() {
+    let input = vec![0, 0, 0, 5, 1, 2, 3, 4, 5];
+    let mut transport = FramedTransport::new(Cursor::new(input)).with_max_frame_size(4);
+    let err = transport.read(&mut [0; 5]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    match ::Error::from(err) {
+        ::Error::ProtocolError(protocol::Error::LimitExceeded(Limit::MessageSize)) => {}
+        err => panic!("unexpected error {:?}", err),
+    }
+}
+
+#[test]
+fn read_whole_frame() {
+    let input = vec![0, 0, 0, 2, b'a', b'b', 0, 0, 0, 1, b'c'];
+    let mut transport = FramedTransport::new(Cursor::new(input));
+    assert_eq!(transport.read(&mut [0; 1]).unwrap(), 1);
+    assert_eq!(transport.read_frame().unwrap(), vec![b'c']);
+}
+
+#[test]
+fn truncated_frame() {
+    let input = vec![0x7f, 0xff, 0xff, 0xff, 1, 2];
+    let mut transport = FramedTransport::new(Cursor::new(input)).with_max_frame_size(usize::max_value());
+    assert_eq!(transport.read_frame().unwrap_err().kind(), ErrorKind::UnexpectedEof);
+}
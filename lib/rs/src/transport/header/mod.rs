@@ -0,0 +1,448 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+//! The THeader transport.
+//!
+//! Every message is sent in its own frame, which carries the id of the
+//! protocol the payload is encoded with, the sequence id, a list of
+//! transforms (e.g. compression) applied to the payload and a set of
+//! key/value info headers. Use it together with
+//! `protocol::header_protocol::HeaderProtocol`.
+//!
+//! Clients get at the headers through `Transport::header`. Handlers called by
+//! generated processors read the headers of the request they are serving
+//! with `request_header` and attach headers to the reply with
+//! `set_reply_header`.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{self, Cursor, Read, Write};
+use std::mem;
+
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use podio::{ReadPodExt, WritePodExt, BigEndian};
+
+use protocol;
+use protocol::limits::{DEFAULT_MAX_MESSAGE_SIZE, Limit, Limits};
+use transport::Transport;
+
+pub static HEADER_MAGIC: u16 = 0x0fff;
+
+/// The largest frame accepted or sent, excluding the length prefix.
+pub static MAX_FRAME_SIZE: u32 = 0x3fff_ffff;
+
+static INFO_PADDING: u32 = 0;
+static INFO_KEYVALUE: u32 = 1;
+
+pub type Headers = BTreeMap<String, String>;
+
+/// The protocol the payload of a frame is encoded with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProtocolId {
+    Binary = 0x00,
+    Compact = 0x02,
+}
+
+impl ProtocolId {
+    pub fn from_num(num: u32) -> Option<ProtocolId> {
+        match num {
+            0x00 => Some(ProtocolId::Binary),
+            0x02 => Some(ProtocolId::Compact),
+            _ => None,
+        }
+    }
+}
+
+impl Default for ProtocolId {
+    fn default() -> ProtocolId {
+        ProtocolId::Binary
+    }
+}
+
+/// A transformation of the frame payload.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Transform {
+    Zlib = 0x01,
+}
+
+impl Transform {
+    pub fn from_num(num: u32) -> Option<Transform> {
+        match num {
+            0x01 => Some(Transform::Zlib),
+            _ => None,
+        }
+    }
+
+    fn apply(self, payload: Vec<u8>) -> io::Result<Vec<u8>> {
+        match self {
+            Transform::Zlib => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                try!(encoder.write_all(&payload));
+                encoder.finish()
+            }
+        }
+    }
+
+    /// Fails once the reverted payload grows beyond `max` bytes.
+    fn revert(self, payload: Vec<u8>, max: usize) -> io::Result<Vec<u8>> {
+        match self {
+            Transform::Zlib => {
+                let mut decoded = Vec::new();
+                let decoder = ZlibDecoder::new(&payload[..]);
+                try!(decoder.take((max as u64).saturating_add(1)).read_to_end(&mut decoded));
+                if decoded.len() > max {
+                    return Err(message_too_large());
+                }
+                Ok(decoded)
+            }
+        }
+    }
+}
+
+/// The THeader state of a connection.
+///
+/// The `read_*` fields describe the last frame received. The protocol id,
+/// sequence id and transforms of a received frame are also used for the
+/// frames written after it, so a server answers in kind.
+#[derive(Clone, Debug, Default)]
+pub struct Header {
+    pub protocol_id: ProtocolId,
+    pub sequence_id: i32,
+    pub flags: u16,
+    pub transforms: Vec<Transform>,
+
+    /// Info headers of the last frame received.
+    pub read_headers: Headers,
+
+    /// Info headers to send with the next frame. Cleared once it is sent.
+    pub write_headers: Headers,
+}
+
+pub struct HeaderTransport<T> {
+    inner: T,
+    header: Header,
+    read_buf: Cursor<Vec<u8>>,
+    write_buf: Vec<u8>,
+    max_message_size: usize,
+}
+
+impl<T: Read + Write> HeaderTransport<T> {
+    pub fn new(inner: T) -> HeaderTransport<T> {
+        HeaderTransport::with_protocol(inner, ProtocolId::default())
+    }
+
+    pub fn with_protocol(inner: T, protocol_id: ProtocolId) -> HeaderTransport<T> {
+        let mut header = Header::default();
+        header.protocol_id = protocol_id;
+        HeaderTransport {
+            inner: inner,
+            header: header,
+            read_buf: Cursor::new(Vec::new()),
+            write_buf: Vec::new(),
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+        }
+    }
+
+    /// Rejects frames, and payloads once their transforms are reverted,
+    /// larger than `limits.max_message_size` with `LimitExceeded`.
+    pub fn with_limits(mut self, limits: Limits) -> HeaderTransport<T> {
+        self.max_message_size = limits.max_message_size;
+        self
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn has_buffered_data(&self) -> bool {
+        (self.read_buf.position() as usize) < self.read_buf.get_ref().len()
+    }
+
+    fn read_frame(&mut self) -> io::Result<()> {
+        let size = try!(self.inner.read_u32::<BigEndian>());
+        if size > MAX_FRAME_SIZE {
+            return Err(invalid_data("THeader frame too large"));
+        }
+        if size as u64 > self.max_message_size as u64 {
+            return Err(message_too_large());
+        }
+        // The buffer grows as data arrives, rather than trusting the size.
+        let mut frame = Vec::new();
+        try!((&mut self.inner).take(size as u64).read_to_end(&mut frame));
+        if frame.len() < size as usize {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "THeader frame truncated"));
+        }
+        let mut frame = Cursor::new(frame);
+
+        if try!(frame.read_u16::<BigEndian>()) != HEADER_MAGIC {
+            return Err(invalid_data("not a THeader frame"));
+        }
+        let flags = try!(frame.read_u16::<BigEndian>());
+        let sequence_id = try!(frame.read_i32::<BigEndian>());
+        let header_size = try!(frame.read_u16::<BigEndian>()) as u64 * 4;
+        let header_end = frame.position() + header_size;
+        if header_end > size as u64 {
+            return Err(invalid_data("THeader header exceeds the frame"));
+        }
+
+        let protocol_id = match ProtocolId::from_num(try!(read_varint(&mut frame))) {
+            Some(id) => id,
+            None => return Err(invalid_data("unsupported THeader protocol id")),
+        };
+        let mut transforms = Vec::new();
+        for _ in 0..try!(read_varint(&mut frame)) {
+            match Transform::from_num(try!(read_varint(&mut frame))) {
+                Some(transform) => transforms.push(transform),
+                None => return Err(invalid_data("unsupported THeader transform")),
+            }
+        }
+        let mut headers = Headers::new();
+        while frame.position() < header_end {
+            let info = try!(read_varint(&mut frame));
+            if info == INFO_KEYVALUE {
+                for _ in 0..try!(read_varint(&mut frame)) {
+                    let key = try!(read_varint_string(&mut frame, header_end));
+                    let value = try!(read_varint_string(&mut frame, header_end));
+                    headers.insert(key, value);
+                }
+            } else {
+                // Padding, or an info type we don't know how to skip.
+                break;
+            }
+        }
+        if frame.position() > header_end {
+            return Err(invalid_data("THeader header exceeds its size"));
+        }
+
+        let mut payload = frame.into_inner().split_off(header_end as usize);
+        for transform in transforms.iter().rev() {
+            payload = try!(transform.revert(payload, self.max_message_size));
+        }
+
+        self.header.protocol_id = protocol_id;
+        self.header.sequence_id = sequence_id;
+        self.header.flags = flags;
+        self.header.transforms = transforms;
+        self.header.read_headers = headers;
+        self.read_buf = Cursor::new(payload);
+        Ok(())
+    }
+
+    fn write_frame(&mut self) -> io::Result<()> {
+        let mut payload = mem::replace(&mut self.write_buf, Vec::new());
+        for transform in &self.header.transforms {
+            payload = try!(transform.apply(payload));
+        }
+
+        let mut header = Vec::new();
+        write_varint(&mut header, self.header.protocol_id as u32);
+        write_varint(&mut header, self.header.transforms.len() as u32);
+        for transform in &self.header.transforms {
+            write_varint(&mut header, *transform as u32);
+        }
+        let headers = mem::replace(&mut self.header.write_headers, Headers::new());
+        if !headers.is_empty() {
+            write_varint(&mut header, INFO_KEYVALUE);
+            write_varint(&mut header, headers.len() as u32);
+            for (key, value) in &headers {
+                write_varint_string(&mut header, key);
+                write_varint_string(&mut header, value);
+            }
+        }
+        while header.len() % 4 != 0 {
+            header.push(INFO_PADDING as u8);
+        }
+
+        let size = 10 + header.len() + payload.len();
+        if size > MAX_FRAME_SIZE as usize || header.len() / 4 > u16::max_value() as usize {
+            return Err(invalid_data("THeader frame too large"));
+        }
+        let mut frame = Vec::with_capacity(4 + size);
+        try!(frame.write_u32::<BigEndian>(size as u32));
+        try!(frame.write_u16::<BigEndian>(HEADER_MAGIC));
+        try!(frame.write_u16::<BigEndian>(self.header.flags));
+        try!(frame.write_i32::<BigEndian>(self.header.sequence_id));
+        try!(frame.write_u16::<BigEndian>((header.len() / 4) as u16));
+        frame.extend_from_slice(&header);
+        frame.extend_from_slice(&payload);
+        self.inner.write_all(&frame)
+    }
+}
+
+impl<T: Read + Write> Read for HeaderTransport<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.has_buffered_data() {
+            try!(self.read_frame());
+        }
+        self.read_buf.read(buf)
+    }
+}
+
+impl<T: Read + Write> Write for HeaderTransport<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_buf.write(buf)
+    }
+
+    /// Sends everything written since the last flush as one frame.
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.write_buf.is_empty() {
+            try!(self.write_frame());
+        }
+        self.inner.flush()
+    }
+}
+
+impl<T: Read + Write> Transport for HeaderTransport<T> {
+    fn header(&mut self) -> Option<&mut Header> {
+        Some(&mut self.header)
+    }
+
+    fn prepare_read(&mut self) -> io::Result<()> {
+        if self.has_buffered_data() {
+            Ok(())
+        } else {
+            self.read_frame()
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn message_too_large() -> io::Error {
+    io::Error::from(protocol::Error::LimitExceeded(Limit::MessageSize))
+}
+
+fn read_varint<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut result = 0u32;
+    for shift in 0..5 {
+        let byte = try!(reader.read_u8());
+        result |= ((byte & 0x7f) as u32) << (7 * shift);
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+    }
+    Err(invalid_data("THeader varint too long"))
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Reads a string which must end before `end`.
+fn read_varint_string(reader: &mut Cursor<Vec<u8>>, end: u64) -> io::Result<String> {
+    let len = try!(read_varint(reader));
+    if reader.position() + len as u64 > end {
+        return Err(invalid_data("THeader string exceeds the header"));
+    }
+    let bytes = try!(ReadPodExt::read_exact(reader, len as usize));
+    String::from_utf8(bytes).map_err(|_| invalid_data("THeader string is not valid UTF-8"))
+}
+
+fn write_varint_string(out: &mut Vec<u8>, value: &str) {
+    write_varint(out, value.len() as u32);
+    out.extend_from_slice(value.as_bytes());
+}
+
+struct Context {
+    request: Headers,
+    reply: Headers,
+}
+
+thread_local!(static CONTEXT: RefCell<Option<Context>> = RefCell::new(None));
+
+/// Restores the enclosing context, even if the handler panics.
+struct ContextGuard(Option<Option<Context>>);
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        if let Some(saved) = self.0.take() {
+            CONTEXT.with(|context| *context.borrow_mut() = saved);
+        }
+    }
+}
+
+/// Runs the handler `f` for a request received on `transport`, making the
+/// request headers available to it and queueing the reply headers it sets.
+///
+/// Called by generated processors; transports without headers run `f` as is.
+pub fn scope<T: Transport, R, F: FnOnce() -> R>(transport: &mut T, f: F) -> R {
+    let request = match transport.header() {
+        Some(header) => header.read_headers.clone(),
+        None => return f(),
+    };
+
+    let context = Context { request: request, reply: Headers::new() };
+    let saved = CONTEXT.with(|current| mem::replace(&mut *current.borrow_mut(), Some(context)));
+    let mut guard = ContextGuard(Some(saved));
+    let result = f();
+
+    let saved = guard.0.take().unwrap();
+    let context = CONTEXT.with(|current| mem::replace(&mut *current.borrow_mut(), saved));
+    if let (Some(context), Some(header)) = (context, transport.header()) {
+        header.write_headers.extend(context.reply);
+    }
+    result
+}
+
+/// The value of the header `key` sent with the request being handled.
+pub fn request_header(key: &str) -> Option<String> {
+    CONTEXT.with(|context| {
+        context.borrow().as_ref().and_then(|context| context.request.get(key).cloned())
+    })
+}
+
+/// All headers sent with the request being handled.
+pub fn request_headers() -> Headers {
+    CONTEXT.with(|context| {
+        context.borrow().as_ref().map(|context| context.request.clone()).unwrap_or_default()
+    })
+}
+
+/// Attach a header to the reply of the request being handled.
+///
+/// Returns `false` if no request is being handled or the transport it came
+/// from doesn't carry headers.
+pub fn set_reply_header<K: Into<String>, V: Into<String>>(key: K, value: V) -> bool {
+    CONTEXT.with(|context| match *context.borrow_mut() {
+        Some(ref mut context) => {
+            context.reply.insert(key.into(), value.into());
+            true
+        }
+        None => false,
+    })
+}
+
+#[cfg(test)]
+pub mod test;
@@ -0,0 +1,145 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::io::{Cursor, ErrorKind, Read, Write};
+
+use super::{HeaderTransport, ProtocolId, Transform};
+use protocol::{self, Limit, Limits};
+use transport::Transport;
+
+static FRAME: &'static [u8] = &[
+    0x00, 0x00, 0x00, 0x15,
+    0x0f, 0xff, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x05,
+    0x00, 0x02,
+    0x00, 0x00, 0x01, 0x01, 0x01, 0x6b, 0x01, 0x76,
+    0x61, 0x62, 0x63,
+];
+
+#[test]
+fn write_frame() {
+    let mut transport = HeaderTransport::new(Cursor::new(Vec::new()));
+    {
+        let header = transport.header().unwrap();
+        header.sequence_id = 5;
+        header.write_headers.insert(String::from("k"), String::from("v"));
+    }
+    transport.write_all(b"abc").unwrap();
+    transport.flush().unwrap();
+
+    assert_eq!(transport.get_ref().get_ref(), &FRAME.to_vec());
+    assert!(transport.header().unwrap().write_headers.is_empty());
+}
+
+#[test]
+fn flush_without_data_writes_nothing() {
+    let mut transport = HeaderTransport::new(Cursor::new(Vec::new()));
+    transport.flush().unwrap();
+    assert!(transport.get_ref().get_ref().is_empty());
+}
+
+#[test]
+fn read_frame() {
+    let mut transport = HeaderTransport::new(Cursor::new(FRAME.to_vec()));
+    let mut payload = [0; 3];
+    transport.read_exact(&mut payload).unwrap();
+    assert_eq!(&payload, b"abc");
+
+    let header = transport.header().unwrap();
+    assert_eq!(header.sequence_id, 5);
+    assert_eq!(header.protocol_id, ProtocolId::Binary);
+    assert_eq!(header.read_headers.get("k").map(|v| &v[..]), Some("v"));
+}
+
+#[test]
+fn zlib_round_trip() {
+    let mut writer = HeaderTransport::with_protocol(Cursor::new(Vec::new()), ProtocolId::Compact);
+    writer.header().unwrap().transforms.push(Transform::Zlib);
+    let payload = vec![0x2a; 1000];
+    writer.write_all(&payload).unwrap();
+    writer.flush().unwrap();
+    let written = writer.into_inner().into_inner();
+    assert!(written.len() < payload.len());
+
+    let mut reader = HeaderTransport::new(Cursor::new(written));
+    let mut read = vec![0; payload.len()];
+    reader.read_exact(&mut read).unwrap();
+    assert_eq!(read, payload);
+
+    let header = reader.header().unwrap();
+    assert_eq!(header.protocol_id, ProtocolId::Compact);
+    assert_eq!(header.transforms, vec![Transform::Zlib]);
+}
+
+#[test]
+fn read_bad_magic() {
+    let mut frame = FRAME.to_vec();
+    frame[4] = 0x80;
+    let mut transport = HeaderTransport::new(Cursor::new(frame));
+    let err = transport.prepare_read().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+fn assert_message_too_large(err: ::std::io::Error) {
+    match ::Error::from(err) {
+        ::Error::ProtocolError(protocol::Error::LimitExceeded(Limit::MessageSize)) => {}
+        err => panic!("unexpected error {:?}", err),
+    }
+}
+
+#[test]
+fn reject_frame_over_limit() {
+    let mut limits = Limits::default();
+    limits.max_message_size = FRAME.len() - 5;
+    let mut transport = HeaderTransport::new(Cursor::new(FRAME.to_vec())).with_limits(limits);
+    assert_message_too_large(transport.prepare_read().unwrap_err());
+}
+
+#[test]
+fn truncated_frame() {
+    // Claims the largest frame allowed, but ends right away.
+    let mut transport = HeaderTransport::new(Cursor::new(vec![0x06, 0x3f, 0xff, 0xff, 0x0f, 0xff]));
+    let err = transport.prepare_read().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn reject_header_string_beyond_header() {
+    let mut frame = FRAME.to_vec();
+    // The length of the key "k", now running past the end of the header.
+    frame[18] = 0x7f;
+    let mut transport = HeaderTransport::new(Cursor::new(frame));
+    let err = transport.prepare_read().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn reject_decompressed_payload_over_limit() {
+    let mut writer = HeaderTransport::new(Cursor::new(Vec::new()));
+    writer.header().unwrap().transforms.push(Transform::Zlib);
+    writer.write_all(&vec![0; 100_000]).unwrap();
+    writer.flush().unwrap();
+    let written = writer.into_inner().into_inner();
+
+    let mut limits = Limits::default();
+    limits.max_message_size = 10_000;
+    assert!(written.len() < limits.max_message_size);
+    let mut reader = HeaderTransport::new(Cursor::new(written)).with_limits(limits);
+    assert_message_too_large(reader.prepare_read().unwrap_err());
+}
@@ -0,0 +1,65 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::io::{self, Cursor, Read, Write};
+
+use transport::Transport;
+
+/// A transport reading from one in-memory buffer and writing to another.
+///
+/// Servers use it to process a request which has already been read from
+/// the connection, collecting the reply before sending it.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryTransport {
+    input: Cursor<Vec<u8>>,
+    output: Vec<u8>,
+}
+
+impl MemoryTransport {
+    pub fn new(input: Vec<u8>) -> MemoryTransport {
+        MemoryTransport { input: Cursor::new(input), output: Vec::new() }
+    }
+
+    /// Everything written so far.
+    pub fn written(&self) -> &[u8] {
+        &self.output
+    }
+
+    pub fn into_written(self) -> Vec<u8> {
+        self.output
+    }
+}
+
+impl Read for MemoryTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl Write for MemoryTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for MemoryTransport {}
@@ -19,12 +19,41 @@
 
 use std::io::{self, Read, Write};
 
+use self::header::Header;
+
 pub mod server;
+pub mod header;
+pub mod slice;
+pub mod memory;
+pub mod framed;
+
+pub trait Transport: Write + Read {
+    /// The THeader state of transports carrying per-message headers.
+    fn header(&mut self) -> Option<&mut Header> { None }
 
-pub trait Transport: Write + Read { }
+    /// Make the headers of the next incoming message available through
+    /// `header`, for transports that only read them on demand.
+    fn prepare_read(&mut self) -> io::Result<()> { Ok(()) }
+}
 
-impl<'t, T> Transport for &'t mut T where T: Transport {}
-impl<'t> Transport for &'t mut Transport {}
+/// Transports whose reading and writing ends can be used from different
+/// threads, as `client::Channel` does.
+pub trait Split: Transport + Sized {
+    type Reader: Transport + Send + 'static;
+    type Writer: Transport + Send + 'static;
+
+    fn split(self) -> io::Result<(Self::Reader, Self::Writer)>;
+}
+
+impl<'t, T> Transport for &'t mut T where T: Transport {
+    fn header(&mut self) -> Option<&mut Header> { (**self).header() }
+    fn prepare_read(&mut self) -> io::Result<()> { (**self).prepare_read() }
+}
+
+impl<'t> Transport for &'t mut Transport {
+    fn header(&mut self) -> Option<&mut Header> { (**self).header() }
+    fn prepare_read(&mut self) -> io::Result<()> { (**self).prepare_read() }
+}
 
 pub struct RwTransport<Rw>(pub Rw);
 
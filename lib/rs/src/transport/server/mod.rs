@@ -17,16 +17,61 @@
  * under the License.
  */
 
-use std::io;
-use std::net::{TcpListener, TcpStream};
-use super::Transport;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, TcpListener, TcpStream};
+use super::{Split, Transport};
 
 impl Transport for TcpStream {}
 
+impl Split for TcpStream {
+    type Reader = TcpStream;
+    type Writer = TcpWriter;
+
+    fn split(self) -> io::Result<(TcpStream, TcpWriter)> {
+        let reader = try!(self.try_clone());
+        Ok((reader, TcpWriter(self)))
+    }
+}
+
+/// The writing end of a split `TcpStream`.
+///
+/// Dropping it shuts the connection down, which also stops the reading end.
+pub struct TcpWriter(TcpStream);
+
+impl Read for TcpWriter {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Write for TcpWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl Transport for TcpWriter {}
+
+impl Drop for TcpWriter {
+    fn drop(&mut self) {
+        let _ = self.0.shutdown(Shutdown::Both);
+    }
+}
+
 pub trait TransportServer {
     type Transport: Transport;
 
     fn accept(&self) -> io::Result<Self::Transport>;
+
+    /// Makes an `accept` blocked on another thread return, so that it can
+    /// notice it should stop.
+    fn wake(&self) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Other, "waking up accept is not supported"))
+    }
 }
 
 impl TransportServer for TcpListener {
@@ -35,6 +80,20 @@ impl TransportServer for TcpListener {
     fn accept(&self) -> io::Result<TcpStream> {
         self.accept().map(|res| res.0)
     }
+
+    /// Connects to the listener, the accepted connection is for the caller
+    /// of `accept` to drop.
+    fn wake(&self) -> io::Result<()> {
+        let mut addr = try!(self.local_addr());
+        if addr.ip().is_unspecified() {
+            let loopback = match addr.ip() {
+                IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+                IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1)),
+            };
+            addr.set_ip(loopback);
+        }
+        TcpStream::connect(addr).map(|_| ())
+    }
 }
 
 impl<F, T> TransportServer for F
@@ -44,3 +103,27 @@ where F: Fn() -> io::Result<T>, T: Transport {
     fn accept(&self) -> io::Result<T> { self() }
 }
 
+
+/// An accepted transport which a server can wait on between requests, and
+/// close from another thread.
+pub trait Connection: Transport {
+    /// Blocks until the peer sends data, returning `false` if it closed the
+    /// connection instead. Doesn't consume anything.
+    fn wait_readable(&mut self) -> io::Result<bool>;
+
+    /// Returns a function closing the connection, which makes pending and
+    /// later reads and writes on it fail or return EOF.
+    fn closer(&self) -> io::Result<Box<Fn() + Send>>;
+}
+
+impl Connection for TcpStream {
+    fn wait_readable(&mut self) -> io::Result<bool> {
+        let mut buf = [0; 1];
+        self.peek(&mut buf).map(|n| n != 0)
+    }
+
+    fn closer(&self) -> io::Result<Box<Fn() + Send>> {
+        let stream = try!(self.try_clone());
+        Ok(Box::new(move || { let _ = stream.shutdown(Shutdown::Both); }))
+    }
+}
@@ -0,0 +1,71 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements. See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership. The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License. You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied. See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::io::{self, Read, Write};
+
+use transport::Transport;
+
+/// A read-only transport over an in-memory buffer.
+///
+/// Besides the usual reads, it can hand out sub-slices of the buffer, which
+/// protocols use to decode strings and binaries without copying them (see
+/// `protocol::DecodeBorrowed`).
+#[derive(Clone, Debug)]
+pub struct SliceTransport<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> SliceTransport<'a> {
+    pub fn new(buf: &'a [u8]) -> SliceTransport<'a> {
+        SliceTransport { buf: buf }
+    }
+
+    /// The bytes not read yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    /// Read the next `len` bytes, borrowing them from the buffer.
+    pub fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if len > self.buf.len() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "buffer too short"));
+        }
+        let (taken, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(taken)
+    }
+}
+
+impl<'a> Read for SliceTransport<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.buf.read(buf)
+    }
+}
+
+impl<'a> Write for SliceTransport<'a> {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, "SliceTransport is read-only"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> Transport for SliceTransport<'a> {}
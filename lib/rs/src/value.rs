@@ -0,0 +1,301 @@
+//! A dynamic representation of Thrift values, for working without generated types.
+
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+use protocol::{self, Decode, Encode, Limits, ThriftTyped, Type};
+use protocol::binary_protocol::BinaryProtocol;
+use protocol::helpers::typ;
+use transport::RwTransport;
+use transport::slice::SliceTransport;
+use {Error, Protocol, Result, Transport};
+
+/// Any Thrift value, as far as it can be told from the wire.
+///
+/// Strings and binaries share a wire type, so both are represented as
+/// `Binary`. Containers keep their declared element types, which are needed
+/// to encode them again when empty, and their elements in wire order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Byte(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    Double(f64),
+    Binary(Vec<u8>),
+    Struct(BTreeMap<i16, Value>),
+    List(Type, Vec<Value>),
+    Set(Type, Vec<Value>),
+    Map(Type, Type, Vec<(Value, Value)>),
+}
+
+impl Default for Value {
+    fn default() -> Value {
+        Value::Struct(BTreeMap::new())
+    }
+}
+
+impl Value {
+    /// Reads a value of type `typ`.
+    pub fn read<P, T>(protocol: &mut P, transport: &mut T, typ: Type) -> Result<Value>
+    where P: Protocol, T: Transport {
+        Ok(match typ {
+            Type::Bool => Value::Bool(try!(protocol.read_bool(transport))),
+            Type::Byte => Value::Byte(try!(protocol.read_byte(transport))),
+            Type::I16 => Value::I16(try!(protocol.read_i16(transport))),
+            Type::I32 => Value::I32(try!(protocol.read_i32(transport))),
+            Type::I64 => Value::I64(try!(protocol.read_i64(transport))),
+            Type::Double => Value::Double(try!(protocol.read_double(transport))),
+            Type::String => Value::Binary(try!(protocol.read_binary(transport))),
+            Type::Struct => {
+                let mut fields = BTreeMap::new();
+                try!(protocol.read_struct_begin(transport));
+                loop {
+                    let (_, typ, id) = try!(protocol.read_field_begin(transport));
+                    if typ == Type::Stop {
+                        break;
+                    }
+                    fields.insert(id, try!(Value::read(protocol, transport, typ)));
+                    try!(protocol.read_field_end(transport));
+                }
+                try!(protocol.read_struct_end(transport));
+                Value::Struct(fields)
+            }
+            Type::List => {
+                let (typ, len) = try!(protocol.read_list_begin(transport));
+                let elements = try!(read_elements(protocol, transport, typ, len));
+                try!(protocol.read_list_end(transport));
+                Value::List(typ, elements)
+            }
+            Type::Set => {
+                let (typ, len) = try!(protocol.read_set_begin(transport));
+                let elements = try!(read_elements(protocol, transport, typ, len));
+                try!(protocol.read_set_end(transport));
+                Value::Set(typ, elements)
+            }
+            Type::Map => {
+                let (ktyp, vtyp, len) = try!(protocol.read_map_begin(transport));
+                let len = try!(protocol.limits().container_len(len as i64));
+                let mut entries = Vec::new();
+                for _ in 0..len {
+                    let key = try!(Value::read(protocol, transport, ktyp));
+                    let value = try!(Value::read(protocol, transport, vtyp));
+                    entries.push((key, value));
+                }
+                try!(protocol.read_map_end(transport));
+                Value::Map(ktyp, vtyp, entries)
+            }
+            Type::Stop | Type::Void => {
+                return Err(Error::from(protocol::Error::ProtocolViolation))
+            }
+        })
+    }
+}
+
+/// Fields a struct didn't know about when it was decoded.
+///
+/// Structs declared with `unknown_fields = <name>` in `strukt!` keep them
+/// here and write them again when encoded, so that data passing through
+/// code built against an older IDL isn't lost. Known field ids that arrive
+/// with another type than expected are kept here as well, and written again
+/// unless the known field itself is encoded.
+///
+/// The fields are not kept as the bytes they were received as: they are
+/// decoded and stored re-encoded with the binary protocol, whatever protocol
+/// they were read with, so they can be written to any protocol.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UnknownFields {
+    fields: BTreeMap<i16, (Type, Vec<u8>)>,
+}
+
+impl UnknownFields {
+    /// Reads the value of field `id`, whose header has already been read.
+    pub fn read<P, T>(&mut self, protocol: &mut P, transport: &mut T, typ: Type, id: i16) -> Result<()>
+    where P: Protocol, T: Transport {
+        let value = try!(Value::read(protocol, transport, typ));
+        self.fields.insert(id, (typ, try!(to_bytes(&value))));
+        Ok(())
+    }
+
+    /// Writes all fields, including their headers.
+    pub fn write<P, T>(&self, protocol: &mut P, transport: &mut T) -> Result<()>
+    where P: Protocol, T: Transport {
+        self.write_except(protocol, transport, |_| false)
+    }
+
+    /// Writes all fields except those whose id `written` returns true for.
+    pub fn write_except<P, T, F>(&self, protocol: &mut P, transport: &mut T, written: F) -> Result<()>
+    where P: Protocol, T: Transport, F: Fn(i16) -> bool {
+        for (&id, &(typ, ref bytes)) in &self.fields {
+            if written(id) {
+                continue;
+            }
+            let value = try!(from_bytes(bytes, typ));
+            try!(protocol.write_field_begin(transport, &id.to_string(), typ, id));
+            try!(value.encode(protocol, transport));
+            try!(protocol.write_field_end(transport));
+        }
+        Ok(())
+    }
+
+    /// The value of field `id`, or `None` if there is no such field.
+    pub fn get(&self, id: i16) -> Result<Option<Value>> {
+        match self.fields.get(&id) {
+            Some(&(typ, ref bytes)) => from_bytes(bytes, typ).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The type of field `id` and its value re-encoded with the binary
+    /// protocol. These are the bytes it was received as only if it was read
+    /// with the binary protocol.
+    pub fn binary_bytes(&self, id: i16) -> Option<(Type, &[u8])> {
+        self.fields.get(&id).map(|&(typ, ref bytes)| (typ, &bytes[..]))
+    }
+
+    pub fn ids<'a>(&'a self) -> impl Iterator<Item=i16> + 'a {
+        self.fields.keys().cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.fields.clear()
+    }
+}
+
+/// Converts any encodable value into a `Value`.
+pub fn to_value<E: Encode>(value: &E) -> Result<Value> {
+    from_bytes(&try!(to_bytes(value)), value.typ())
+}
+
+/// Converts a `Value` into a `D`, failing if its type doesn't fit.
+pub fn from_value<D: Decode>(value: &Value) -> Result<D> {
+    if value.typ() != typ::<D>() {
+        return Err(Error::from(protocol::Error::ProtocolViolation));
+    }
+    let mut transport = RwTransport(Cursor::new(try!(to_bytes(value))));
+    let mut decoded = D::default();
+    try!(decoded.decode(&mut in_memory(), &mut transport));
+    Ok(decoded)
+}
+
+fn to_bytes<E: Encode>(value: &E) -> Result<Vec<u8>> {
+    let mut transport = RwTransport(Cursor::new(Vec::new()));
+    try!(value.encode(&mut in_memory(), &mut transport));
+    Ok(transport.0.into_inner())
+}
+
+fn from_bytes(bytes: &[u8], typ: Type) -> Result<Value> {
+    Value::read(&mut in_memory(), &mut SliceTransport::new(bytes), typ)
+}
+
+/// Data converted in memory is trusted, so no limits apply.
+fn in_memory() -> BinaryProtocol {
+    BinaryProtocol::default().with_limits(Limits::unlimited())
+}
+
+fn read_elements<P, T>(protocol: &mut P, transport: &mut T, typ: Type, len: i32) -> Result<Vec<Value>>
+where P: Protocol, T: Transport {
+    let len = try!(protocol.limits().container_len(len as i64));
+    let mut elements = Vec::new();
+    for _ in 0..len {
+        elements.push(try!(Value::read(protocol, transport, typ)));
+    }
+    Ok(elements)
+}
+
+fn encode_elements<P, T>(elements: &[Value], typ: Type, protocol: &mut P, transport: &mut T) -> Result<()>
+where P: Protocol, T: Transport {
+    for el in elements {
+        if el.typ() != typ {
+            return Err(Error::from(protocol::Error::ProtocolViolation));
+        }
+        try!(el.encode(protocol, transport));
+    }
+    Ok(())
+}
+
+impl ThriftTyped for Value {
+    fn typ(&self) -> Type {
+        match *self {
+            Value::Bool(_) => Type::Bool,
+            Value::Byte(_) => Type::Byte,
+            Value::I16(_) => Type::I16,
+            Value::I32(_) => Type::I32,
+            Value::I64(_) => Type::I64,
+            Value::Double(_) => Type::Double,
+            Value::Binary(_) => Type::String,
+            Value::Struct(_) => Type::Struct,
+            Value::List(..) => Type::List,
+            Value::Set(..) => Type::Set,
+            Value::Map(..) => Type::Map,
+        }
+    }
+}
+
+impl Encode for Value {
+    fn encode<P, T>(&self, protocol: &mut P, transport: &mut T) -> Result<()>
+    where P: Protocol, T: Transport {
+        match *self {
+            Value::Bool(v) => try!(protocol.write_bool(transport, v)),
+            Value::Byte(v) => try!(protocol.write_byte(transport, v)),
+            Value::I16(v) => try!(protocol.write_i16(transport, v)),
+            Value::I32(v) => try!(protocol.write_i32(transport, v)),
+            Value::I64(v) => try!(protocol.write_i64(transport, v)),
+            Value::Double(v) => try!(protocol.write_double(transport, v)),
+            Value::Binary(ref v) => try!(protocol.write_binary(transport, v)),
+            Value::Struct(ref fields) => {
+                try!(protocol.write_struct_begin(transport, ""));
+                for (&id, value) in fields {
+                    // Field names aren't known, the id is the best stand-in.
+                    try!(protocol.write_field_begin(transport, &id.to_string(), value.typ(), id));
+                    try!(value.encode(protocol, transport));
+                    try!(protocol.write_field_end(transport));
+                }
+                try!(protocol.write_field_stop(transport));
+                try!(protocol.write_struct_end(transport));
+            }
+            Value::List(typ, ref elements) => {
+                try!(protocol.write_list_begin(transport, typ, elements.len()));
+                try!(encode_elements(elements, typ, protocol, transport));
+                try!(protocol.write_list_end(transport));
+            }
+            Value::Set(typ, ref elements) => {
+                try!(protocol.write_set_begin(transport, typ, elements.len()));
+                try!(encode_elements(elements, typ, protocol, transport));
+                try!(protocol.write_set_end(transport));
+            }
+            Value::Map(ktyp, vtyp, ref entries) => {
+                try!(protocol.write_map_begin(transport, ktyp, vtyp, entries.len()));
+                for &(ref key, ref value) in entries {
+                    if key.typ() != ktyp || value.typ() != vtyp {
+                        return Err(Error::from(protocol::Error::ProtocolViolation));
+                    }
+                    try!(key.encode(protocol, transport));
+                    try!(value.encode(protocol, transport));
+                }
+                try!(protocol.write_map_end(transport));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads a value of the type `self` currently has, so a `Value::default()`
+/// reads a struct. Set `self` to e.g. an empty `Value::List` to read a list.
+impl Decode for Value {
+    fn decode<P, T>(&mut self, protocol: &mut P, transport: &mut T) -> Result<()>
+    where P: Protocol, T: Transport {
+        let typ = self.typ();
+        *self = try!(Value::read(protocol, transport, typ));
+        Ok(())
+    }
+}
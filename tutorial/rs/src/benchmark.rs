@@ -44,7 +44,7 @@ pub fn main() {
     };
 
     let mut client = tutorial::CalculatorClient::new(
-        BinaryProtocol, RwTransport(BufStream::new(TcpStream::connect("127.0.0.1:9090").unwrap())));
+        BinaryProtocol::default(), RwTransport(BufStream::new(TcpStream::connect("127.0.0.1:9090").unwrap())));
 
     println!("Rust Thrift benchmark");
     println!("Running {} iterations", iterations);
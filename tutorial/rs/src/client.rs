@@ -31,7 +31,7 @@ mod shared;
 
 pub fn main() {
     let stream = RwTransport(BufStream::new(TcpStream::connect("127.0.0.1:9090").unwrap()));
-    let mut client = tutorial::CalculatorClient::new(BinaryProtocol, stream);
+    let mut client = tutorial::CalculatorClient::new(BinaryProtocol::default(), stream);
 
     // Ping
     client.ping().unwrap();
@@ -70,20 +70,20 @@ fn main() {
         let receiver = Handler { sender: receiver_tx };
 
         let mut source_processor = SharedServiceProcessor::new(source);
-        source_processor.proxy(SimpleProxy::new(|| BinaryProtocol,
+        source_processor.proxy(SimpleProxy::new(|| BinaryProtocol::default(),
                                                 move || Ok(RwTransport(try!(TcpStream::connect(receiver_addr))))));
 
         let mut proxy_server = LimitedServer {
             limit: requests,
             processor: source_processor,
-            protocols: || BinaryProtocol,
+            protocols: || BinaryProtocol::default(),
             transports: BufferServer(TcpListener::bind(proxy_addr).unwrap())
         };
 
         let mut receiver_server = LimitedServer {
             limit: requests,
             processor: SharedServiceProcessor::new(receiver),
-            protocols: || BinaryProtocol,
+            protocols: || BinaryProtocol::default(),
             transports: BufferServer(TcpListener::bind(receiver_addr).unwrap())
         };
 
@@ -99,7 +99,7 @@ fn main() {
 
     let client_guard = thread::spawn(move || {
         let stream = RwTransport(BufStream::new(TcpStream::connect(proxy_addr).unwrap()));
-        let mut client = SharedServiceClient::new(BinaryProtocol, stream);
+        let mut client = SharedServiceClient::new(BinaryProtocol::default(), stream);
 
         for i in 0..requests {
             client.getStruct(i).unwrap();
@@ -97,7 +97,7 @@ pub fn main() {
     let processor = CalculatorProcessor::new(&handler, &handler);
 
     let server_transport = BufferServer(TcpListener::bind("127.0.0.1:9090").unwrap());
-    let mut server = SimpleServer::new(processor, server_transport, || BinaryProtocol);
+    let mut server = SimpleServer::new(processor, server_transport, || BinaryProtocol::default());
 
     println!("Starting the server...");
     server.serve();